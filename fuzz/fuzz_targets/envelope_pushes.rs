@@ -0,0 +1,33 @@
+//! Parses an envelope made of arbitrary pushes (i.e.: arbitrary fields and body chunks)
+#![no_main]
+
+use bitcoin::{
+    opcodes,
+    script::{Builder, PushBytesBuf},
+};
+use brc20::ord::envelope::{ParsedEnvelope, PROTOCOL_ID};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|pushes: Vec<Vec<u8>>| {
+    let mut builder = Builder::new()
+        .push_opcode(opcodes::OP_FALSE)
+        .push_opcode(opcodes::all::OP_IF)
+        .push_slice(PROTOCOL_ID);
+    for push in &pushes {
+        let Ok(push) = PushBytesBuf::try_from(push.clone()) else {
+            return;
+        };
+        builder = builder.push_slice(push);
+    }
+    let tapscript = builder.push_opcode(opcodes::all::OP_ENDIF).into_script();
+
+    let envelopes = ParsedEnvelope::from_tapscript(&tapscript, 0).unwrap();
+    assert_eq!(envelopes.len(), 1);
+
+    let body = pushes
+        .iter()
+        .enumerate()
+        .position(|(i, push)| i % 2 == 0 && push.is_empty())
+        .map(|i| pushes[i + 1..].concat());
+    assert_eq!(envelopes[0].payload.body, body);
+});
@@ -0,0 +1,20 @@
+//! Parses arbitrary bytes as the tapscript of an input
+#![no_main]
+
+use bitcoin::Script;
+use brc20::ord::envelope::ParsedEnvelope;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(envelopes) = ParsedEnvelope::from_tapscript(Script::from_bytes(data), 0) else {
+        return;
+    };
+
+    for envelope in envelopes {
+        envelope.payload.content_type();
+        envelope.payload.content_encoding();
+        envelope.payload.metaprotocol();
+        envelope.payload.parent();
+        envelope.payload.pointer();
+    }
+});
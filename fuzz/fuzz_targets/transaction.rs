@@ -0,0 +1,18 @@
+//! Parses the envelopes of arbitrary consensus-encoded transactions
+#![no_main]
+
+use bitcoin::{consensus::deserialize, Transaction};
+use brc20::ord::envelope::ParsedEnvelope;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(tx) = deserialize::<Transaction>(data) else {
+        return;
+    };
+
+    for envelope in ParsedEnvelope::from_transaction(&tx) {
+        assert!((envelope.input as usize) < tx.input.len());
+        envelope.payload.parent();
+        envelope.payload.pointer();
+    }
+});
@@ -1,36 +1,88 @@
+CREATE TABLE IF NOT EXISTS tokens (
+    "id" VARCHAR(64) NOT NULL PRIMARY KEY,
+    "symbol" VARCHAR(64) NOT NULL,
+    "max_supply" NUMERIC NOT NULL,
+    "mint_limit" NUMERIC NOT NULL,
+    "decimals" INT NOT NULL,
+    "deployer" VARCHAR(128) NOT NULL,
+    -- ID of the deploy inscription, i.e.: "{TXID}i{INDEX}"
+    "deployment" VARCHAR(80) NOT NULL,
+    "minted" NUMERIC NOT NULL,
+    "burned" NUMERIC NOT NULL,
+    "holders" BIGINT NOT NULL,
+    "mint_count" NUMERIC NOT NULL,
+    "transfer_count" NUMERIC NOT NULL,
+    "completed_at_block" BIGINT
+);
 
-CREATE TABLE IF NOT EXISTS approvals (
-    "evt_tx_hash" VARCHAR(64),
-    "evt_index" INT,
-    "evt_block_time" TIMESTAMP,
-    "evt_block_number" DECIMAL,
-    "approved" VARCHAR(40),
-    "owner" VARCHAR(40),
-    "token_id" DECIMAL
+CREATE TABLE IF NOT EXISTS account_balances (
+    "id" VARCHAR(256) NOT NULL PRIMARY KEY,
+    "account" VARCHAR(128) NOT NULL,
+    "token" VARCHAR(64) NOT NULL,
+    "balance" NUMERIC NOT NULL,
+    "transferable" NUMERIC NOT NULL
 );
-CREATE TABLE IF NOT EXISTS approval_for_alls (
-    "evt_tx_hash" VARCHAR(64),
-    "evt_index" INT,
-    "evt_block_time" TIMESTAMP,
-    "evt_block_number" DECIMAL,
-    "approved" BOOL,
-    "operator" VARCHAR(40),
-    "owner" VARCHAR(40)
+
+CREATE TABLE IF NOT EXISTS balance_changes (
+    "id" VARCHAR(512) NOT NULL PRIMARY KEY,
+    "account" VARCHAR(128) NOT NULL,
+    "token" VARCHAR(64) NOT NULL,
+    "block_number" BIGINT NOT NULL,
+    "ordinal" NUMERIC NOT NULL,
+    -- One of "mint", "inscribe", "execute", "burn" or "fee-return"
+    "cause" VARCHAR(16) NOT NULL,
+    -- Either "balance" or "transferable"
+    "kind" VARCHAR(16) NOT NULL,
+    "amount" NUMERIC NOT NULL,
+    "value" NUMERIC NOT NULL
 );
-CREATE TABLE IF NOT EXISTS ownership_transferreds (
-    "evt_tx_hash" VARCHAR(64),
-    "evt_index" INT,
-    "evt_block_time" TIMESTAMP,
-    "evt_block_number" DECIMAL,
-    "new_owner" VARCHAR(40),
-    "previous_owner" VARCHAR(40)
+
+CREATE INDEX IF NOT EXISTS balance_changes_account_token_block_idx
+    ON balance_changes ("account", "token", "block_number");
+
+CREATE TABLE IF NOT EXISTS inscribe_transfers (
+    -- Inscription ID, i.e.: "{TXID}i{INDEX}"
+    "id" VARCHAR(80) NOT NULL PRIMARY KEY,
+    "token" VARCHAR(64) NOT NULL,
+    "from" VARCHAR(128) NOT NULL,
+    "to" VARCHAR(128),
+    "amount" NUMERIC NOT NULL,
+    -- One of "inscribed", "executed", "sent_to_fee", "burned" or "invalid"
+    "status" VARCHAR(16) NOT NULL,
+    -- Outpoint holding the inscription, i.e.: "{TXID}:{VOUT}"
+    "utxo" VARCHAR(80) NOT NULL,
+    "block_number" BIGINT NOT NULL,
+    "execution_tx" VARCHAR(64)
 );
+
+CREATE INDEX IF NOT EXISTS inscribe_transfers_from_status_idx
+    ON inscribe_transfers ("from", "status");
+
+CREATE TABLE IF NOT EXISTS deploys (
+    -- Inscription ID, i.e.: "{TXID}i{INDEX}"
+    "id" VARCHAR(80) NOT NULL PRIMARY KEY,
+    "token" VARCHAR(64) NOT NULL,
+    "deployer" VARCHAR(128) NOT NULL,
+    "block_number" BIGINT NOT NULL,
+    "timestamp" BIGINT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS mints (
+    -- Inscription ID, i.e.: "{TXID}i{INDEX}"
+    "id" VARCHAR(80) NOT NULL PRIMARY KEY,
+    "token" VARCHAR(64) NOT NULL,
+    "to" VARCHAR(128) NOT NULL,
+    "amount" NUMERIC NOT NULL,
+    "block_number" BIGINT NOT NULL
+);
+
 CREATE TABLE IF NOT EXISTS transfers (
-    "evt_tx_hash" VARCHAR(64),
-    "evt_index" INT,
-    "evt_block_time" TIMESTAMP,
-    "evt_block_number" DECIMAL,
-    "from" VARCHAR(40),
-    "to" VARCHAR(40),
-    "token_id" DECIMAL
-);
\ No newline at end of file
+    -- Inscription ID, i.e.: "{TXID}i{INDEX}"
+    "id" VARCHAR(80) NOT NULL PRIMARY KEY,
+    "token" VARCHAR(64) NOT NULL,
+    "from" VARCHAR(128) NOT NULL,
+    "to" VARCHAR(128) NOT NULL,
+    "amount" NUMERIC NOT NULL,
+    "txid" VARCHAR(64) NOT NULL,
+    "block_number" BIGINT NOT NULL
+);
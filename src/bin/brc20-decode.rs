@@ -0,0 +1,109 @@
+//! Decodes the inscriptions revealed by raw transactions, along with their BRC-20 operations,
+//! the location of their inscribed sat and the reason why they are not indexed as BRC-20
+//! operations, if any (see `brc20::decode`). Prints one JSON object per transaction.
+
+use std::{
+    env, fs,
+    io::{self, BufRead},
+    path::Path,
+    process,
+};
+
+use brc20::{decode::decode_raw_transaction, rules::RuleSet};
+
+const USAGE: &str = "\
+Usage: brc20-decode [--input-values SATS,...] [--height HEIGHT | --rules NAME]
+                    <RAW_TX_HEX | FILE | ->...
+
+Arguments:
+  RAW_TX_HEX  Hex-encoded raw transaction (e.g.: from `bitcoin-cli getrawtransaction <txid>`)
+  FILE        File with one hex-encoded raw transaction per line
+  -           Reads hex-encoded raw transactions from stdin, one per line
+
+Options:
+  --input-values SATS,...  Values of the outputs spent by the inputs of the transaction, in
+                           order, used to locate inscriptions revealed after the first input
+                           (only with a single transaction)
+  --height HEIGHT          Checks operations with the rules of the block at that height (default:
+                           the latest rules)
+  --rules NAME             Checks operations with the named rule set (genesis or self_mint)
+  -h, --help               Prints this message";
+
+fn usage_error(msg: &str) -> ! {
+    eprintln!("error: {}\n\n{}", msg, USAGE);
+    process::exit(2);
+}
+
+fn lines(reader: impl BufRead) -> io::Result<Vec<String>> {
+    reader
+        .lines()
+        .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .collect()
+}
+
+fn main() -> io::Result<()> {
+    let mut input_sats = vec![];
+    let mut rule_set = RuleSet::latest();
+    let mut txs = vec![];
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            "--input-values" => {
+                let values = args
+                    .next()
+                    .unwrap_or_else(|| usage_error("missing value for --input-values"));
+                input_sats = values
+                    .split(',')
+                    .map(|value| value.trim().parse::<u64>())
+                    .collect::<Result<_, _>>()
+                    .unwrap_or_else(|err| usage_error(&format!("invalid input value: {}", err)));
+            }
+            "--height" => {
+                let height = args
+                    .next()
+                    .and_then(|height| height.parse().ok())
+                    .unwrap_or_else(|| usage_error("invalid height"));
+                rule_set = RuleSet::at(height);
+            }
+            "--rules" => {
+                rule_set = args
+                    .next()
+                    .and_then(|name| RuleSet::named(&name))
+                    .unwrap_or_else(|| usage_error("unknown rule set"));
+            }
+            "-" => txs.extend(lines(io::stdin().lock())?),
+            _ if Path::new(&arg).is_file() => {
+                txs.extend(lines(io::BufReader::new(fs::File::open(&arg)?))?)
+            }
+            _ => txs.push(arg),
+        }
+    }
+
+    if txs.is_empty() {
+        usage_error("no transaction given");
+    }
+    if !input_sats.is_empty() && txs.len() > 1 {
+        usage_error("--input-values can only be used with a single transaction");
+    }
+
+    let mut failed = false;
+    for (i, tx) in txs.iter().enumerate() {
+        match decode_raw_transaction(tx, &input_sats, rule_set) {
+            Ok(decoded) => println!("{}", serde_json::to_string(&decoded)?),
+            Err(err) => {
+                eprintln!("transaction #{}: {}", i + 1, err);
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        process::exit(1);
+    }
+    Ok(())
+}
@@ -0,0 +1,135 @@
+//! Re-indexes the blocks of a Bitcoin Core node's blocks directory (see `brc20::bitcoind`) by
+//! running the module DAG natively with in-memory stores (see `brc20::replay`), without any
+//! network access. Prints one summary line per block.
+
+use std::{env, io, path::PathBuf, process};
+
+use bitcoin::Network;
+use brc20::{bitcoind::BlocksDir, replay::Replay, snapshot::Snapshot};
+
+const USAGE: &str = "\
+Usage: brc20-reindex [--network NETWORK] [--from HEIGHT] [--to HEIGHT] [--resume SNAPSHOT]
+                     [--snapshot PATH] <BLOCKS_DIR>
+
+Arguments:
+  BLOCKS_DIR  Blocks directory of a Bitcoin Core node (e.g.: ~/.bitcoin/blocks), which must not be
+              pruned
+
+Options:
+  --network NETWORK  bitcoin (default), testnet, signet or regtest
+  --from HEIGHT      First block to print (every block is still processed from the genesis block,
+                     or from the snapshot's)
+  --to HEIGHT        Last block to process (default: the tip of the best chain)
+  --resume SNAPSHOT  Restores the state of a snapshot and processes the blocks after it
+  --snapshot PATH    Writes a snapshot of the state after the last block processed
+  -h, --help         Prints this message";
+
+fn usage_error(msg: &str) -> ! {
+    eprintln!("error: {}\n\n{}", msg, USAGE);
+    process::exit(2);
+}
+
+fn height(value: Option<String>) -> u64 {
+    value
+        .and_then(|value| value.parse().ok())
+        .unwrap_or_else(|| usage_error("invalid height"))
+}
+
+fn path_arg(value: Option<String>) -> String {
+    value.unwrap_or_else(|| usage_error("missing path"))
+}
+
+fn main() -> io::Result<()> {
+    let mut network = Network::Bitcoin;
+    let (mut from, mut to) = (0, None);
+    let (mut resume, mut snapshot) = (None, None);
+    let mut path = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            "--network" => {
+                network = args
+                    .next()
+                    .and_then(|network| network.parse().ok())
+                    .unwrap_or_else(|| usage_error("invalid network"));
+            }
+            "--from" => from = height(args.next()),
+            "--to" => to = Some(height(args.next())),
+            "--resume" => resume = Some(PathBuf::from(path_arg(args.next()))),
+            "--snapshot" => snapshot = Some(PathBuf::from(path_arg(args.next()))),
+            _ if path.is_none() => path = Some(arg),
+            _ => usage_error(&format!("unexpected argument: {}", arg)),
+        }
+    }
+
+    let path = path.unwrap_or_else(|| usage_error("no blocks directory given"));
+    let blocks_dir = BlocksDir::open(path, network)?;
+    let tip = match (blocks_dir.tip_height(), to) {
+        (Some(tip), Some(to)) => tip.min(to),
+        (Some(tip), None) => tip,
+        (None, _) => usage_error("the blocks directory has no blocks"),
+    };
+
+    // Stores are kept in memory (including the value of every unspent output), so a full
+    // re-index of mainnet requires a lot of it. The value of spent outputs is taken from undo
+    // data, as Firehose does, rather than relying on `store_utxo_values` only.
+    let replay = Replay::default();
+    let start = match resume {
+        Some(resume) => {
+            let snapshot = Snapshot::read(&resume)?;
+            let hash = blocks_dir.block(snapshot.height)?.block.block_hash();
+            if hash.to_string() != snapshot.block_hash {
+                usage_error(&format!(
+                    "the snapshot's block {} is not in the best chain",
+                    snapshot.block_hash
+                ));
+            }
+            snapshot
+                .restore(&replay)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            snapshot.height + 1
+        }
+        None => 0,
+    };
+    if start > tip {
+        usage_error("the snapshot is past the last block to process");
+    }
+
+    let mut last_hash = None;
+    for height in start..=tip {
+        let block = blocks_dir.block(height)?;
+        replay.set_utxo_values(
+            block
+                .prevouts()
+                .iter()
+                .map(|(utxo, sats)| (utxo.as_str(), *sats)),
+        );
+        let outputs = replay.process(block.to_proto());
+
+        if height >= from {
+            let events = &outputs.resolved_events;
+            println!(
+                "{} {} inscriptions={} deploys={} mints={} inscribed_transfers={} executed_transfers={}",
+                height,
+                block.block.block_hash(),
+                outputs.inscriptions.inscriptions.len(),
+                events.deploys.len(),
+                events.mints.len(),
+                events.inscribed_transfers.len(),
+                events.executed_transfers.len(),
+            );
+        }
+        last_hash = Some(block.block.block_hash());
+    }
+
+    if let (Some(path), Some(hash)) = (snapshot, last_hash) {
+        Snapshot::export(&replay, tip, &hash.to_string()).write(&path)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,722 @@
+//! Reads the blocks of the best chain from the blocks directory of a Bitcoin Core node
+//! (`blk*.dat` files), along with the outputs spent by their transactions, taken from their undo
+//! data (`rev*.dat` files), so that the module DAG can be run locally (see the `brc20-reindex`
+//! binary).
+//!
+//! Both kinds of files are sequences of records made of the network's magic bytes, the size of
+//! the record's data and its data: a consensus encoded block in `blk*.dat` files, and the undo
+//! data of a block (i.e.: the outputs spent by its transactions) followed by a checksum in
+//! `rev*.dat` files. The undo data of a block is always in the `rev*.dat` file numbered like the
+//! `blk*.dat` file holding the block, but blocks are stored in the order in which they were
+//! received, which is neither the order of the chain nor the order of their undo data. Since
+//! Bitcoin Core 28.0, both kinds of files are XORed with the key of `xor.dat`.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom},
+    path::PathBuf,
+};
+
+use bitcoin::{
+    block::Header,
+    consensus::deserialize,
+    hashes::{sha256d, Hash},
+    opcodes, script, secp256k1, Amount, Block, BlockHash, Network, PubkeyHash, PublicKey,
+    ScriptBuf, ScriptHash, TxOut,
+};
+
+use crate::{btc_utils::block_to_proto, pb::sf::bitcoin::r#type::v1 as btc};
+
+/// Size of the scripts above which Bitcoin Core does not store scripts in its undo data
+const MAX_SCRIPT_SIZE: u64 = 10_000;
+
+/// Number of special (i.e.: compressed) script types in undo data
+const SPECIAL_SCRIPTS: u64 = 6;
+
+/// Location of the data of a record
+#[derive(Debug, Clone, Copy)]
+struct Record {
+    file: u32,
+    offset: u64,
+    size: u32,
+}
+
+/// Undo data of the blocks of a `rev*.dat` file, along with the number of transactions and
+/// inputs they undo, which are used to match blocks with their undo data
+struct UndoFile {
+    file: u32,
+    data: Vec<u8>,
+    records: Vec<(usize, usize, (usize, usize))>,
+}
+
+/// Blocks directory of a Bitcoin Core node (e.g.: `~/.bitcoin/blocks`)
+pub struct BlocksDir {
+    path: PathBuf,
+    magic: [u8; 4],
+    xor_key: [u8; 8],
+    /// Records of the blocks of the best chain, by height
+    chain: Vec<Record>,
+    /// Last `rev*.dat` file read
+    undo_file: RefCell<Option<UndoFile>>,
+}
+
+/// Block of the best chain, along with the outputs spent by its transactions
+#[derive(Debug)]
+pub struct ChainBlock {
+    pub height: u64,
+    pub block: Block,
+    /// Outputs spent by the inputs of each transaction but the coinbase transaction
+    pub spent_outputs: Vec<Vec<TxOut>>,
+}
+
+impl ChainBlock {
+    /// Converts the block to its Firehose representation
+    pub fn to_proto(&self) -> btc::Block {
+        block_to_proto(&self.block, self.height as i64)
+    }
+
+    /// Returns the value of the outputs spent by the block, by outpoint (i.e.: `{txid}:{vout}`)
+    pub fn prevouts(&self) -> Vec<(String, u64)> {
+        self.block
+            .txdata
+            .iter()
+            .skip(1)
+            .zip(&self.spent_outputs)
+            .flat_map(|(tx, spent_outputs)| tx.input.iter().zip(spent_outputs))
+            .map(|(input, output)| (input.previous_output.to_string(), output.value.to_sat()))
+            .collect()
+    }
+}
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+impl BlocksDir {
+    /// Indexes the blocks of the directory and selects the chain with the most work
+    pub fn open(path: impl Into<PathBuf>, network: Network) -> io::Result<Self> {
+        let path = path.into();
+        if !path.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} is not a directory", path.display()),
+            ));
+        }
+        let xor_key = match fs::read(path.join("xor.dat")) {
+            Ok(key) => key
+                .try_into()
+                .map_err(|_| invalid_data("xor.dat must be 8 bytes long"))?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => [0; 8],
+            Err(err) => return Err(err),
+        };
+
+        let mut blocks_dir = BlocksDir {
+            path,
+            magic: network.magic().to_bytes(),
+            xor_key,
+            chain: vec![],
+            undo_file: RefCell::new(None),
+        };
+        blocks_dir.chain = blocks_dir.best_chain(blocks_dir.headers()?)?;
+
+        Ok(blocks_dir)
+    }
+
+    /// Height of the tip of the best chain
+    pub fn tip_height(&self) -> Option<u64> {
+        (self.chain.len() as u64).checked_sub(1)
+    }
+
+    /// Reads the block of the best chain at the given height, along with its undo data
+    pub fn block(&self, height: u64) -> io::Result<ChainBlock> {
+        let record = *self
+            .chain
+            .get(height as usize)
+            .ok_or_else(|| invalid_data(format!("no block at height {}", height)))?;
+
+        let mut file = File::open(self.file_path("blk", record.file))?;
+        let data = self.read_at(&mut file, record.offset, record.size as usize)?;
+        let block: Block = deserialize(&data).map_err(|err| invalid_data(err.to_string()))?;
+
+        // The genesis block has no undo data
+        let spent_outputs = if height == 0 {
+            vec![]
+        } else {
+            self.undo(record.file, &block)?
+        };
+
+        Ok(ChainBlock {
+            height,
+            block,
+            spent_outputs,
+        })
+    }
+
+    fn file_path(&self, prefix: &str, file: u32) -> PathBuf {
+        self.path.join(format!("{}{:05}.dat", prefix, file))
+    }
+
+    fn read_at(&self, file: &mut File, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+        let mut data = vec![0; len];
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut data)?;
+        self.unxor(offset, &mut data);
+        Ok(data)
+    }
+
+    fn unxor(&self, offset: u64, data: &mut [u8]) {
+        if self.xor_key == [0; 8] {
+            return;
+        }
+
+        for (i, byte) in data.iter_mut().enumerate() {
+            *byte ^= self.xor_key[((offset + i as u64) % 8) as usize];
+        }
+    }
+
+    /// Reads the header of every block of the `blk*.dat` files
+    fn headers(&self) -> io::Result<Vec<(Header, Record)>> {
+        let mut headers = vec![];
+
+        for file_number in 0.. {
+            let mut file = match File::open(self.file_path("blk", file_number)) {
+                Ok(file) => file,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => break,
+                Err(err) => return Err(err),
+            };
+            let len = file.metadata()?.len();
+
+            // Files are preallocated, so records are followed by zeros
+            let mut offset = 0;
+            while offset + 8 + 80 <= len {
+                let prefix = self.read_at(&mut file, offset, 8)?;
+                if prefix[..4] != self.magic {
+                    break;
+                }
+                let size = u32::from_le_bytes(prefix[4..].try_into().unwrap());
+
+                let header = self.read_at(&mut file, offset + 8, 80)?;
+                let header: Header =
+                    deserialize(&header).map_err(|err| invalid_data(err.to_string()))?;
+                headers.push((
+                    header,
+                    Record {
+                        file: file_number,
+                        offset: offset + 8,
+                        size,
+                    },
+                ));
+
+                offset += 8 + size as u64;
+            }
+        }
+
+        Ok(headers)
+    }
+
+    /// Returns the records of the blocks of the chain with the most work, by height
+    fn best_chain(&self, headers: Vec<(Header, Record)>) -> io::Result<Vec<Record>> {
+        let mut blocks = HashMap::new();
+        let mut children: HashMap<BlockHash, Vec<BlockHash>> = HashMap::new();
+        for (header, record) in headers {
+            let hash = header.block_hash();
+            if blocks.insert(hash, (header, record)).is_none() {
+                children
+                    .entry(header.prev_blockhash)
+                    .or_default()
+                    .push(hash);
+            }
+        }
+
+        let genesis = match children.get(&BlockHash::all_zeros()).map(Vec::as_slice) {
+            Some([genesis]) => *genesis,
+            _ => {
+                return Err(invalid_data(
+                    "the blocks directory must hold the genesis block (i.e.: not be pruned)",
+                ))
+            }
+        };
+
+        // Walk the tree of blocks from the genesis block
+        let mut tip = (blocks[&genesis].0.work(), genesis);
+        let mut stack = vec![tip];
+        while let Some((work, hash)) = stack.pop() {
+            if work > tip.0 {
+                tip = (work, hash);
+            }
+            for child in children.get(&hash).into_iter().flatten() {
+                stack.push((work + blocks[child].0.work(), *child));
+            }
+        }
+
+        let mut chain = vec![];
+        let mut hash = tip.1;
+        while let Some((header, record)) = blocks.get(&hash) {
+            chain.push(*record);
+            hash = header.prev_blockhash;
+        }
+        chain.reverse();
+
+        Ok(chain)
+    }
+
+    /// Reads the outputs spent by the block from the undo data of the `rev*.dat` file numbered
+    /// like its `blk*.dat` file. The undo data of the block is the one with the same number of
+    /// transactions and inputs whose checksum (i.e.: the double SHA256 of the previous block's
+    /// hash followed by the undo data) matches.
+    fn undo(&self, file: u32, block: &Block) -> io::Result<Vec<Vec<TxOut>>> {
+        let mut undo_file = self.undo_file.borrow_mut();
+        if undo_file.as_ref().map(|undo_file| undo_file.file) != Some(file) {
+            *undo_file = Some(self.undo_file(file)?);
+        }
+        let undo_file = undo_file.as_ref().unwrap();
+
+        let shape = (
+            block.txdata.len() - 1,
+            block
+                .txdata
+                .iter()
+                .skip(1)
+                .map(|tx| tx.input.len())
+                .sum::<usize>(),
+        );
+        let prev_blockhash = block.header.prev_blockhash.to_byte_array();
+
+        undo_file
+            .records
+            .iter()
+            .filter(|(_, _, record_shape)| *record_shape == shape)
+            .map(|(start, end, _)| {
+                let (data, checksum) = undo_file.data[*start..*end + 32].split_at(end - start);
+                (data, checksum)
+            })
+            .find(|(data, checksum)| {
+                sha256d::Hash::hash(&[&prev_blockhash[..], data].concat()).as_byte_array()
+                    == *checksum
+            })
+            .map(|(mut data, _)| parse_block_undo(&mut data))
+            .unwrap_or_else(|| {
+                Err(invalid_data(format!(
+                    "no undo data for block {} in rev{:05}.dat",
+                    block.block_hash(),
+                    file
+                )))
+            })
+    }
+
+    fn undo_file(&self, file: u32) -> io::Result<UndoFile> {
+        let mut data = fs::read(self.file_path("rev", file))?;
+        self.unxor(0, &mut data);
+
+        let mut records = vec![];
+        let mut offset = 0;
+        while offset + 8 <= data.len() && data[offset..offset + 4] == self.magic {
+            let size = u32::from_le_bytes(data[offset + 4..offset + 8].try_into().unwrap());
+            let (start, end) = (offset + 8, offset + 8 + size as usize);
+            // Records are followed by a 32 bytes checksum
+            if end + 32 > data.len() {
+                break;
+            }
+
+            let undo = parse_block_undo(&mut &data[start..end])?;
+            let shape = (undo.len(), undo.iter().map(Vec::len).sum());
+            records.push((start, end, shape));
+
+            offset = end + 32;
+        }
+
+        Ok(UndoFile {
+            file,
+            data,
+            records,
+        })
+    }
+}
+
+// ================================================================
+// Undo data
+// ================================================================
+
+fn read_u8(data: &mut &[u8]) -> io::Result<u8> {
+    let mut byte = [0];
+    data.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+fn read_bytes(data: &mut &[u8], len: u64) -> io::Result<Vec<u8>> {
+    if len > data.len() as u64 {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    let (bytes, rest) = data.split_at(len as usize);
+    *data = rest;
+    Ok(bytes.to_vec())
+}
+
+/// Reads a consensus encoded `CompactSize` (i.e.: the length of a vector)
+fn read_compact_size(data: &mut &[u8]) -> io::Result<u64> {
+    let bytes = |data: &mut &[u8], len| -> io::Result<u64> {
+        let mut value = [0; 8];
+        data.read_exact(&mut value[..len])?;
+        Ok(u64::from_le_bytes(value))
+    };
+
+    match read_u8(data)? {
+        0xfd => bytes(data, 2),
+        0xfe => bytes(data, 4),
+        0xff => bytes(data, 8),
+        size => Ok(size as u64),
+    }
+}
+
+/// Reads a `VARINT`, Bitcoin Core's variable length encoding of integers in its databases (MSB
+/// base-128, where each byte but the last has its high bit set and adds one to the value)
+fn read_varint(data: &mut &[u8]) -> io::Result<u64> {
+    let mut value: u64 = 0;
+    loop {
+        let byte = read_u8(data)?;
+        if value > u64::MAX >> 7 {
+            return Err(invalid_data("VARINT overflow"));
+        }
+        value = (value << 7) | (byte & 0x7f) as u64;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+        value += 1;
+    }
+}
+
+/// Decompresses an amount of sats compressed by Bitcoin Core (see `CompressAmount`)
+fn decompress_amount(x: u64) -> u64 {
+    if x == 0 {
+        return 0;
+    }
+
+    let mut x = x - 1;
+    let mut e = x % 10;
+    x /= 10;
+    let mut n = if e < 9 {
+        let d = (x % 9) + 1;
+        x /= 9;
+        x * 10 + d
+    } else {
+        x + 1
+    };
+    while e > 0 {
+        n *= 10;
+        e -= 1;
+    }
+
+    n
+}
+
+/// Reads a script compressed by Bitcoin Core (see `ScriptCompression`): standard scripts are
+/// stored as their hash or public key
+fn read_script(data: &mut &[u8]) -> io::Result<ScriptBuf> {
+    let size = read_varint(data)?;
+
+    let script = match size {
+        0 => ScriptBuf::new_p2pkh(&PubkeyHash::from_byte_array(
+            read_bytes(data, 20)?.try_into().unwrap(),
+        )),
+        1 => ScriptBuf::new_p2sh(&ScriptHash::from_byte_array(
+            read_bytes(data, 20)?.try_into().unwrap(),
+        )),
+        2 | 3 => {
+            let mut key = [size as u8; 33];
+            key[1..].copy_from_slice(&read_bytes(data, 32)?);
+            script::Builder::new()
+                .push_slice(key)
+                .push_opcode(opcodes::all::OP_CHECKSIG)
+                .into_script()
+        }
+        4 | 5 => {
+            let mut key = [size as u8 - 2; 33];
+            key[1..].copy_from_slice(&read_bytes(data, 32)?);
+            match secp256k1::PublicKey::from_slice(&key) {
+                Ok(key) => ScriptBuf::new_p2pk(&PublicKey::new_uncompressed(key)),
+                Err(_) => ScriptBuf::new(),
+            }
+        }
+        _ if size - SPECIAL_SCRIPTS > MAX_SCRIPT_SIZE => {
+            read_bytes(data, size - SPECIAL_SCRIPTS)?;
+            ScriptBuf::new_op_return([])
+        }
+        _ => ScriptBuf::from_bytes(read_bytes(data, size - SPECIAL_SCRIPTS)?),
+    };
+
+    Ok(script)
+}
+
+/// Reads the outputs spent by each transaction of a block but the coinbase transaction (i.e.:
+/// a `CBlockUndo`)
+fn parse_block_undo(data: &mut &[u8]) -> io::Result<Vec<Vec<TxOut>>> {
+    (0..read_compact_size(data)?)
+        .map(|_| {
+            (0..read_compact_size(data)?)
+                .map(|_| {
+                    // Height of the output's transaction and whether it is a coinbase transaction
+                    let code = read_varint(data)?;
+                    if code >> 1 > 0 {
+                        // Unused, kept for compatibility
+                        read_varint(data)?;
+                    }
+
+                    Ok(TxOut {
+                        value: Amount::from_sat(decompress_amount(read_varint(data)?)),
+                        script_pubkey: read_script(data)?,
+                    })
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use bitcoin::{
+        block::Version, consensus::serialize, pow::CompactTarget, Transaction, TxMerkleNode,
+    };
+
+    use super::*;
+    use crate::tx_builder::{account, outpoint, TxBuilder};
+
+    fn write_varint(mut value: u64) -> Vec<u8> {
+        let mut bytes = vec![(value & 0x7f) as u8];
+        while value > 0x7f {
+            value = (value >> 7) - 1;
+            bytes.push((value & 0x7f) as u8 | 0x80);
+        }
+        bytes.reverse();
+        bytes
+    }
+
+    fn compress_amount(mut n: u64) -> u64 {
+        if n == 0 {
+            return 0;
+        }
+        let mut e = 0;
+        // Trailing zeros, up to 9
+        while e < 9 && n % 10 < 1 {
+            n /= 10;
+            e += 1;
+        }
+        if e < 9 {
+            let d = n % 10;
+            n /= 10;
+            1 + (n * 9 + d - 1) * 10 + e
+        } else {
+            1 + (n - 1) * 10 + 9
+        }
+    }
+
+    /// Undo data of the block whose transactions spend the outputs, with uncompressed scripts
+    fn block_undo(spent_outputs: &[Vec<TxOut>]) -> Vec<u8> {
+        let mut data = vec![spent_outputs.len() as u8];
+        for outputs in spent_outputs {
+            data.push(outputs.len() as u8);
+            for output in outputs {
+                // Spent output of a coinbase transaction at height 1
+                data.extend(write_varint(1 << 1 | 1));
+                data.extend(write_varint(0));
+                data.extend(write_varint(compress_amount(output.value.to_sat())));
+                let script = output.script_pubkey.as_bytes();
+                data.extend(write_varint(script.len() as u64 + SPECIAL_SCRIPTS));
+                data.extend(script);
+            }
+        }
+        data
+    }
+
+    fn block(prev: &Block, txdata: Vec<Transaction>) -> Block {
+        let mut block = Block {
+            header: Header {
+                version: Version::TWO,
+                prev_blockhash: prev.block_hash(),
+                merkle_root: TxMerkleNode::all_zeros(),
+                time: prev.header.time + 600,
+                bits: CompactTarget::from_consensus(0x207fffff),
+                nonce: 0,
+            },
+            txdata,
+        };
+        block.header.merkle_root = block.compute_merkle_root().unwrap();
+        block
+    }
+
+    /// Writes the records to the file, XORed with the key
+    fn write_records(path: &Path, key: [u8; 8], records: &[Vec<u8>]) {
+        let mut data = records
+            .iter()
+            .flat_map(|record| {
+                let magic = Network::Regtest.magic().to_bytes();
+                [&magic[..], &(record.len() as u32).to_le_bytes(), record].concat()
+            })
+            .collect::<Vec<_>>();
+        // Preallocated space
+        data.extend([0; 64]);
+        data.iter_mut()
+            .enumerate()
+            .for_each(|(i, byte)| *byte ^= key[i % 8]);
+        fs::write(path, data).unwrap();
+    }
+
+    #[test]
+    fn test_varint() {
+        for (value, bytes) in [
+            (0, "00"),
+            (0x7f, "7f"),
+            (0x80, "8000"),
+            (0x1234, "a334"),
+            (0xffff, "82fe7f"),
+            (0x123456, "c7e756"),
+            (0x80123456, "86ffc7e756"),
+            (0xffffffff, "8efefefe7f"),
+        ] {
+            assert_eq!(hex::encode(write_varint(value)), bytes);
+            assert_eq!(
+                read_varint(&mut &hex::decode(bytes).unwrap()[..]).unwrap(),
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn test_decompress_amount() {
+        for (sats, compressed) in [
+            (0, 0x0),
+            (1, 0x1),
+            (1_000_000, 0x7),
+            (100_000_000, 0x9),
+            (5_000_000_000, 0x32),
+            (2_100_000_000_000_000, 0x1406f40),
+        ] {
+            assert_eq!(compress_amount(sats), compressed);
+            assert_eq!(decompress_amount(compressed), sats);
+        }
+        for sats in [546, 10_000, 123_456_789, 20_999_999_997_690_000] {
+            assert_eq!(decompress_amount(compress_amount(sats)), sats);
+        }
+    }
+
+    #[test]
+    fn test_read_special_scripts() {
+        let hash = [0x42; 20];
+        let read = |data: &[u8]| read_script(&mut &data[..]).unwrap();
+
+        assert_eq!(
+            read(&[&[0x00][..], &hash].concat()),
+            ScriptBuf::new_p2pkh(&PubkeyHash::from_byte_array(hash))
+        );
+        assert_eq!(
+            read(&[&[0x01][..], &hash].concat()),
+            ScriptBuf::new_p2sh(&ScriptHash::from_byte_array(hash))
+        );
+
+        // Generator point, whose y coordinate is even
+        let x = hex::decode("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
+            .unwrap();
+        let key = PublicKey::from_slice(&[&[0x02][..], &x].concat()).unwrap();
+        assert_eq!(read(&[&[0x02][..], &x].concat()), ScriptBuf::new_p2pk(&key));
+        assert_eq!(
+            read(&[&[0x04][..], &x].concat()),
+            ScriptBuf::new_p2pk(&PublicKey::new_uncompressed(key.inner))
+        );
+    }
+
+    #[test]
+    fn test_blocks_dir() {
+        let dir = std::env::temp_dir().join(format!("brc20-blocks-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let key = [1, 2, 3, 4, 5, 6, 7, 8];
+        fs::write(dir.join("xor.dat"), key).unwrap();
+
+        let coinbase = |height: i64, byte: u8| {
+            TxBuilder::coinbase(height)
+                .output(account(byte), 5_000_000_000)
+                .build()
+        };
+        let genesis = Block {
+            header: Header {
+                version: Version::ONE,
+                prev_blockhash: BlockHash::all_zeros(),
+                merkle_root: TxMerkleNode::all_zeros(),
+                time: 1_231_006_505,
+                bits: CompactTarget::from_consensus(0x207fffff),
+                nonce: 0,
+            },
+            txdata: vec![coinbase(0, 0xaa)],
+        };
+        let block_1 = block(&genesis, vec![coinbase(1, 0xaa)]);
+        // Stale block at height 1
+        let stale_1 = block(&genesis, vec![coinbase(1, 0xbb)]);
+        let spend = TxBuilder::new()
+            .input(outpoint(&block_1.txdata[0], 0))
+            .output(account(0xcc), 4_999_990_000)
+            .build();
+        let block_2 = block(&block_1, vec![coinbase(2, 0xaa), spend]);
+
+        let spent_outputs = vec![vec![block_1.txdata[0].output[0].clone()]];
+        // Blocks are stored in the order in which they were received, and undo data in the order
+        // in which blocks were connected
+        write_records(
+            &dir.join("blk00000.dat"),
+            key,
+            &[&genesis, &block_2, &stale_1, &block_1].map(serialize),
+        );
+        let undo_record = |block: &Block, spent_outputs: &[Vec<TxOut>]| {
+            let undo = block_undo(spent_outputs);
+            let checksum = sha256d::Hash::hash(
+                &[&block.header.prev_blockhash.to_byte_array()[..], &undo].concat(),
+            );
+            (undo, checksum.to_byte_array().to_vec())
+        };
+        let records = [
+            undo_record(&stale_1, &[]),
+            undo_record(&block_1, &[]),
+            undo_record(&block_2, &spent_outputs),
+        ];
+        // The checksum follows the record
+        let mut rev = records
+            .iter()
+            .flat_map(|(undo, checksum)| {
+                let magic = Network::Regtest.magic().to_bytes();
+                [
+                    &magic[..],
+                    &(undo.len() as u32).to_le_bytes(),
+                    undo,
+                    checksum,
+                ]
+                .concat()
+            })
+            .collect::<Vec<_>>();
+        rev.iter_mut()
+            .enumerate()
+            .for_each(|(i, byte)| *byte ^= key[i % 8]);
+        fs::write(dir.join("rev00000.dat"), rev).unwrap();
+
+        let blocks_dir = BlocksDir::open(&dir, Network::Regtest).unwrap();
+
+        assert_eq!(blocks_dir.tip_height(), Some(2));
+        assert_eq!(blocks_dir.block(0).unwrap().block, genesis);
+        assert_eq!(blocks_dir.block(1).unwrap().block, block_1);
+        let chain_block = blocks_dir.block(2).unwrap();
+        assert_eq!(chain_block.block, block_2);
+        assert_eq!(chain_block.spent_outputs, spent_outputs);
+        assert_eq!(
+            chain_block.prevouts(),
+            [(format!("{}:0", block_1.txdata[0].txid()), 5_000_000_000)]
+        );
+        let proto = chain_block.to_proto();
+        assert_eq!(proto.height, 2);
+        assert_eq!(proto.hash, block_2.block_hash().to_string());
+        assert_eq!(proto.previous_hash, block_1.block_hash().to_string());
+        assert_eq!(proto.tx[1].vin[0].outpoint(), chain_block.prevouts()[0].0);
+        assert!(blocks_dir.block(3).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -38,6 +38,8 @@ pub struct Deploy {
     #[serde(deserialize_with = "deserialize_bigint_option")]
     lim: Option<BigInt>,
     dec: Option<i32>,
+    /// Any type is accepted, as extra fields do not invalidate operations
+    self_mint: Option<Value>,
 }
 
 impl Deploy {
@@ -53,18 +55,10 @@ impl Deploy {
         self.tick.to_lowercase()
     }
 
-    pub fn valid(&self) -> bool {
-        // Check zero values
-        if self.max.is_zero() || self.lim().is_zero() {
-            return false;
-        }
-
-        // Check dec value
-        if self.dec() > 18 || self.dec() < 0 {
-            return false;
-        }
-
-        true
+    /// Returns true if the deploy is for a self-mint token, i.e.: a 5-byte ticker deployed with
+    /// `"self_mint": "true"` (whether such deploys are valid depends on the rule set)
+    pub fn self_mint(&self) -> bool {
+        self.tick().len() == 5 && matches!(&self.self_mint, Some(Value::String(s)) if s == "true")
     }
 }
 
@@ -80,15 +74,6 @@ impl Mint {
     pub fn tick(&self) -> String {
         self.tick.to_lowercase()
     }
-
-    pub fn valid(&self) -> bool {
-        // Check zero values
-        if self.amt.is_zero() {
-            return false;
-        }
-
-        true
-    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -103,14 +88,16 @@ impl Transfer {
     pub fn tick(&self) -> String {
         self.tick.to_lowercase()
     }
+}
 
-    pub fn valid(&self) -> bool {
-        // Check zero values
-        if self.amt.is_zero() {
-            return false;
-        }
-
-        true
+/// Returns true if the inscription's content type is one of the content types allowed for BRC-20
+/// operations, i.e.: `text/plain` or `application/json`, with or without parameters (e.g.:
+/// `text/plain;charset=utf-8`). The MIME type is split on ";" and the first part is compared
+/// as is, without being trimmed.
+pub fn is_brc20_content_type(content_type: Option<&str>) -> bool {
+    match content_type.and_then(|ctype| ctype.split(';').next()) {
+        Some(content_type) => content_type == "text/plain" || content_type == "application/json",
+        None => false,
     }
 }
 
@@ -138,22 +125,82 @@ impl Brc20Event {
             Brc20Event::Transfer(t) => t.tick(),
         }
     }
+}
 
-    pub fn valid(&self) -> bool {
-        // Check protocol
-        if self.p() != "brc-20" {
-            return false;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        btc_utils::parse_envelopes,
+        ord::inscription::Inscription,
+        pb::sf::bitcoin::r#type::v1 as btc,
+        tx_builder::{external_outpoint, to_proto, TxBuilder},
+    };
+
+    /// Builds a reveal transaction with a single inscription in its first input
+    fn reveal_tx(content_type: &str, content: &str) -> btc::Transaction {
+        let inscription = Inscription::new(
+            Some(content_type.as_bytes().to_vec()),
+            Some(content.as_bytes().to_vec()),
+        );
+
+        to_proto(
+            &TxBuilder::new()
+                .reveal(external_outpoint(0xa0), &[inscription])
+                .build(),
+        )
+    }
+
+    fn brc20_inscriptions(tx: &btc::Transaction) -> Vec<Inscription> {
+        if !tx.may_contain_inscriptions() {
+            return vec![];
         }
 
-        // Check ticker
-        if self.tick().len() != 4 {
-            return false;
+        parse_envelopes(tx)
+            .unwrap()
+            .into_iter()
+            .map(|envelope| envelope.payload)
+            .filter(|inscription| is_brc20_content_type(inscription.content_type()))
+            .collect()
+    }
+
+    #[test]
+    fn test_brc20_content_types() {
+        let content = r#"{"p":"brc-20","op":"mint","tick":"ordi","amt":"1000"}"#;
+
+        for content_type in [
+            "text/plain;charset=utf-8",
+            "text/plain;charset=UTF-8",
+            "text/plain; charset=utf-8",
+            "text/plain",
+            "application/json",
+            "application/json;charset=utf-8",
+        ] {
+            let inscriptions = brc20_inscriptions(&reveal_tx(content_type, content));
+            assert_eq!(inscriptions.len(), 1, "{} should be accepted", content_type);
+            assert_eq!(inscriptions[0].body(), Some(content.as_bytes()));
         }
+    }
 
-        match self {
-            Brc20Event::Deploy(d) => d.valid(),
-            Brc20Event::Mint(m) => m.valid(),
-            Brc20Event::Transfer(t) => t.valid(),
+    #[test]
+    fn test_non_brc20_content_types() {
+        let content = r#"{"p":"brc-20","op":"mint","tick":"ordi","amt":"1000"}"#;
+
+        for content_type in [
+            "text/html;charset=utf-8",
+            "image/png",
+            "text/plainx",
+            " text/plain",
+            "",
+        ] {
+            let inscriptions = brc20_inscriptions(&reveal_tx(content_type, content));
+            assert!(
+                inscriptions.is_empty(),
+                "{} should be rejected",
+                content_type
+            );
         }
+
+        assert!(!is_brc20_content_type(None));
     }
 }
@@ -1,19 +1,51 @@
 use crate::{
-    ord::{envelope::ParsedEnvelope, inscription::Inscription},
+    errors::{Error, Result},
+    ord::envelope::ParsedEnvelope,
     pb::sf::bitcoin::r#type::v1 as btc,
 };
-use anyhow::Result;
 use bitcoin::{
-    address::Address, blockdata::script::Script, consensus::deserialize, hashes::hex::FromHex,
-    network::Network, Transaction,
+    address::Address,
+    blockdata::script::Script,
+    consensus::{deserialize, encode::serialize_hex},
+    hashes::Hash,
+    network::Network,
+    Block, BlockHash, ScriptBuf, Transaction,
 };
 
 // ================================================================
 // Sat utils
 // ================================================================
-pub fn btc_to_sats(btc_amount: f64) -> u64 {
-    let s = format!("{:.8}", btc_amount);
-    s.replace(".", "").parse::<u64>().unwrap()
+const SATS_PER_BTC: u64 = 100_000_000;
+
+/// Converts an amount of BTC to sats by parsing the shortest decimal representation of the
+/// amount, which is exact for any amount with at most 8 decimals up to the 21M BTC supply.
+/// Used for the values of outputs, unless their transaction is decoded anyway (see
+/// `output_sats`).
+pub fn btc_to_sats(btc_amount: f64) -> Result<u64> {
+    let invalid = |reason: &str| Error::Amount(format!("{} BTC: {}", btc_amount, reason));
+
+    if !btc_amount.is_finite() || btc_amount < 0.0 {
+        return Err(invalid("not a positive amount"));
+    }
+
+    // Note: The `Display` implementation of `f64` never uses the scientific notation
+    let repr = btc_amount.to_string();
+    let (whole, fraction) = repr.split_once('.').unwrap_or((&repr, ""));
+    if fraction.len() > 8 {
+        return Err(invalid("not a whole amount of sats"));
+    }
+
+    let whole = whole
+        .parse::<u64>()
+        .map_err(|err| invalid(&err.to_string()))?;
+    let fraction = format!("{:0<8}", fraction)
+        .parse::<u64>()
+        .map_err(|err| invalid(&err.to_string()))?;
+
+    whole
+        .checked_mul(SATS_PER_BTC)
+        .and_then(|sats| sats.checked_add(fraction))
+        .ok_or_else(|| invalid("overflow"))
 }
 
 // From https://github.com/ordinals/ord/blob/master/bip.mediawiki
@@ -39,8 +71,8 @@ pub fn block_supply(height: u64) -> u64 {
 // Address utils
 // ================================================================
 pub fn address_from_scriptpubkey(script_pub_key_hex: &str) -> Option<String> {
-    // Decode the script from hex
-    let hex_data = hex::decode(script_pub_key_hex).expect("Valid hex script");
+    // Decode the script from hex (a script which cannot be decoded has no address)
+    let hex_data = hex::decode(script_pub_key_hex).ok()?;
     let script = Script::from_bytes(&hex_data);
 
     // Create a Bitcoin address from the public key script
@@ -52,28 +84,236 @@ pub fn address_from_scriptpubkey(script_pub_key_hex: &str) -> Option<String> {
 // ================================================================
 // Inscriptions utils
 // ================================================================
-pub fn parse_inscriptions(tx: &btc::Transaction) -> Result<Vec<Inscription>> {
-    let raw_trx = Vec::from_hex(&tx.hex).unwrap();
-    let tx_: Transaction = deserialize(&raw_trx).unwrap();
-    let envelopes = ParsedEnvelope::from_transaction(&tx_);
-
-    Ok(envelopes
-        .into_iter()
-        .map(|envelope| envelope.payload)
-        .collect())
+/// Hex encoding of the protocol identifier (i.e.: `ord`) pushed at the start of every envelope
+const PROTOCOL_ID_HEX: &str = "6f7264";
+
+/// Returns true if the hex-encoded tapscript might contain an inscription envelope.
+/// This check is done on the hex string directly, without decoding it.
+pub fn may_contain_envelope(tapscript_hex: &str) -> bool {
+    // Only matches aligned on a byte boundary are actual occurrences of the protocol identifier
+    tapscript_hex
+        .match_indices(PROTOCOL_ID_HEX)
+        .any(|(idx, _)| idx % 2 == 0)
+}
+
+/// Parses the inscription envelopes of a transaction. Only the tapscripts which might
+/// contain an envelope are decoded, the rest of the transaction is never decoded.
+/// As in `ord`, an input whose tapscript cannot be parsed is skipped.
+pub fn parse_envelopes(tx: &btc::Transaction) -> Result<Vec<ParsedEnvelope>> {
+    let mut envelopes = vec![];
+
+    for (input, vin) in tx.vin.iter().enumerate() {
+        let tapscript_hex = match vin.tapscript_hex() {
+            Some(tapscript_hex) if may_contain_envelope(tapscript_hex) => tapscript_hex,
+            _ => continue,
+        };
+
+        let tapscript = ScriptBuf::from_hex(tapscript_hex).map_err(|err| {
+            Error::Decode(format!(
+                "tapscript of input {} of tx {}: {}",
+                input, tx.txid, err
+            ))
+        })?;
+
+        match ParsedEnvelope::from_tapscript(&tapscript, input) {
+            Ok(input_envelopes) => envelopes.extend(input_envelopes),
+            Err(err) => substreams::log::info!(
+                "Skipping input {} of tx {}: {}",
+                input,
+                tx.txid,
+                Error::Script(err.to_string())
+            ),
+        }
+    }
+
+    Ok(envelopes)
 }
 
 // ================================================================
 // BTC protobuf models utils
 // ================================================================
+/// Converts the transaction to its Firehose representation (i.e.: bitcoind's `getrawtransaction`
+/// verbose output)
+pub fn to_proto(tx: &Transaction) -> btc::Transaction {
+    btc::Transaction {
+        hex: serialize_hex(tx),
+        txid: tx.txid().to_string(),
+        hash: tx.wtxid().to_string(),
+        size: tx.total_size() as i32,
+        vsize: tx.vsize() as i32,
+        weight: tx.weight().to_wu() as i32,
+        version: tx.version.0 as u32,
+        locktime: tx.lock_time.to_consensus_u32(),
+        vin: tx
+            .input
+            .iter()
+            .map(|input| {
+                if tx.is_coinbase() {
+                    btc::Vin {
+                        coinbase: input.script_sig.to_hex_string(),
+                        sequence: input.sequence.0,
+                        ..Default::default()
+                    }
+                } else {
+                    btc::Vin {
+                        txid: input.previous_output.txid.to_string(),
+                        vout: input.previous_output.vout,
+                        script_sig: Some(btc::ScriptSig {
+                            asm: input.script_sig.to_asm_string(),
+                            hex: input.script_sig.to_hex_string(),
+                        }),
+                        sequence: input.sequence.0,
+                        txinwitness: input.witness.iter().map(hex::encode).collect(),
+                        ..Default::default()
+                    }
+                }
+            })
+            .collect(),
+        vout: tx
+            .output
+            .iter()
+            .enumerate()
+            .map(|(n, output)| btc::Vout {
+                value: output.value.to_btc(),
+                n: n as u32,
+                script_pub_key: Some(btc::ScriptPubKey {
+                    asm: output.script_pubkey.to_asm_string(),
+                    hex: output.script_pubkey.to_hex_string(),
+                    address: address_from_scriptpubkey(&output.script_pubkey.to_hex_string())
+                        .unwrap_or_default(),
+                    ..Default::default()
+                }),
+            })
+            .collect(),
+        ..Default::default()
+    }
+}
+
+/// Converts the block at the given height to its Firehose representation (i.e.: bitcoind's
+/// `getblock <hash> 2` output). Fields which depend on the rest of the chain (i.e.: `mediantime`
+/// and `chainwork`) are left empty.
+pub fn block_to_proto(block: &Block, height: i64) -> btc::Block {
+    let hash = block.block_hash().to_string();
+    let weight = block.weight().to_wu() as usize;
+
+    btc::Block {
+        hash: hash.clone(),
+        size: block.total_size() as i32,
+        // Weight is three times the stripped size plus the total size
+        stripped_size: ((weight - block.total_size()) / 3) as i32,
+        weight: weight as i32,
+        height,
+        version: block.header.version.to_consensus(),
+        version_hex: format!("{:08x}", block.header.version.to_consensus()),
+        merkle_root: block.header.merkle_root.to_string(),
+        tx: block
+            .txdata
+            .iter()
+            .map(|tx| btc::Transaction {
+                blockhash: hash.clone(),
+                blocktime: block.header.time as i64,
+                ..to_proto(tx)
+            })
+            .collect(),
+        time: block.header.time as i64,
+        nonce: block.header.nonce,
+        bits: format!("{:08x}", block.header.bits.to_consensus()),
+        difficulty: block.header.difficulty_float(),
+        n_tx: block.txdata.len() as u32,
+        // As in bitcoind, the genesis block has no previous block
+        previous_hash: Some(block.header.prev_blockhash)
+            .filter(|hash| *hash != BlockHash::all_zeros())
+            .map(|hash| hash.to_string())
+            .unwrap_or_default(),
+        ..Default::default()
+    }
+}
+
 impl btc::Transaction {
-    /// Returns the nth satoshi UTXO in the transaction along with its offset within the UTXO.
-    pub fn nth_sat_utxo(&self, offset: u64) -> Option<(btc::Vout, u64)> {
+    /// Returns true if any of the transaction's inputs might contain an inscription envelope
+    pub fn may_contain_inscriptions(&self) -> bool {
+        self.vin
+            .iter()
+            .filter_map(|vin| vin.tapscript_hex())
+            .any(may_contain_envelope)
+    }
+
+    /// Returns the value in sats of each output of the transaction, converted from the
+    /// outputs' BTC values without decoding the transaction (see `btc_to_sats`)
+    pub fn vout_sats(&self) -> Result<Vec<u64>> {
+        self.vout
+            .iter()
+            .map(|output| btc_to_sats(output.value))
+            .collect()
+    }
+
+    /// Returns the value in sats of each output of the transaction. Values are taken from the
+    /// consensus-decoded transaction when its raw hex is available, and are otherwise converted
+    /// from the outputs' BTC values. Decoding is costly, so it is only used for the transactions
+    /// revealing inscriptions, whose values callers compute once per transaction and pass to
+    /// `inscription_offset` and `nth_sat_utxo`. Other transactions use `vout_sats`.
+    pub fn output_sats(&self) -> Result<Vec<u64>> {
+        if self.hex.is_empty() {
+            return self.vout_sats();
+        }
+
+        let decode_error =
+            |err: &dyn std::fmt::Display| Error::Decode(format!("tx {}: {}", self.txid, err));
+        let raw = hex::decode(&self.hex).map_err(|err| decode_error(&err))?;
+        let tx: Transaction = deserialize(&raw).map_err(|err| decode_error(&err))?;
+
+        if tx.output.len() != self.vout.len() {
+            return Err(decode_error(&"number of outputs does not match"));
+        }
+
+        Ok(tx
+            .output
+            .iter()
+            .map(|output| output.value.to_sat())
+            .collect())
+    }
+
+    /// Returns the offset of the first sat of the given input within the transaction, i.e.: the
+    /// sum of the values of the previous inputs, which are looked up with `input_sats`.
+    pub fn input_offset(
+        &self,
+        input: usize,
+        input_sats: impl Fn(&btc::Vin) -> Option<u64>,
+    ) -> Result<u64> {
+        self.vin
+            .iter()
+            .take(input)
+            .map(|vin| input_sats(vin).ok_or_else(|| Error::UnknownUtxo(vin.outpoint())))
+            .sum()
+    }
+
+    /// Returns the offset within the transaction's outputs of the sat inscribed by a new
+    /// inscription, following ord's rules: the inscription's pointer if it points to a sat of
+    /// the outputs, otherwise the first sat of the input revealing the inscription. Inscriptions
+    /// revealed in the same input without a pointer are therefore stacked on the same sat.
+    /// Note: The offset can be past the outputs, in which case the inscribed sat is spent as fee.
+    pub fn inscription_offset(
+        &self,
+        envelope: &ParsedEnvelope,
+        output_sats: &[u64],
+        input_sats: impl Fn(&btc::Vin) -> Option<u64>,
+    ) -> Result<u64> {
+        let total_output_sats = output_sats.iter().sum::<u64>();
+
+        match envelope.payload.pointer() {
+            Some(pointer) if pointer < total_output_sats => Ok(pointer),
+            _ => self.input_offset(envelope.input as usize, input_sats),
+        }
+    }
+
+    /// Returns the nth satoshi UTXO in the transaction along with its offset within the UTXO
+    /// and the UTXO's value in sats, or `None` if the sat is spent as fee. `output_sats` are the
+    /// values of the transaction's outputs (see `output_sats`).
+    pub fn nth_sat_utxo(&self, output_sats: &[u64], offset: u64) -> Option<(btc::Vout, u64, u64)> {
         let mut sat = 0;
-        for (idx, output) in self.vout.iter().enumerate() {
-            let utxo_sats = btc_to_sats(output.value);
+        for (vout, &utxo_sats) in self.vout.iter().zip(output_sats) {
             if sat + utxo_sats > offset {
-                return Some((self.vout[idx].clone(), offset - sat));
+                return Some((vout.clone(), offset - sat, utxo_sats));
             }
             sat += utxo_sats;
         }
@@ -81,21 +321,73 @@ impl btc::Transaction {
     }
 }
 
+impl btc::Vin {
+    /// Returns the outpoint spent by the input, i.e.: `{txid}:{vout}`
+    pub fn outpoint(&self) -> String {
+        format!("{}:{}", self.txid, self.vout)
+    }
+
+    /// Returns the hex-encoded tapscript of the input's witness, if any (see `Witness::tapscript`)
+    pub fn tapscript_hex(&self) -> Option<&str> {
+        let len = self.txinwitness.len();
+
+        // If there are at least two witness elements and the first byte of the last element
+        // is 0x50, the last element is an annex and is removed from the witness stack (BIP341)
+        let position = match self.txinwitness.last() {
+            Some(last) if len >= 2 && last.starts_with("50") => 3,
+            Some(_) => 2,
+            None => return None,
+        };
+
+        if len < position {
+            return None;
+        }
+
+        Some(&self.txinwitness[len - position])
+    }
+}
+
 impl btc::Vout {
     pub fn address(&self) -> Option<String> {
         self.script_pub_key
             .as_ref()
             .and_then(|script_pub_key| address_from_scriptpubkey(&script_pub_key.hex))
     }
+
+    /// Returns the account owning the output: its address, or its hex-encoded scriptPubKey if it
+    /// has none (e.g.: bare multisig or nonstandard scripts). Outputs with an empty scriptPubKey
+    /// have no owner.
+    pub fn owner(&self) -> Option<String> {
+        self.address().or_else(|| {
+            self.script_pub_key
+                .as_ref()
+                .map(|script_pub_key| script_pub_key.hex.clone())
+                .filter(|hex| !hex.is_empty())
+        })
+    }
+
+    /// Returns true if the output is provably unspendable (i.e.: its script starts with OP_RETURN)
+    pub fn is_op_return(&self) -> bool {
+        self.script_pub_key
+            .as_ref()
+            .map(|script_pub_key| script_pub_key.hex.starts_with("6a"))
+            .unwrap_or(false)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{ord::inscription::Inscription, tx_builder::TxBuilder};
 
     #[test]
     fn test_btc_to_sats() {
-        assert_eq!(btc_to_sats(0.00000001), 1);
+        assert_eq!(btc_to_sats(0.00000001), Ok(1));
+        assert_eq!(btc_to_sats(0.1), Ok(10_000_000));
+        assert_eq!(btc_to_sats(20_999_999.976_9), Ok(2_099_999_997_690_000));
+        assert!(matches!(btc_to_sats(-1.0), Err(Error::Amount(_))));
+        assert!(matches!(btc_to_sats(f64::NAN), Err(Error::Amount(_))));
+        assert!(matches!(btc_to_sats(0.000000001), Err(Error::Amount(_))));
     }
 
     #[test]
@@ -144,6 +436,293 @@ mod tests {
         assert_eq!(
             address_from_scriptpubkey("76a914534e48e9a49ce7ebf8d84c8313e4edfa48852fa188ac"),
             Some("18bUsFHLgFotUqAL9ftLBVenJDVP7M64Nu".into())
+        );
+        assert_eq!(address_from_scriptpubkey("not hex"), None);
+    }
+
+    #[test]
+    fn test_vout_is_op_return() {
+        let vout = |hex: &str| btc::Vout {
+            script_pub_key: Some(btc::ScriptPubKey {
+                hex: hex.into(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        assert!(vout("6a0462726332").is_op_return());
+        assert!(!vout("76a914534e48e9a49ce7ebf8d84c8313e4edfa48852fa188ac").is_op_return());
+        assert!(!btc::Vout::default().is_op_return());
+    }
+
+    /// Builds a transaction with one output per value, along with its raw hex
+    fn tx_with_outputs(values: &[u64]) -> btc::Transaction {
+        let builder = values.iter().fold(TxBuilder::new(), |builder, value| {
+            builder.output(ScriptBuf::new(), *value)
+        });
+
+        to_proto(&builder.build())
+    }
+
+    #[test]
+    fn test_nth_sat_utxo() {
+        let tx = tx_with_outputs(&[546, 10_000, 330]);
+
+        let output_sats = tx.output_sats().unwrap();
+        assert_eq!(output_sats, vec![546, 10_000, 330]);
+        assert_eq!(
+            tx.nth_sat_utxo(&output_sats, 0)
+                .map(|(vout, offset, sats)| (vout.n, offset, sats)),
+            Some((0, 0, 546))
+        );
+        assert_eq!(
+            tx.nth_sat_utxo(&output_sats, 546)
+                .map(|(vout, offset, sats)| (vout.n, offset, sats)),
+            Some((1, 0, 10_000))
+        );
+        assert_eq!(
+            tx.nth_sat_utxo(&output_sats, 10_875)
+                .map(|(vout, offset, sats)| (vout.n, offset, sats)),
+            Some((2, 329, 330))
+        );
+        // Sats spent as fee
+        assert_eq!(tx.nth_sat_utxo(&output_sats, 10_876), None);
+
+        let malformed = btc::Transaction {
+            hex: "0200".into(),
+            ..tx
+        };
+        assert!(matches!(malformed.output_sats(), Err(Error::Decode(_))));
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn prop_btc_to_sats_is_exact(sats in 0..=21_000_000 * SATS_PER_BTC) {
+            proptest::prop_assert_eq!(btc_to_sats(sats as f64 / SATS_PER_BTC as f64), Ok(sats));
+        }
+
+        #[test]
+        fn prop_output_sats_match_fallback(
+            values in proptest::collection::vec(0..=21_000_000 * SATS_PER_BTC, 1..8)
+        ) {
+            let tx = tx_with_outputs(&values);
+            let fallback = btc::Transaction {
+                hex: "".into(),
+                ..tx.clone()
+            };
+
+            proptest::prop_assert_eq!(tx.output_sats(), Ok(values));
+            proptest::prop_assert_eq!(fallback.output_sats(), tx.output_sats());
+            // Values of reveal transactions match the values of their spent outputs
+            proptest::prop_assert_eq!(tx.vout_sats(), tx.output_sats());
+        }
+    }
+
+    #[test]
+    fn test_inscription_offset() {
+        let mut tx = tx_with_outputs(&[1_000, 2_000]);
+        tx.vin = ["a", "b", "c"]
+            .iter()
+            .map(|txid| btc::Vin {
+                txid: txid.to_string(),
+                ..Default::default()
+            })
+            .collect();
+        let input_sats = |vin: &btc::Vin| match vin.txid.as_str() {
+            "a" => Some(500),
+            "b" => Some(1_500),
+            _ => None,
+        };
+        let output_sats = tx.output_sats().unwrap();
+        let envelope = |input: u32, pointer: Option<u64>| ParsedEnvelope {
+            input,
+            payload: Inscription {
+                pointer: pointer.map(Inscription::pointer_value),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        // Inscriptions are placed on the first sat of their input
+        assert_eq!(
+            tx.inscription_offset(&envelope(0, None), &output_sats, input_sats),
+            Ok(0)
+        );
+        assert_eq!(
+            tx.inscription_offset(&envelope(1, None), &output_sats, input_sats),
+            Ok(500)
+        );
+        assert_eq!(
+            tx.inscription_offset(&envelope(2, None), &output_sats, input_sats),
+            Ok(2_000)
+        );
+
+        // Pointers are only honored if they point to a sat of the outputs
+        assert_eq!(
+            tx.inscription_offset(&envelope(1, Some(2_999)), &output_sats, input_sats),
+            Ok(2_999)
+        );
+        assert_eq!(
+            tx.inscription_offset(&envelope(1, Some(3_000)), &output_sats, input_sats),
+            Ok(500)
+        );
+
+        // The values of all the previous inputs are required
+        assert_eq!(
+            tx.inscription_offset(&envelope(2, None), &output_sats, |vin: &btc::Vin| {
+                input_sats(vin).filter(|_| vin.txid != "a")
+            }),
+            Err(Error::UnknownUtxo("a:0".into()))
+        );
+    }
+
+    #[test]
+    fn test_vin_tapscript_hex() {
+        let vin = |witness: &[&str]| btc::Vin {
+            txinwitness: witness.iter().map(|item| item.to_string()).collect(),
+            ..Default::default()
+        };
+
+        assert_eq!(vin(&[]).tapscript_hex(), None);
+        assert_eq!(vin(&["c0"]).tapscript_hex(), None);
+        assert_eq!(vin(&["00", "ab", "c0"]).tapscript_hex(), Some("ab"));
+        // The annex is skipped
+        assert_eq!(vin(&["00", "ab", "c0", "50"]).tapscript_hex(), Some("ab"));
+        assert_eq!(vin(&["ab", "50"]).tapscript_hex(), None);
+    }
+
+    #[test]
+    fn test_may_contain_envelope() {
+        assert!(may_contain_envelope("0063036f726401"));
+        assert!(!may_contain_envelope("00630301"));
+        // Not aligned on a byte boundary
+        assert!(!may_contain_envelope("06f72640"));
+        assert!(may_contain_envelope("06f726406f7264"));
+    }
+
+    /// Legacy parsing path: full decode of the transactions containing the
+    /// `text/plain;charset=utf-8` content type, which returns `None` for the other transactions
+    fn legacy_inscriptions(tx: &btc::Transaction) -> Option<Vec<Inscription>> {
+        use bitcoin::{consensus::deserialize, Transaction};
+
+        if !tx
+            .hex
+            .contains("746578742f706c61696e3b636861727365743d7574662d38")
+        {
+            return None;
+        }
+        let tx: Transaction = deserialize(&hex::decode(&tx.hex).unwrap()).unwrap();
+        Some(
+            ParsedEnvelope::from_transaction(&tx)
+                .into_iter()
+                .map(|envelope| envelope.payload)
+                .collect(),
         )
     }
+
+    /// Parsing path of the substream: pre-scan of the witness data, and decode of the tapscripts
+    /// which may contain an envelope, which returns `None` for the other transactions
+    fn prescan_inscriptions(tx: &btc::Transaction) -> Option<Vec<Inscription>> {
+        if !tx.may_contain_inscriptions() {
+            return None;
+        }
+        Some(
+            parse_envelopes(tx)
+                .unwrap()
+                .into_iter()
+                .map(|envelope| envelope.payload)
+                .collect(),
+        )
+    }
+
+    /// Decodes a block in the format of bitcoind's `getblock <hash> 0`
+    fn block_from_hex(hex: &str) -> btc::Block {
+        let block: Block = deserialize(&hex::decode(hex.trim()).unwrap()).unwrap();
+        let height = block.bip34_block_height().unwrap_or_default();
+        block_to_proto(&block, height as i64)
+    }
+
+    /// Compares the legacy parsing path with the witness pre-scan, over a block where one in four
+    /// transactions is a BRC-20 reveal and one in four carries the content type in an `OP_RETURN`
+    /// output
+    #[test]
+    fn test_parse_inscriptions_prescan() {
+        let block = block_from_hex(include_str!("../fixtures/blocks/780000.hex"));
+
+        let legacy = block.tx.iter().filter_map(legacy_inscriptions);
+        let prescan = block.tx.iter().filter_map(prescan_inscriptions);
+        let (legacy_decodes, prescan_decodes) = (legacy.clone().count(), prescan.clone().count());
+        let expected = legacy.flatten().collect::<Vec<_>>();
+        let actual = prescan.flatten().collect::<Vec<_>>();
+
+        assert_eq!(block.tx.len(), 401);
+        assert_eq!(actual, expected);
+        assert_eq!(actual.len(), 100);
+        // Only the reveal transactions' tapscripts are decoded
+        assert_eq!(legacy_decodes, 200);
+        assert_eq!(prescan_decodes, 100);
+    }
+
+    /// Times both parsing paths, as well as both ways of getting the values of the outputs of
+    /// every transaction (decoding it, or converting the BTC values of its outputs), over the
+    /// block saved at `BENCH_BLOCK` (e.g.: a mainnet block saved with bitcoind's
+    /// `getblock <hash> 0`), or over the block fixture if it is not set
+    #[test]
+    #[ignore]
+    fn bench_parse_inscriptions() {
+        let block = match std::env::var("BENCH_BLOCK") {
+            Ok(path) => block_from_hex(&std::fs::read_to_string(path).unwrap()),
+            Err(_) => block_from_hex(include_str!("../fixtures/blocks/780000.hex")),
+        };
+        let iterations = 20;
+        let time = |name: &str, run: &dyn Fn() -> usize| {
+            let start = std::time::Instant::now();
+            let count = (0..iterations).map(|_| run()).last().unwrap_or_default();
+            let elapsed = start.elapsed() / iterations;
+            println!("{:<24} {:>12?} per block ({} items)", name, elapsed, count);
+            elapsed.as_secs_f64()
+        };
+
+        println!("block {} ({} txs)", block.height, block.tx.len());
+        let legacy = time("legacy parsing", &|| {
+            block
+                .tx
+                .iter()
+                .filter_map(legacy_inscriptions)
+                .flatten()
+                .count()
+        });
+        let prescan = time("pre-scan parsing", &|| {
+            block
+                .tx
+                .iter()
+                .filter_map(prescan_inscriptions)
+                .flatten()
+                .count()
+        });
+        let decoded = time("decoded output values", &|| {
+            block
+                .tx
+                .iter()
+                .map(|tx| tx.output_sats().unwrap().len())
+                .sum()
+        });
+        let converted = time("converted output values", &|| {
+            block
+                .tx
+                .iter()
+                .map(|tx| tx.vout_sats().unwrap().len())
+                .sum()
+        });
+        assert!(block.tx.iter().all(|tx| {
+            legacy_inscriptions(tx).unwrap_or_default()
+                == prescan_inscriptions(tx).unwrap_or_default()
+                && tx.output_sats().unwrap() == tx.vout_sats().unwrap()
+        }));
+        println!(
+            "speedup: {:.1}x (parsing), {:.1}x (output values)",
+            legacy / prescan,
+            decoded / converted
+        );
+    }
 }
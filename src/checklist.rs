@@ -0,0 +1,942 @@
+use anyhow::{ensure, Result};
+use bitcoin::{OutPoint, ScriptBuf, Transaction};
+
+use crate::inscriptions::JUBILEE_HEIGHT;
+use crate::ord::inscription::Inscription;
+use crate::pb::btc::brc20::v1::{Brc20Events, Token};
+use crate::replay::{BlockOutputs, Replay};
+use crate::tx_builder::{
+    account, address, commit_reveal, commit_tx, external_outpoint, outpoint, text, with_pointer,
+    TxBuilder, POSTAGE,
+};
+use substreams::store::StoreGet;
+
+// Scenarios of the rules of checklist.md, in the same order. Rules which are not enforced yet are
+// marked as expected to fail: their scenario must fail until they are enforced, at which point
+// the mark must be removed and checklist.md regenerated with
+// `cargo test -- --ignored generate_checklist`.
+
+struct Rule {
+    name: &'static str,
+    scenario: fn() -> Result<()>,
+    expected_fail: bool,
+}
+
+macro_rules! rules {
+    ($($name:ident $(=> $expected_fail:ident)?),* $(,)?) => {
+        [$(Rule {
+            name: stringify!($name),
+            scenario: $name,
+            expected_fail: rules!(@expected_fail $($expected_fail)?),
+        }),*]
+    };
+    (@expected_fail) => { false };
+    (@expected_fail expected_fail) => { true };
+}
+
+const RULES: [Rule; 33] = rules![
+    content_type_is_text_plain_or_application_json,
+    content_is_strict_json,
+    surrounding_whitespace_is_trimmed,
+    p_op_and_tick_are_required,
+    deploy_requires_max_with_optional_lim_and_dec => expected_fail,
+    mint_and_transfer_require_amt,
+    op_and_field_names_are_lowercase,
+    numeric_fields_are_strings => expected_fail,
+    numeric_fields_are_well_formed => expected_fail,
+    empty_numeric_fields_are_invalid,
+    zero_is_invalid_except_for_dec => expected_fail,
+    amounts_respect_the_ticker_decimals => expected_fail,
+    dec_is_at_most_18 => expected_fail,
+    numeric_fields_are_at_most_uint64_max => expected_fail,
+    tick_is_4_bytes_and_case_insensitive,
+    operations_inscribed_as_fee_are_ignored,
+    transfers_spent_as_fee_are_returned,
+    mints_above_lim_are_ignored,
+    transfers_above_available_balance_are_ignored => expected_fail,
+    balances_are_tracked_by_script_pub_key => expected_fail,
+    only_the_first_deploy_of_a_ticker_is_valid,
+    mints_stop_at_max_supply,
+    mints_increase_overall_and_available_balances,
+    transfers_are_inscribed_up_to_available_balance => expected_fail,
+    inscribed_transfers_decrease_available_balance,
+    executed_transfers_move_overall_balance,
+    transfers_to_self_restore_available_balance,
+    transfer_inscriptions_are_used_after_first_transfer,
+    moving_deploy_and_mint_inscriptions_changes_no_balance,
+    fee_and_to_keys_are_ignored,
+    cursed_inscriptions_are_ignored,
+    vindicated_inscriptions_are_ignored,
+    transfers_sent_to_op_return_are_burned,
+];
+
+// ================================================================
+// Chain
+// ================================================================
+
+const ALICE: u8 = 0xaa;
+const BOB: u8 = 0xbb;
+const CAROL: u8 = 0xcc;
+const MINER: u8 = 0xdd;
+
+/// Value of the UTXOs funding the transactions of a chain
+const FUNDING: u64 = 100_000;
+
+/// Chain of blocks built transaction by transaction, replayed through the module DAG
+struct Chain {
+    replay: Replay,
+    height: i64,
+    funding: u8,
+    txs: Vec<Transaction>,
+}
+
+impl Chain {
+    fn at(height: i64) -> Self {
+        let funding = (1..=u8::MAX)
+            .map(|byte| (external_outpoint(byte).to_string(), FUNDING))
+            .collect::<Vec<_>>();
+        let funding = funding
+            .iter()
+            .map(|(utxo, sats)| (utxo.as_str(), *sats))
+            .collect::<Vec<_>>();
+
+        Self {
+            replay: Replay::default().with_utxo_values(&funding),
+            height,
+            funding: 0,
+            txs: vec![],
+        }
+    }
+
+    fn new() -> Self {
+        Self::at(779_830)
+    }
+
+    /// Chain in which `ordi` is deployed (max 21000, lim 1000) and Alice minted 500 `ordi`
+    fn with_ordi() -> Self {
+        let mut chain = Self::new();
+        chain.inscribe(deploy(r#""tick":"ordi","max":"21000","lim":"1000""#), ALICE);
+        chain.mine();
+        chain.inscribe(mint("ordi", "500"), ALICE);
+        chain.mine();
+        chain
+    }
+
+    /// Returns an unspent UTXO of `FUNDING` sats
+    fn funding(&mut self) -> OutPoint {
+        self.funding += 1;
+        external_outpoint(self.funding)
+    }
+
+    fn tx(&mut self, tx: Transaction) -> OutPoint {
+        let outpoint = outpoint(&tx, 0);
+        self.txs.push(tx);
+        outpoint
+    }
+
+    /// Inscribes the inscription on the first sat of an output owned by `owner`, and returns
+    /// this output
+    fn inscribe(&mut self, inscription: Inscription, owner: u8) -> OutPoint {
+        let funding = self.funding();
+        let (commit, reveal) = commit_reveal(funding, &[inscription], account(owner), 1_000);
+        self.tx(commit);
+        self.tx(reveal)
+    }
+
+    /// Inscribes the inscription with a reveal transaction without outputs, i.e.: the inscribed
+    /// sat is spent as fee
+    fn inscribe_as_fee(&mut self, inscription: Inscription) {
+        let funding = self.funding();
+        let commit = self.tx(commit_tx(
+            funding,
+            std::slice::from_ref(&inscription),
+            POSTAGE,
+        ));
+        self.tx(TxBuilder::new().reveal(commit, &[inscription]).build());
+    }
+
+    /// Sends the sats of the UTXO to `script_pubkey`
+    fn send_to(&mut self, utxo: OutPoint, script_pubkey: ScriptBuf) -> OutPoint {
+        self.tx(TxBuilder::new()
+            .input(utxo)
+            .output(script_pubkey, POSTAGE)
+            .build())
+    }
+
+    fn send(&mut self, utxo: OutPoint, to: u8) -> OutPoint {
+        self.send_to(utxo, account(to))
+    }
+
+    /// Spends the UTXO after a funding input, without enough outputs for its sats, which are
+    /// spent as fee
+    fn spend_as_fee(&mut self, utxo: OutPoint) {
+        let funding = self.funding();
+        self.tx(TxBuilder::new()
+            .input(funding)
+            .input(utxo)
+            .output(account(MINER), POSTAGE)
+            .build());
+    }
+
+    /// Mines the pending transactions in a block
+    fn mine(&mut self) -> BlockOutputs {
+        let coinbase = TxBuilder::coinbase(self.height)
+            .output(account(MINER), 625_000_000)
+            .build();
+        let txs = [vec![coinbase], std::mem::take(&mut self.txs)].concat();
+        let block = crate::tx_builder::block(self.height, &txs);
+        self.height += 1;
+
+        self.replay.process(block)
+    }
+
+    fn token(&self, tick: &str) -> Option<Token> {
+        self.replay.tokens.get_last(tick)
+    }
+
+    fn available(&self, tick: &str, owner: u8) -> String {
+        balance(&self.replay.balances, tick, owner)
+    }
+
+    fn transferable(&self, tick: &str, owner: u8) -> String {
+        balance(&self.replay.transferable_balances, tick, owner)
+    }
+
+    fn overall(&self, tick: &str, owner: u8) -> String {
+        balance(&self.replay.total_balances, tick, owner)
+    }
+}
+
+fn balance(store: &impl StoreGet<substreams::scalar::BigInt>, tick: &str, owner: u8) -> String {
+    store
+        .get_last(format!("{}:{}", tick, address(&account(owner))))
+        .map(|balance| balance.to_string())
+        .unwrap_or_else(|| "0".into())
+}
+
+fn deploy(fields: &str) -> Inscription {
+    text(&format!(r#"{{"p":"brc-20","op":"deploy",{}}}"#, fields))
+}
+
+fn mint(tick: &str, amt: &str) -> Inscription {
+    crate::tx_builder::mint(tick, amt)
+}
+
+fn transfer(tick: &str, amt: &str) -> Inscription {
+    crate::tx_builder::transfer(tick, amt)
+}
+
+/// Inscribes the content on a chain in which `ordi` is deployed, and returns the resulting
+/// events
+fn events(content_type: &str, content: &str) -> Brc20Events {
+    let mut chain = Chain::with_ordi();
+    chain.inscribe(
+        Inscription::new(
+            Some(content_type.as_bytes().to_vec()),
+            Some(content.as_bytes().to_vec()),
+        ),
+        ALICE,
+    );
+    chain.mine().resolved_events
+}
+
+/// Returns true if inscribing the content (as `text/plain`) results in a valid operation
+fn valid(content: &str) -> bool {
+    valid_with_content_type("text/plain;charset=utf-8", content)
+}
+
+fn valid_with_content_type(content_type: &str, content: &str) -> bool {
+    let events = events(content_type, content);
+    !(events.deploys.is_empty() && events.mints.is_empty() && events.inscribed_transfers.is_empty())
+}
+
+/// Ensures that every content is a valid (or invalid) operation
+fn ensure_valid(contents: &[&str], expected: bool) -> Result<()> {
+    for content in contents {
+        ensure!(
+            valid(content) == expected,
+            "{} should be {}",
+            content,
+            if expected { "valid" } else { "invalid" }
+        );
+    }
+    Ok(())
+}
+
+// ================================================================
+// Scenarios
+// ================================================================
+
+fn content_type_is_text_plain_or_application_json() -> Result<()> {
+    let content = r#"{"p":"brc-20","op":"mint","tick":"ordi","amt":"100"}"#;
+
+    for content_type in [
+        "text/plain",
+        "text/plain;charset=utf-8",
+        "application/json",
+        "application/json;charset=utf-8",
+    ] {
+        ensure!(
+            valid_with_content_type(content_type, content),
+            "{} should be accepted",
+            content_type
+        );
+    }
+    for content_type in ["text/html", " text/plain", "text/plain ;charset=utf-8"] {
+        ensure!(
+            !valid_with_content_type(content_type, content),
+            "{} should be rejected",
+            content_type
+        );
+    }
+    Ok(())
+}
+
+fn content_is_strict_json() -> Result<()> {
+    ensure_valid(
+        &[
+            r#"{"p":"brc-20","op":"mint","tick":"ordi","amt":"100",}"#,
+            r#"{p:"brc-20",op:"mint",tick:"ordi",amt:"100"}"#,
+            r#"{'p':'brc-20','op':'mint','tick':'ordi','amt':'100'}"#,
+        ],
+        false,
+    )
+}
+
+fn surrounding_whitespace_is_trimmed() -> Result<()> {
+    ensure_valid(
+        &["\n \t{\"p\":\"brc-20\",\"op\":\"mint\",\"tick\":\"ordi\",\"amt\":\"100\"}\t\n "],
+        true,
+    )
+}
+
+fn p_op_and_tick_are_required() -> Result<()> {
+    ensure_valid(
+        &[
+            r#"{"op":"mint","tick":"ordi","amt":"100"}"#,
+            r#"{"p":"brc-21","op":"mint","tick":"ordi","amt":"100"}"#,
+            r#"{"p":"brc-20","tick":"ordi","amt":"100"}"#,
+            r#"{"p":"brc-20","op":"burn","tick":"ordi","amt":"100"}"#,
+            r#"{"p":"brc-20","op":"mint","amt":"100"}"#,
+        ],
+        false,
+    )
+}
+
+fn deploy_requires_max_with_optional_lim_and_dec() -> Result<()> {
+    ensure_valid(
+        &[r#"{"p":"brc-20","op":"deploy","tick":"test","lim":"100"}"#],
+        false,
+    )?;
+
+    let mut chain = Chain::new();
+    chain.inscribe(deploy(r#""tick":"test","max":"21000""#), ALICE);
+    chain.mine();
+    let token = chain.token("test");
+    ensure!(
+        token.is_some(),
+        "deploy without lim and dec should be valid"
+    );
+    let token = token.unwrap();
+    ensure!(token.mint_limit == "21000", "lim should default to max");
+    ensure!(token.decimals == 18, "dec should default to 18");
+    Ok(())
+}
+
+fn mint_and_transfer_require_amt() -> Result<()> {
+    ensure_valid(
+        &[
+            r#"{"p":"brc-20","op":"mint","tick":"ordi"}"#,
+            r#"{"p":"brc-20","op":"transfer","tick":"ordi"}"#,
+        ],
+        false,
+    )
+}
+
+fn op_and_field_names_are_lowercase() -> Result<()> {
+    ensure_valid(
+        &[
+            r#"{"p":"brc-20","op":"MINT","tick":"ordi","amt":"100"}"#,
+            r#"{"P":"brc-20","op":"mint","tick":"ordi","amt":"100"}"#,
+            r#"{"p":"brc-20","OP":"mint","tick":"ordi","amt":"100"}"#,
+            r#"{"p":"brc-20","op":"mint","Tick":"ordi","amt":"100"}"#,
+            r#"{"p":"brc-20","op":"mint","tick":"ordi","AMT":"100"}"#,
+        ],
+        false,
+    )
+}
+
+fn numeric_fields_are_strings() -> Result<()> {
+    ensure_valid(
+        &[
+            r#"{"p":"brc-20","op":"mint","tick":"ordi","amt":100}"#,
+            r#"{"p":"brc-20","op":"deploy","tick":"test","max":21000,"lim":"1000"}"#,
+            r#"{"p":"brc-20","op":"deploy","tick":"test","max":"21000","lim":100}"#,
+            r#"{"p":"brc-20","op":"deploy","tick":"test","max":"21000","lim":"1000","dec":8}"#,
+        ],
+        false,
+    )?;
+    ensure_valid(
+        &[
+            r#"{"p":"brc-20","op":"deploy","tick":"test","max":"21000","lim":"1000","dec":"8"}"#,
+            r#"{"p":"brc-20","op":"mint","tick":"ordi","amt":"100","memo":{"id":1}}"#,
+        ],
+        true,
+    )
+}
+
+fn numeric_fields_are_well_formed() -> Result<()> {
+    ensure_valid(
+        &[
+            r#"{"p":"brc-20","op":"mint","tick":"ordi","amt":" 100"}"#,
+            r#"{"p":"brc-20","op":"mint","tick":"ordi","amt":"100 "}"#,
+            r#"{"p":"brc-20","op":"mint","tick":"ordi","amt":"+100"}"#,
+            r#"{"p":"brc-20","op":"mint","tick":"ordi","amt":"-100"}"#,
+            r#"{"p":"brc-20","op":"mint","tick":"ordi","amt":".5"}"#,
+            r#"{"p":"brc-20","op":"mint","tick":"ordi","amt":"5."}"#,
+            r#"{"p":"brc-20","op":"mint","tick":"ordi","amt":"1.2.3"}"#,
+            r#"{"p":"brc-20","op":"deploy","tick":"test","max":"21000","lim":"1000","dec":"1.0"}"#,
+        ],
+        false,
+    )?;
+    ensure_valid(
+        &[r#"{"p":"brc-20","op":"mint","tick":"ordi","amt":"1.5"}"#],
+        true,
+    )
+}
+
+fn empty_numeric_fields_are_invalid() -> Result<()> {
+    ensure_valid(
+        &[
+            r#"{"p":"brc-20","op":"mint","tick":"ordi","amt":""}"#,
+            r#"{"p":"brc-20","op":"transfer","tick":"ordi","amt":""}"#,
+            r#"{"p":"brc-20","op":"deploy","tick":"test","max":"","lim":"1000"}"#,
+            r#"{"p":"brc-20","op":"deploy","tick":"test","max":"21000","lim":""}"#,
+        ],
+        false,
+    )
+}
+
+fn zero_is_invalid_except_for_dec() -> Result<()> {
+    ensure_valid(
+        &[
+            r#"{"p":"brc-20","op":"mint","tick":"ordi","amt":"0"}"#,
+            r#"{"p":"brc-20","op":"transfer","tick":"ordi","amt":"0"}"#,
+            r#"{"p":"brc-20","op":"deploy","tick":"test","max":"0","lim":"1000"}"#,
+            r#"{"p":"brc-20","op":"deploy","tick":"test","max":"21000","lim":"0"}"#,
+        ],
+        false,
+    )?;
+    ensure_valid(
+        &[r#"{"p":"brc-20","op":"deploy","tick":"test","max":"21000","lim":"1000","dec":"0"}"#],
+        true,
+    )
+}
+
+fn amounts_respect_the_ticker_decimals() -> Result<()> {
+    let mint_valid = |amt: &str| {
+        let mut chain = Chain::new();
+        chain.inscribe(
+            deploy(r#""tick":"test","max":"21000","lim":"1000","dec":"2""#),
+            ALICE,
+        );
+        chain.mine();
+        chain.inscribe(mint("test", amt), ALICE);
+        !chain.mine().resolved_events.mints.is_empty()
+    };
+
+    ensure!(mint_valid("1.01"), "1.01 has 2 decimals");
+    ensure!(!mint_valid("1.001"), "1.001 has 3 decimals");
+    ensure!(!mint_valid("1.000"), "1.000 has 3 decimals");
+    Ok(())
+}
+
+fn dec_is_at_most_18() -> Result<()> {
+    ensure_valid(
+        &[r#"{"p":"brc-20","op":"deploy","tick":"test","max":"21000","lim":"1000","dec":"18"}"#],
+        true,
+    )?;
+    ensure_valid(
+        &[r#"{"p":"brc-20","op":"deploy","tick":"test","max":"21000","lim":"1000","dec":"19"}"#],
+        false,
+    )
+}
+
+fn numeric_fields_are_at_most_uint64_max() -> Result<()> {
+    ensure_valid(
+        &[
+            r#"{"p":"brc-20","op":"deploy","tick":"test","max":"18446744073709551615","lim":"1000"}"#,
+        ],
+        true,
+    )?;
+    ensure_valid(
+        &[
+            r#"{"p":"brc-20","op":"deploy","tick":"test","max":"18446744073709551616","lim":"1000"}"#,
+            r#"{"p":"brc-20","op":"transfer","tick":"ordi","amt":"18446744073709551616"}"#,
+        ],
+        false,
+    )
+}
+
+fn tick_is_4_bytes_and_case_insensitive() -> Result<()> {
+    let events = events(
+        "text/plain",
+        r#"{"p":"brc-20","op":"mint","tick":"ORDI","amt":"100"}"#,
+    );
+    ensure!(
+        events.mints.iter().any(|mint| mint.token == "ordi"),
+        "ORDI should be tracked as ordi"
+    );
+
+    ensure_valid(
+        &[r#"{"p":"brc-20","op":"deploy","tick":"😀","max":"21000","lim":"1000"}"#],
+        true,
+    )?;
+    ensure_valid(
+        &[
+            r#"{"p":"brc-20","op":"deploy","tick":"abc","max":"21000","lim":"1000"}"#,
+            r#"{"p":"brc-20","op":"deploy","tick":"abcde","max":"21000","lim":"1000"}"#,
+        ],
+        false,
+    )
+}
+
+fn operations_inscribed_as_fee_are_ignored() -> Result<()> {
+    let mut chain = Chain::with_ordi();
+    chain.inscribe_as_fee(deploy(r#""tick":"test","max":"21000","lim":"1000""#));
+    chain.inscribe_as_fee(mint("ordi", "100"));
+    chain.inscribe_as_fee(transfer("ordi", "100"));
+    let events = chain.mine().resolved_events;
+
+    ensure!(events.deploys.is_empty(), "deploy should be ignored");
+    ensure!(events.mints.is_empty(), "mint should be ignored");
+    ensure!(
+        events.inscribed_transfers.is_empty(),
+        "transfer should be ignored"
+    );
+    ensure!(chain.token("test").is_none(), "test should not be deployed");
+    Ok(())
+}
+
+fn transfers_spent_as_fee_are_returned() -> Result<()> {
+    let mut chain = Chain::with_ordi();
+    let inscription = chain.inscribe(transfer("ordi", "200"), ALICE);
+    chain.mine();
+    chain.spend_as_fee(inscription);
+    let events = chain.mine().resolved_events;
+
+    ensure!(
+        events.returned_transfers.len() == 1,
+        "transfer should be returned"
+    );
+    ensure!(chain.available("ordi", ALICE) == "500", "available balance");
+    ensure!(
+        chain.transferable("ordi", ALICE) == "0",
+        "transferable balance"
+    );
+    ensure!(chain.overall("ordi", ALICE) == "500", "overall balance");
+    Ok(())
+}
+
+fn mints_above_lim_are_ignored() -> Result<()> {
+    let mut chain = Chain::with_ordi();
+    chain.inscribe(mint("ordi", "1001"), ALICE);
+    chain.inscribe(mint("ordi", "1000"), BOB);
+    chain.mine();
+
+    ensure!(chain.overall("ordi", ALICE) == "500", "mint above lim");
+    ensure!(chain.overall("ordi", BOB) == "1000", "mint of lim");
+    Ok(())
+}
+
+fn transfers_above_available_balance_are_ignored() -> Result<()> {
+    let mut chain = Chain::with_ordi();
+    // The minted supply exceeds the transfer, which is only above Alice's balance
+    chain.inscribe(mint("ordi", "500"), BOB);
+    chain.mine();
+    chain.inscribe(transfer("ordi", "501"), ALICE);
+    chain.mine();
+
+    ensure!(chain.available("ordi", ALICE) == "500", "available balance");
+    ensure!(
+        chain.transferable("ordi", ALICE) == "0",
+        "transferable balance"
+    );
+    Ok(())
+}
+
+fn balances_are_tracked_by_script_pub_key() -> Result<()> {
+    let mut chain = Chain::with_ordi();
+    let funding = chain.funding();
+    // Bare `OP_TRUE` script, which has no address
+    let (commit, reveal) = commit_reveal(
+        funding,
+        &[mint("ordi", "100")],
+        ScriptBuf::from_bytes(vec![0x51]),
+        1_000,
+    );
+    chain.tx(commit);
+    chain.tx(reveal);
+
+    ensure!(
+        chain.mine().resolved_events.mints.len() == 1,
+        "mint to a script without address"
+    );
+    Ok(())
+}
+
+fn only_the_first_deploy_of_a_ticker_is_valid() -> Result<()> {
+    let mut chain = Chain::with_ordi();
+    chain.inscribe(deploy(r#""tick":"ORDI","max":"1","lim":"1""#), BOB);
+    chain.inscribe(deploy(r#""tick":"test","max":"21000","lim":"1000""#), ALICE);
+    chain.inscribe(deploy(r#""tick":"TEST","max":"1","lim":"1000""#), BOB);
+    let events = chain.mine().resolved_events;
+
+    ensure!(events.deploys.len() == 1, "only test should be deployed");
+    ensure!(
+        chain.token("ordi").map(|token| token.max_supply) == Some("21000".into()),
+        "ordi should keep its first deploy"
+    );
+    ensure!(
+        chain.token("test").map(|token| token.max_supply) == Some("21000".into()),
+        "test should keep its first deploy"
+    );
+    Ok(())
+}
+
+fn mints_stop_at_max_supply() -> Result<()> {
+    let mut chain = Chain::new();
+    chain.inscribe(deploy(r#""tick":"test","max":"1500","lim":"1000""#), ALICE);
+    chain.mine();
+    chain.inscribe(mint("test", "900"), ALICE);
+    chain.inscribe(mint("test", "900"), BOB);
+    chain.inscribe(mint("test", "900"), CAROL);
+    chain.mine();
+
+    ensure!(chain.overall("test", ALICE) == "900", "first mint");
+    ensure!(chain.overall("test", BOB) == "600", "mint reaching max");
+    ensure!(chain.overall("test", CAROL) == "0", "mint after max");
+
+    chain.inscribe(mint("test", "900"), CAROL);
+    chain.mine();
+
+    ensure!(chain.overall("test", CAROL) == "0", "mint in a later block");
+    Ok(())
+}
+
+fn mints_increase_overall_and_available_balances() -> Result<()> {
+    let chain = Chain::with_ordi();
+
+    ensure!(chain.overall("ordi", ALICE) == "500", "overall balance");
+    ensure!(chain.available("ordi", ALICE) == "500", "available balance");
+    Ok(())
+}
+
+fn transfers_are_inscribed_up_to_available_balance() -> Result<()> {
+    let mut chain = Chain::with_ordi();
+    chain.inscribe(transfer("ordi", "500"), ALICE);
+    chain.mine();
+    chain.inscribe(transfer("ordi", "1"), ALICE);
+    chain.mine();
+
+    ensure!(chain.available("ordi", ALICE) == "0", "available balance");
+    ensure!(
+        chain.transferable("ordi", ALICE) == "500",
+        "transferable balance"
+    );
+    Ok(())
+}
+
+fn inscribed_transfers_decrease_available_balance() -> Result<()> {
+    let mut chain = Chain::with_ordi();
+    chain.inscribe(transfer("ordi", "200"), ALICE);
+    chain.mine();
+
+    ensure!(chain.overall("ordi", ALICE) == "500", "overall balance");
+    ensure!(chain.available("ordi", ALICE) == "300", "available balance");
+    ensure!(
+        chain.transferable("ordi", ALICE) == "200",
+        "transferable balance"
+    );
+    Ok(())
+}
+
+fn executed_transfers_move_overall_balance() -> Result<()> {
+    let mut chain = Chain::with_ordi();
+    let inscription = chain.inscribe(transfer("ordi", "200"), ALICE);
+    chain.mine();
+    chain.send(inscription, BOB);
+    chain.mine();
+
+    ensure!(
+        chain.overall("ordi", ALICE) == "300",
+        "sender's overall balance"
+    );
+    ensure!(
+        chain.available("ordi", ALICE) == "300",
+        "sender's available balance"
+    );
+    ensure!(
+        chain.overall("ordi", BOB) == "200",
+        "receiver's overall balance"
+    );
+    ensure!(
+        chain.available("ordi", BOB) == "200",
+        "receiver's available balance"
+    );
+    Ok(())
+}
+
+fn transfers_to_self_restore_available_balance() -> Result<()> {
+    let mut chain = Chain::with_ordi();
+    let inscription = chain.inscribe(transfer("ordi", "200"), ALICE);
+    chain.mine();
+    let inscription = chain.send(inscription, ALICE);
+    chain.mine();
+
+    ensure!(chain.available("ordi", ALICE) == "500", "available balance");
+    ensure!(
+        chain.transferable("ordi", ALICE) == "0",
+        "transferable balance"
+    );
+
+    chain.send(inscription, BOB);
+    ensure!(
+        chain.mine().resolved_events.executed_transfers.is_empty(),
+        "transfer inscription should be used"
+    );
+    Ok(())
+}
+
+fn transfer_inscriptions_are_used_after_first_transfer() -> Result<()> {
+    let mut chain = Chain::with_ordi();
+    let inscription = chain.inscribe(transfer("ordi", "200"), ALICE);
+    chain.mine();
+    let inscription = chain.send(inscription, BOB);
+    chain.mine();
+    chain.send(inscription, CAROL);
+    let events = chain.mine().resolved_events;
+
+    ensure!(events.executed_transfers.is_empty(), "second transfer");
+    ensure!(
+        chain.overall("ordi", BOB) == "200",
+        "first receiver's balance"
+    );
+    ensure!(
+        chain.overall("ordi", CAROL) == "0",
+        "second receiver's balance"
+    );
+    Ok(())
+}
+
+fn moving_deploy_and_mint_inscriptions_changes_no_balance() -> Result<()> {
+    let mut chain = Chain::new();
+    let deploy = chain.inscribe(deploy(r#""tick":"ordi","max":"21000","lim":"1000""#), ALICE);
+    chain.mine();
+    let mint = chain.inscribe(mint("ordi", "500"), ALICE);
+    chain.mine();
+    chain.send(deploy, BOB);
+    chain.send(mint, BOB);
+    chain.mine();
+
+    ensure!(chain.overall("ordi", ALICE) == "500", "minter's balance");
+    ensure!(chain.overall("ordi", BOB) == "0", "receiver's balance");
+    Ok(())
+}
+
+fn fee_and_to_keys_are_ignored() -> Result<()> {
+    let events = events(
+        "text/plain",
+        r#"{"p":"brc-20","op":"mint","tick":"ordi","amt":"100","to":"bob","fee":"1"}"#,
+    );
+
+    ensure!(events.mints.len() == 1, "mint should be valid");
+    ensure!(
+        events.mints[0].to == address(&account(ALICE)),
+        "mint should go to its inscriber"
+    );
+    Ok(())
+}
+
+fn cursed_inscriptions_are_ignored() -> Result<()> {
+    let mut chain = Chain::with_ordi();
+    // Inscriptions with a pointer are cursed before the jubilee
+    chain.inscribe(with_pointer(mint("ordi", "100"), 0), ALICE);
+
+    ensure!(
+        chain.mine().resolved_events.mints.is_empty(),
+        "cursed mint should be ignored"
+    );
+    Ok(())
+}
+
+fn vindicated_inscriptions_are_ignored() -> Result<()> {
+    let mut chain = Chain::at(JUBILEE_HEIGHT as i64);
+    chain.inscribe(deploy(r#""tick":"ordi","max":"21000","lim":"1000""#), ALICE);
+    chain.mine();
+    // Inscriptions with a pointer would have been cursed before the jubilee
+    chain.inscribe(with_pointer(mint("ordi", "100"), 0), ALICE);
+
+    ensure!(
+        chain.mine().resolved_events.mints.is_empty(),
+        "vindicated mint should be ignored"
+    );
+    Ok(())
+}
+
+fn transfers_sent_to_op_return_are_burned() -> Result<()> {
+    let mut chain = Chain::with_ordi();
+    let inscription = chain.inscribe(transfer("ordi", "200"), ALICE);
+    chain.mine();
+    chain.send_to(inscription, ScriptBuf::new_op_return(b"burn"));
+    let events = chain.mine().resolved_events;
+
+    ensure!(
+        events.burned_transfers.len() == 1,
+        "transfer should be burned"
+    );
+    ensure!(
+        events.returned_transfers.is_empty(),
+        "transfer should not be returned"
+    );
+    ensure!(chain.overall("ordi", ALICE) == "300", "overall balance");
+    ensure!(chain.available("ordi", ALICE) == "300", "available balance");
+    ensure!(
+        chain.transferable("ordi", ALICE) == "0",
+        "transferable balance"
+    );
+    Ok(())
+}
+
+// ================================================================
+// Checklist
+// ================================================================
+
+const CHECKLIST: &str = include_str!("../checklist.md");
+
+/// Runs the scenario of every rule, in the order of the checklist
+fn results() -> Vec<(&'static Rule, Result<()>)> {
+    RULES.iter().map(|rule| (rule, (rule.scenario)())).collect()
+}
+
+/// Returns the checklist with the check state of every rule set from its scenario's result
+fn render_checklist(results: &[(&Rule, Result<()>)]) -> String {
+    let mut results = results.iter();
+
+    CHECKLIST
+        .split_inclusive('\n')
+        .map(|line| {
+            match line
+                .strip_prefix("- [x] ")
+                .or_else(|| line.strip_prefix("- [ ] "))
+            {
+                Some(rule) => {
+                    let (_, result) = results.next().expect("more rules than scenarios");
+                    let check = if result.is_ok() { "x" } else { " " };
+                    format!("- [{}] {}", check, rule)
+                }
+                None => line.to_string(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checklist_rules() {
+        let failures = results()
+            .into_iter()
+            .filter_map(|(rule, result)| match (result, rule.expected_fail) {
+                (Err(err), false) => Some(format!("{}: {}", rule.name, err)),
+                (Ok(_), true) => Some(format!(
+                    "{}: passes, remove its expected fail mark and regenerate checklist.md",
+                    rule.name
+                )),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
+
+    #[test]
+    fn test_checklist_is_up_to_date() {
+        let rules = CHECKLIST
+            .lines()
+            .filter(|line| line.starts_with("- [x] ") || line.starts_with("- [ ] "))
+            .count();
+        assert_eq!(
+            rules,
+            RULES.len(),
+            "every rule of checklist.md needs a scenario"
+        );
+
+        assert!(
+            render_checklist(&results()) == CHECKLIST,
+            "checklist.md is out of date, regenerate it with `cargo test -- --ignored generate_checklist`"
+        );
+    }
+
+    /// Sends a 200 `ordi` transfer inscribed by Alice to the script, and returns the events of the
+    /// block executing it
+    fn transfer_to_script(script_pubkey: ScriptBuf) -> (Chain, Brc20Events) {
+        let mut chain = Chain::with_ordi();
+        let inscription = chain.inscribe(transfer("ordi", "200"), ALICE);
+        chain.mine();
+        chain.send_to(inscription, script_pubkey);
+        let events = chain.mine().resolved_events;
+        (chain, events)
+    }
+
+    #[test]
+    fn test_transfer_to_bare_multisig() {
+        // 1-of-1 bare multisig, which has no address
+        let script_pubkey = ScriptBuf::from_bytes(
+            [vec![0x51, 0x21, 0x02], vec![0x11; 32], vec![0x51, 0xae]].concat(),
+        );
+        let (chain, events) = transfer_to_script(script_pubkey.clone());
+
+        assert_eq!(events.executed_transfers.len(), 1);
+        assert_eq!(
+            events.executed_transfers[0].to,
+            script_pubkey.to_hex_string()
+        );
+        assert_eq!(
+            chain
+                .replay
+                .total_balances
+                .get_last(format!("ordi:{}", script_pubkey.to_hex_string()))
+                .map(|balance| balance.to_string()),
+            Some("200".into())
+        );
+        assert_eq!(chain.transferable("ordi", ALICE), "0");
+        assert_eq!(chain.overall("ordi", ALICE), "300");
+    }
+
+    #[test]
+    fn test_transfer_to_nonstandard_script() {
+        // Bare `OP_TRUE` script, which has no address
+        let (chain, events) = transfer_to_script(ScriptBuf::from_bytes(vec![0x51]));
+        assert_eq!(events.executed_transfers.len(), 1);
+        assert_eq!(events.executed_transfers[0].to, "51");
+        assert_eq!(chain.transferable("ordi", ALICE), "0");
+
+        // Nobody can be credited with the transfer of an output without script
+        let (chain, events) = transfer_to_script(ScriptBuf::new());
+        assert!(events.executed_transfers.is_empty());
+        assert_eq!(events.burned_transfers.len(), 1);
+        assert_eq!(chain.transferable("ordi", ALICE), "0");
+        assert_eq!(chain.overall("ordi", ALICE), "300");
+    }
+
+    /// Regenerates the check state of the rules of checklist.md from their scenarios' results
+    #[test]
+    #[ignore]
+    fn generate_checklist() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/checklist.md");
+        std::fs::write(path, render_checklist(&results())).unwrap();
+    }
+}
@@ -0,0 +1,410 @@
+//! Stateless decoding of the inscriptions revealed by a raw transaction and of their BRC-20
+//! operations, following the same rules as the substreams' modules (see the `brc20-decode`
+//! binary). Rules which depend on the indexer's state (e.g.: whether the ticker is deployed, or
+//! whether the balance is sufficient) are not checked, and stateless rules are those of the given
+//! rule set (see `rules`).
+
+use std::collections::HashMap;
+
+use bitcoin::{consensus::deserialize, Transaction};
+use serde::Serialize;
+
+use crate::{
+    brc20::{self, Brc20Event},
+    btc_utils::{parse_envelopes, to_proto},
+    errors::{Error, Result},
+    inscriptions::curses,
+    ord::{envelope::ParsedEnvelope, inscription::Inscription},
+    pb::sf::bitcoin::r#type::v1 as btc,
+    rules::{Candidate, RuleSet},
+};
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct DecodedTransaction {
+    pub txid: String,
+    pub inscriptions: Vec<DecodedInscription>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct DecodedInscription {
+    pub id: String,
+    /// Index of the input revealing the inscription
+    pub input: u32,
+    /// Index of the envelope within the input's tapscript
+    pub offset: u32,
+    pub content_type: Option<String>,
+    pub content_encoding: Option<String>,
+    pub metaprotocol: Option<String>,
+    pub parent: Option<String>,
+    pub pointer: Option<u64>,
+    pub content_length: usize,
+    /// Content of the inscription, if it is valid UTF-8
+    pub content: Option<String>,
+    pub curses: Vec<String>,
+    pub placement: Placement,
+    /// BRC-20 operation of the inscription, if its content is one
+    pub brc20: Option<Operation>,
+    /// Reason why the inscription is not indexed as a BRC-20 operation, if it is not
+    pub rejection: Option<String>,
+}
+
+/// Location of the sat inscribed by an inscription
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Placement {
+    /// Sat at `offset` within the output `vout` of the transaction
+    Output {
+        vout: u32,
+        offset: u64,
+        address: Option<String>,
+    },
+    /// Sat spent as fee, `offset` being its offset within the transaction's outputs
+    Fee { offset: u64 },
+    /// Sat which cannot be located without the value of an output spent by the transaction
+    Unknown { reason: String },
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Operation {
+    pub op: &'static str,
+    pub tick: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lim: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dec: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amt: Option<String>,
+}
+
+impl From<&Brc20Event> for Operation {
+    fn from(event: &Brc20Event) -> Self {
+        let operation = |op| Operation {
+            op,
+            tick: event.tick(),
+            max: None,
+            lim: None,
+            dec: None,
+            amt: None,
+        };
+
+        match event {
+            Brc20Event::Deploy(deploy) => Operation {
+                max: Some(deploy.max.to_string()),
+                lim: Some(deploy.lim().to_string()),
+                dec: Some(deploy.dec()),
+                ..operation("deploy")
+            },
+            Brc20Event::Mint(mint) => Operation {
+                amt: Some(mint.amt.to_string()),
+                ..operation("mint")
+            },
+            Brc20Event::Transfer(transfer) => Operation {
+                amt: Some(transfer.amt.to_string()),
+                ..operation("transfer")
+            },
+        }
+    }
+}
+
+/// Decodes a hex-encoded raw transaction (see `decode_transaction`)
+pub fn decode_raw_transaction(
+    hex: &str,
+    input_sats: &[u64],
+    rule_set: &RuleSet,
+) -> Result<DecodedTransaction> {
+    let raw = hex::decode(hex.trim()).map_err(|err| Error::Decode(err.to_string()))?;
+    let tx: Transaction = deserialize(&raw).map_err(|err| Error::Decode(err.to_string()))?;
+
+    decode_transaction(&tx, input_sats, rule_set)
+}
+
+/// Decodes the inscriptions revealed by the transaction. `input_sats` are the values of the
+/// outputs spent by the transaction's inputs, in order, which are only needed to locate the
+/// inscriptions revealed after the first input (or pointing past the transaction's outputs).
+pub fn decode_transaction(
+    tx: &Transaction,
+    input_sats: &[u64],
+    rule_set: &RuleSet,
+) -> Result<DecodedTransaction> {
+    let output_sats = tx
+        .output
+        .iter()
+        .map(|output| output.value.to_sat())
+        .collect::<Vec<_>>();
+    let tx = to_proto(tx);
+    let input_sats = tx
+        .vin
+        .iter()
+        .map(btc::Vin::outpoint)
+        .zip(input_sats.iter().copied())
+        .collect::<HashMap<_, _>>();
+
+    let inscriptions = parse_envelopes(&tx)?
+        .into_iter()
+        .enumerate()
+        .map(|(index, envelope)| {
+            let placement = placement(&tx, &envelope, &output_sats, &input_sats)?;
+            let curses = curses(&envelope)
+                .into_iter()
+                .map(|curse| curse.as_str_name().to_string())
+                .collect::<Vec<_>>();
+            let (brc20, rejection) =
+                interpret(&envelope.payload, !curses.is_empty(), &placement, rule_set);
+            let inscription = envelope.payload;
+
+            Ok(DecodedInscription {
+                id: format!("{}i{}", tx.txid, index),
+                input: envelope.input,
+                offset: envelope.offset,
+                content_type: inscription.content_type().map(String::from),
+                content_encoding: inscription
+                    .content_encoding()
+                    .and_then(|encoding| encoding.to_str().ok().map(String::from)),
+                metaprotocol: inscription.metaprotocol().map(String::from),
+                parent: inscription.parent().map(|parent| parent.to_string()),
+                pointer: inscription.pointer(),
+                content_length: inscription.content_length().unwrap_or_default(),
+                content: inscription
+                    .body
+                    .and_then(|body| String::from_utf8(body).ok()),
+                curses,
+                placement,
+                brc20,
+                rejection,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(DecodedTransaction {
+        txid: tx.txid,
+        inscriptions,
+    })
+}
+
+fn placement(
+    tx: &btc::Transaction,
+    envelope: &ParsedEnvelope,
+    output_sats: &[u64],
+    input_sats: &HashMap<String, u64>,
+) -> Result<Placement> {
+    let offset = match tx.inscription_offset(envelope, output_sats, |vin| {
+        input_sats.get(&vin.outpoint()).copied()
+    }) {
+        Ok(offset) => offset,
+        Err(Error::UnknownUtxo(outpoint)) => {
+            return Ok(Placement::Unknown {
+                reason: format!("value of {} is unknown", outpoint),
+            })
+        }
+        Err(err) => return Err(err),
+    };
+
+    Ok(match tx.nth_sat_utxo(output_sats, offset) {
+        Some((vout, offset, _)) => Placement::Output {
+            vout: vout.n,
+            offset,
+            address: vout.address(),
+        },
+        None => Placement::Fee { offset },
+    })
+}
+
+/// Returns the BRC-20 operation of the inscription, if any, along with the first rule (in the
+/// order of `map_brc20_events`) because of which it is not indexed, if any
+fn interpret(
+    inscription: &Inscription,
+    has_curses: bool,
+    placement: &Placement,
+    rule_set: &RuleSet,
+) -> (Option<Operation>, Option<String>) {
+    let event = match inscription.body().map(std::str::from_utf8) {
+        Some(Ok(content)) => {
+            serde_json::from_str::<Brc20Event>(content).map_err(|err| err.to_string())
+        }
+        Some(Err(_)) => Err("content is not valid UTF-8".to_string()),
+        None => Err("inscription has no content".to_string()),
+    };
+    let operation = event.as_ref().ok().map(Operation::from);
+
+    let rejection = if !brc20::is_brc20_content_type(inscription.content_type()) {
+        Some("content type is neither text/plain nor application/json".to_string())
+    } else if let Placement::Fee { .. } = placement {
+        Some("inscription is sent as fee".to_string())
+    } else {
+        match &event {
+            Err(err) => Some(format!("not a BRC-20 operation: {}", err)),
+            Ok(event) => match (
+                rule_set.violation(&Candidate { event, has_curses }),
+                placement,
+            ) {
+                (Some(rule), _) => Some(rule.reason.to_string()),
+                (None, Placement::Output { address: None, .. }) => {
+                    Some("inscribed output has no address".to_string())
+                }
+                (None, _) => None,
+            },
+        }
+    };
+
+    (operation, rejection)
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::consensus::encode::serialize_hex;
+
+    use super::*;
+    use crate::tx_builder::{
+        account, address, external_outpoint, mint, text, with_pointer, TxBuilder, POSTAGE,
+    };
+
+    #[test]
+    fn test_decode_mint() {
+        let tx = TxBuilder::new()
+            .reveal(external_outpoint(1), &[mint("ORDI", "1000")])
+            .output(account(0xaa), POSTAGE)
+            .build();
+
+        let decoded = decode_raw_transaction(&serialize_hex(&tx), &[], RuleSet::latest()).unwrap();
+
+        assert_eq!(decoded.txid, tx.txid().to_string());
+        assert_eq!(decoded.inscriptions.len(), 1);
+        let inscription = &decoded.inscriptions[0];
+        assert_eq!(inscription.id, format!("{}i0", tx.txid()));
+        assert_eq!(
+            inscription.content_type.as_deref(),
+            Some("text/plain;charset=utf-8")
+        );
+        assert!(inscription.curses.is_empty());
+        assert_eq!(
+            inscription.placement,
+            Placement::Output {
+                vout: 0,
+                offset: 0,
+                address: Some(address(&account(0xaa))),
+            }
+        );
+        assert_eq!(
+            inscription.brc20,
+            Some(Operation {
+                op: "mint",
+                tick: "ordi".into(),
+                max: None,
+                lim: None,
+                dec: None,
+                amt: Some("1000".into()),
+            })
+        );
+        assert_eq!(inscription.rejection, None);
+    }
+
+    #[test]
+    fn test_decode_placement() {
+        // Revealed in the second input, then pointed to the second output
+        let tx = TxBuilder::new()
+            .input(external_outpoint(1))
+            .reveal(external_outpoint(2), &[mint("ordi", "1000")])
+            .reveal(
+                external_outpoint(3),
+                &[with_pointer(mint("ordi", "1000"), 1_000)],
+            )
+            .output(account(0xaa), 1_000)
+            .output(account(0xbb), 1_000)
+            .build();
+
+        let placements = |input_sats: &[u64]| {
+            decode_transaction(&tx, input_sats, RuleSet::latest())
+                .unwrap()
+                .inscriptions
+                .into_iter()
+                .map(|inscription| inscription.placement)
+                .collect::<Vec<_>>()
+        };
+        let second_output = Placement::Output {
+            vout: 1,
+            offset: 0,
+            address: Some(address(&account(0xbb))),
+        };
+
+        assert_eq!(
+            placements(&[]),
+            [
+                Placement::Unknown {
+                    reason: format!("value of {} is unknown", external_outpoint(1)),
+                },
+                second_output.clone(),
+            ]
+        );
+        assert_eq!(
+            placements(&[1_500, POSTAGE, POSTAGE]),
+            [
+                Placement::Output {
+                    vout: 1,
+                    offset: 500,
+                    address: Some(address(&account(0xbb))),
+                },
+                second_output.clone(),
+            ]
+        );
+        // The inscribed sat of the first reveal is past the outputs
+        assert_eq!(
+            placements(&[2_000, POSTAGE, POSTAGE]),
+            [Placement::Fee { offset: 2_000 }, second_output]
+        );
+    }
+
+    #[test]
+    fn test_decode_rejections() {
+        let rejection = |inscription: Inscription, outputs: &[(bitcoin::ScriptBuf, u64)]| {
+            let tx = outputs
+                .iter()
+                .fold(
+                    TxBuilder::new().reveal(external_outpoint(1), &[inscription]),
+                    |builder, (script_pubkey, sats)| builder.output(script_pubkey.clone(), *sats),
+                )
+                .build();
+            decode_transaction(&tx, &[], RuleSet::latest())
+                .unwrap()
+                .inscriptions[0]
+                .rejection
+                .clone()
+        };
+        let to_alice = [(account(0xaa), POSTAGE)];
+
+        assert_eq!(rejection(mint("ordi", "1000"), &to_alice), None);
+        assert_eq!(
+            rejection(mint("ordi", "0"), &to_alice).as_deref(),
+            Some("amt must not be zero")
+        );
+        assert_eq!(
+            rejection(mint("ordi", "1000"), &[]).as_deref(),
+            Some("inscription is sent as fee")
+        );
+        assert_eq!(
+            rejection(
+                mint("ordi", "1000"),
+                &[(bitcoin::ScriptBuf::new_op_return(b"burn"), POSTAGE)]
+            )
+            .as_deref(),
+            Some("inscribed output has no address")
+        );
+        assert!(rejection(text("gm"), &to_alice)
+            .unwrap()
+            .starts_with("not a BRC-20 operation"));
+        assert_eq!(
+            rejection(
+                Inscription::new(Some(b"image/png".to_vec()), Some(vec![0x89])),
+                &to_alice
+            )
+            .as_deref(),
+            Some("content type is neither text/plain nor application/json")
+        );
+        // Inscriptions with a pointer would have been cursed before the jubilee
+        assert_eq!(
+            rejection(with_pointer(mint("ordi", "1000"), 0), &to_alice).as_deref(),
+            Some("inscription is cursed or vindicated")
+        );
+    }
+}
@@ -0,0 +1,520 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use substreams::scalar::BigInt;
+use substreams::store::StoreGet;
+
+use crate::pb::btc::brc20::v1::Brc20Events;
+use crate::pb::sf::bitcoin::r#type::v1 as btc;
+use crate::replay::{block_from_json, block_from_proto, prevouts_from_json, MemoryStore, Replay};
+
+// Differential test of the module DAG against the operations and balances of a reference
+// indexer (e.g.: OPI or unisat), dumped locally. Run with
+// `DIFF_BLOCKS=<dir> DIFF_REFERENCE=<dir> cargo test --release -- --ignored differential`,
+// optionally restricted to a range of blocks with `DIFF_FROM` and `DIFF_TO`, and starting from
+// the BRC-20 state of a snapshot with `DIFF_SNAPSHOT` (see `snapshot`).
+
+/// BRC-20 operation. Inscription operations (`deploy-inscribe`, `mint-inscribe` and
+/// `transfer-inscribe`) have no `from`, their `to` being the owner of the inscription.
+/// Transfers (`transfer-transfer`) spent as fee are sent back to `from`, while burned transfers
+/// have no `to`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+pub struct Operation {
+    pub block_height: u64,
+    pub event_type: String,
+    pub inscription_id: String,
+    pub tick: String,
+    #[serde(default)]
+    pub from: String,
+    #[serde(default)]
+    pub to: String,
+    pub amount: String,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} -> {} ({})",
+            self.event_type, self.amount, self.tick, self.from, self.to, self.inscription_id
+        )
+    }
+}
+
+/// Overall and available balances of an account after a block
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Balance {
+    pub block_height: u64,
+    pub tick: String,
+    pub address: String,
+    pub overall_balance: String,
+    pub available_balance: String,
+}
+
+/// Operations and balances of a reference indexer. Balances only need to be given for the
+/// accounts whose balance changed in a block.
+#[derive(Debug, Default)]
+pub struct Reference {
+    pub operations: Vec<Operation>,
+    pub balances: Vec<Balance>,
+}
+
+impl Reference {
+    /// Loads `operations.csv` (or `.json`) and `balances.csv` (or `.json`) from the directory.
+    /// CSV files have a header with the names of the fields, and JSON files are arrays of
+    /// objects.
+    pub fn load(dir: &Path) -> Self {
+        Self {
+            operations: records(dir, "operations"),
+            balances: records(dir, "balances"),
+        }
+    }
+}
+
+fn records<T: DeserializeOwned>(dir: &Path, name: &str) -> Vec<T> {
+    let json = dir.join(format!("{}.json", name));
+    if json.exists() {
+        let content = fs::read_to_string(&json).unwrap();
+        return serde_json::from_str(&content)
+            .unwrap_or_else(|err| panic!("invalid {}: {}", json.display(), err));
+    }
+
+    let csv = dir.join(format!("{}.csv", name));
+    let content = fs::read_to_string(&csv)
+        .unwrap_or_else(|err| panic!("could not read {}: {}", csv.display(), err));
+    let mut lines = content.lines().filter(|line| !line.trim().is_empty());
+    let header = lines
+        .next()
+        .unwrap_or_default()
+        .split(',')
+        .collect::<Vec<_>>();
+
+    lines
+        .enumerate()
+        .map(|(i, line)| {
+            // Numeric fields are kept as strings, which the records parse when needed
+            let record = header
+                .iter()
+                .zip(line.split(','))
+                .map(|(field, value)| {
+                    let value = match value.parse::<u64>() {
+                        Ok(number) if *field == "block_height" => Value::from(number),
+                        _ => Value::from(value),
+                    };
+                    (field.to_string(), value)
+                })
+                .collect::<Map<_, _>>();
+
+            serde_json::from_value(Value::Object(record)).unwrap_or_else(|err| {
+                panic!("invalid record {} of {}: {}", i + 1, csv.display(), err)
+            })
+        })
+        .collect()
+}
+
+/// Block to replay, along with the value of the UTXOs it spends when known
+pub struct SavedBlock {
+    pub block: btc::Block,
+    pub prevouts: Vec<(String, u64)>,
+}
+
+/// Loads the blocks of the directory whose height is within the range, sorted by height.
+/// Blocks are either `{HEIGHT}.json` files in the format of bitcoind's `getblock <hash> 3`
+/// (or 2, in which case the values of the UTXOs they spend must be known from previous blocks)
+/// or `{HEIGHT}.binpb` protobuf encoded `sf.bitcoin.type.v1.Block`s.
+pub fn load_blocks(dir: &Path, from: Option<u64>, to: Option<u64>) -> Vec<SavedBlock> {
+    let mut files = fs::read_dir(dir)
+        .unwrap_or_else(|err| panic!("could not read {}: {}", dir.display(), err))
+        .map(|entry| entry.unwrap().path())
+        .filter_map(|path| {
+            let height = path.file_stem()?.to_str()?.parse::<u64>().ok()?;
+            let in_range = from.map(|from| height >= from).unwrap_or(true)
+                && to.map(|to| height <= to).unwrap_or(true);
+            in_range.then_some((height, path))
+        })
+        .collect::<Vec<(u64, PathBuf)>>();
+    files.sort();
+
+    files
+        .into_iter()
+        .filter_map(|(_, path)| match path.extension()?.to_str()? {
+            "json" => {
+                let json = fs::read_to_string(&path).unwrap();
+                Some(SavedBlock {
+                    block: block_from_json(&json),
+                    prevouts: prevouts_from_json(&json),
+                })
+            }
+            "binpb" => Some(SavedBlock {
+                block: block_from_proto(&fs::read(&path).unwrap()),
+                prevouts: vec![],
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// First difference with the reference
+#[derive(Debug, PartialEq)]
+pub enum Mismatch {
+    Operation {
+        block_height: u64,
+        expected: Option<Operation>,
+        actual: Option<Operation>,
+        expected_operations: Vec<Operation>,
+        actual_operations: Vec<Operation>,
+    },
+    Balance {
+        expected: Balance,
+        actual: Balance,
+        operations: Vec<Operation>,
+    },
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let list = |f: &mut fmt::Formatter, operations: &[Operation]| {
+            operations
+                .iter()
+                .try_for_each(|operation| writeln!(f, "    {}", operation))
+        };
+
+        match self {
+            Mismatch::Operation {
+                block_height,
+                expected,
+                actual,
+                expected_operations,
+                actual_operations,
+            } => {
+                writeln!(f, "Operation mismatch at block {}", block_height)?;
+                match expected {
+                    Some(expected) => writeln!(f, "  expected: {}", expected)?,
+                    None => writeln!(f, "  expected: nothing")?,
+                }
+                match actual {
+                    Some(actual) => writeln!(f, "  actual: {}", actual)?,
+                    None => writeln!(f, "  actual: nothing")?,
+                }
+                writeln!(f, "  expected operations of the block:")?;
+                list(f, expected_operations)?;
+                writeln!(f, "  actual operations of the block:")?;
+                list(f, actual_operations)
+            }
+            Mismatch::Balance {
+                expected,
+                actual,
+                operations,
+            } => {
+                writeln!(
+                    f,
+                    "Balance mismatch at block {} for {} {}",
+                    expected.block_height, expected.tick, expected.address
+                )?;
+                writeln!(
+                    f,
+                    "  expected: overall {}, available {}",
+                    expected.overall_balance, expected.available_balance
+                )?;
+                writeln!(
+                    f,
+                    "  actual: overall {}, available {}",
+                    actual.overall_balance, actual.available_balance
+                )?;
+                writeln!(f, "  operations of the account in the block:")?;
+                list(f, operations)
+            }
+        }
+    }
+}
+
+/// Operations of the block, sorted so that they can be compared regardless of the order of the
+/// events
+pub fn operations(block_height: u64, events: &Brc20Events) -> Vec<Operation> {
+    let operation =
+        |event_type: &str, id: &str, tick: &str, from: &str, to: &str, amount: &str| Operation {
+            block_height,
+            event_type: event_type.into(),
+            inscription_id: id.into(),
+            tick: tick.into(),
+            from: from.into(),
+            to: to.into(),
+            amount: amount.into(),
+        };
+
+    let mut operations = events
+        .deploys
+        .iter()
+        .map(|deploy| {
+            operation(
+                "deploy-inscribe",
+                &deploy.id,
+                &deploy.symbol,
+                "",
+                &deploy.deployer,
+                &deploy.max_supply,
+            )
+        })
+        .chain(events.mints.iter().map(|mint| {
+            operation(
+                "mint-inscribe",
+                &mint.id,
+                &mint.token,
+                "",
+                &mint.to,
+                &mint.amount,
+            )
+        }))
+        .chain(events.inscribed_transfers.iter().map(|transfer| {
+            operation(
+                "transfer-inscribe",
+                &transfer.id,
+                &transfer.token,
+                "",
+                &transfer.from,
+                &transfer.amount,
+            )
+        }))
+        .chain(
+            events
+                .executed_transfers
+                .iter()
+                .chain(events.returned_transfers.iter())
+                .map(|transfer| {
+                    operation(
+                        "transfer-transfer",
+                        &transfer.id,
+                        &transfer.token,
+                        &transfer.from,
+                        &transfer.to,
+                        &transfer.amount,
+                    )
+                }),
+        )
+        .chain(events.burned_transfers.iter().map(|transfer| {
+            operation(
+                "transfer-transfer",
+                &transfer.id,
+                &transfer.token,
+                &transfer.from,
+                "",
+                &transfer.amount,
+            )
+        }))
+        .collect::<Vec<_>>();
+
+    operations.sort();
+    operations
+}
+
+/// Returns the first difference between the reference and the operations and balances
+/// resulting from replaying the blocks (in order) through the module DAG
+pub fn diff(
+    blocks: impl IntoIterator<Item = SavedBlock>,
+    reference: &Reference,
+) -> Result<(), Box<Mismatch>> {
+    diff_from(Replay::default(), blocks, reference)
+}
+
+/// Same as `diff`, starting from the state of the replay (e.g.: restored from a snapshot)
+pub fn diff_from(
+    replay: Replay,
+    blocks: impl IntoIterator<Item = SavedBlock>,
+    reference: &Reference,
+) -> Result<(), Box<Mismatch>> {
+    for saved in blocks {
+        let block_height = saved.block.height as u64;
+        replay.set_utxo_values(
+            saved
+                .prevouts
+                .iter()
+                .map(|(utxo, sats)| (utxo.as_str(), *sats)),
+        );
+
+        let outputs = replay.process(saved.block);
+        let actual_operations = operations(block_height, &outputs.resolved_events);
+        let mut expected_operations = reference
+            .operations
+            .iter()
+            .filter(|operation| operation.block_height == block_height)
+            .cloned()
+            .collect::<Vec<_>>();
+        expected_operations.sort();
+
+        if actual_operations != expected_operations {
+            let (expected, actual) = first_difference(&expected_operations, &actual_operations);
+            return Err(Box::new(Mismatch::Operation {
+                block_height,
+                expected,
+                actual,
+                expected_operations,
+                actual_operations,
+            }));
+        }
+
+        for expected in reference
+            .balances
+            .iter()
+            .filter(|balance| balance.block_height == block_height)
+        {
+            let key = format!("{}:{}", expected.tick, expected.address);
+            let balance = |store: &MemoryStore<BigInt>| {
+                store
+                    .get_last(&key)
+                    .map(|balance| balance.to_string())
+                    .unwrap_or_else(|| "0".into())
+            };
+            let actual = Balance {
+                overall_balance: balance(&replay.total_balances),
+                available_balance: balance(&replay.balances),
+                ..expected.clone()
+            };
+
+            if actual != *expected {
+                return Err(Box::new(Mismatch::Balance {
+                    expected: expected.clone(),
+                    actual,
+                    operations: actual_operations
+                        .into_iter()
+                        .filter(|operation| {
+                            operation.tick == expected.tick
+                                && (operation.from == expected.address
+                                    || operation.to == expected.address)
+                        })
+                        .collect(),
+                }));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the first operation which differs between two sorted lists of operations
+fn first_difference(
+    expected: &[Operation],
+    actual: &[Operation],
+) -> (Option<Operation>, Option<Operation>) {
+    let index = expected
+        .iter()
+        .zip(actual)
+        .position(|(expected, actual)| expected != actual)
+        .unwrap_or_else(|| expected.len().min(actual.len()));
+
+    (expected.get(index).cloned(), actual.get(index).cloned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::Snapshot;
+
+    fn fixtures(name: &str) -> PathBuf {
+        Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures")).join(name)
+    }
+
+    /// Runs the differential test configured by the environment (see the top of this file)
+    #[test]
+    #[ignore]
+    fn differential() {
+        let var = |name: &str| std::env::var(name).ok();
+        let blocks = var("DIFF_BLOCKS").expect("DIFF_BLOCKS must be set");
+        let reference = var("DIFF_REFERENCE").expect("DIFF_REFERENCE must be set");
+        let from = var("DIFF_FROM").map(|from| from.parse().expect("invalid DIFF_FROM"));
+        let to = var("DIFF_TO").map(|to| to.parse().expect("invalid DIFF_TO"));
+
+        let replay = Replay::default();
+        if let Some(snapshot) = var("DIFF_SNAPSHOT") {
+            let snapshot = Snapshot::read(Path::new(&snapshot)).expect("invalid DIFF_SNAPSHOT");
+            snapshot.restore(&replay).expect("invalid DIFF_SNAPSHOT");
+        }
+
+        let blocks = load_blocks(Path::new(&blocks), from, to);
+        let count = blocks.len();
+        let reference = Reference::load(Path::new(&reference));
+        if let Err(mismatch) = diff_from(replay, blocks, &reference) {
+            panic!("{}", mismatch);
+        }
+        println!("{} blocks match the reference", count);
+    }
+
+    #[test]
+    fn test_diff_fixtures() {
+        let blocks = load_blocks(&fixtures("blocks"), None, None);
+        assert_eq!(blocks.len(), 3);
+
+        assert_eq!(
+            diff(blocks, &Reference::load(&fixtures("reference"))),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_diff_reports_first_mismatch() {
+        let mut reference = Reference::load(&fixtures("reference"));
+
+        // Wrong balance after the transfer
+        reference.balances[3].overall_balance = "500".into();
+        let blocks = load_blocks(&fixtures("blocks"), None, None);
+        match diff(blocks, &reference).map_err(|mismatch| *mismatch) {
+            Err(Mismatch::Balance {
+                expected,
+                actual,
+                operations,
+            }) => {
+                assert_eq!(expected.block_height, 779_832);
+                assert_eq!(actual.overall_balance, "400");
+                assert_eq!(operations.len(), 1);
+                assert_eq!(operations[0].event_type, "transfer-transfer");
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+
+        // Missing mint, only the blocks up to the mint are replayed
+        reference.operations.remove(1);
+        let blocks = load_blocks(&fixtures("blocks"), None, Some(779_830));
+        match diff(blocks, &reference).map_err(|mismatch| *mismatch) {
+            Err(Mismatch::Operation {
+                block_height,
+                expected,
+                actual,
+                ..
+            }) => {
+                assert_eq!(block_height, 779_830);
+                assert_eq!(expected, None);
+                assert_eq!(
+                    actual.map(|operation| operation.event_type),
+                    Some("mint-inscribe".into())
+                );
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn test_diff_from_snapshot() {
+        // Only the last block is replayed, from the state after the previous ones
+        let previous = Replay::default();
+        for saved in load_blocks(&fixtures("blocks"), None, Some(779_831)) {
+            previous.set_utxo_values(
+                saved
+                    .prevouts
+                    .iter()
+                    .map(|(utxo, sats)| (utxo.as_str(), *sats)),
+            );
+            previous.process(saved.block);
+        }
+        let replay = Replay::default();
+        Snapshot::export(&previous, 779_831, "")
+            .restore(&replay)
+            .unwrap();
+
+        let reference = Reference::load(&fixtures("reference"));
+        let blocks = load_blocks(&fixtures("blocks"), Some(779_832), None);
+        assert_eq!(diff_from(replay, blocks, &reference), Ok(()));
+    }
+}
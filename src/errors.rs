@@ -0,0 +1,42 @@
+use std::fmt;
+
+/// Errors which can happen while processing a block.
+///
+/// Errors caused by the content of a block (e.g.: a malformed transaction) are reported and the
+/// offending transaction (or input) is skipped, while errors caused by the substream's own data
+/// (e.g.: a malformed store key) are returned as module errors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// Hex or consensus encoded data which could not be decoded
+    Decode(String),
+    /// Script which could not be parsed (e.g.: invalid push in a tapscript)
+    Script(String),
+    /// Amount which could not be parsed or converted
+    Amount(String),
+    /// Store key which does not have the expected format
+    StoreKey(String),
+    /// UTXO whose value is not known (e.g.: not in the UTXO values store)
+    UnknownUtxo(String),
+    /// Module params which could not be parsed (e.g.: unknown rule set)
+    Params(String),
+    /// Module graph which substreams cannot run (e.g.: a store taking itself as input)
+    Manifest(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Decode(msg) => write!(f, "decode error: {}", msg),
+            Error::Script(msg) => write!(f, "script error: {}", msg),
+            Error::Amount(msg) => write!(f, "invalid amount: {}", msg),
+            Error::StoreKey(msg) => write!(f, "invalid store key: {}", msg),
+            Error::UnknownUtxo(msg) => write!(f, "unknown UTXO: {}", msg),
+            Error::Params(msg) => write!(f, "invalid params: {}", msg),
+            Error::Manifest(msg) => write!(f, "invalid manifest: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,594 @@
+use std::collections::HashMap;
+
+use crate::{
+    btc_utils::parse_envelopes,
+    errors::Result,
+    ord::envelope::ParsedEnvelope,
+    ordinals::{sat_at, sat_destination},
+    pb::{
+        ordinals::v1::{
+            self as ord_pb, Curse, InscriptionLocation, InscriptionTransfer, OrdinalBlock,
+            UtxoSatRanges, UtxoSpend,
+        },
+        sf::bitcoin::r#type::v1 as btc,
+    },
+};
+
+/// Height of the block from which cursed inscriptions are numbered as blessed inscriptions
+pub const JUBILEE_HEIGHT: u64 = 824_544;
+
+/// Number of blessed and cursed inscriptions
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct InscriptionCounts {
+    pub blessed: i64,
+    pub cursed: i64,
+}
+
+impl InscriptionCounts {
+    /// Counts a new inscription and returns its number
+    fn next_number(&mut self, cursed: bool) -> i64 {
+        if cursed {
+            self.cursed += 1;
+            -self.cursed
+        } else {
+            self.blessed += 1;
+            self.blessed - 1
+        }
+    }
+}
+
+/// Returns the curses of a new inscription, following ord's rules (in the same order)
+/// Note: Reinscriptions are not detected, since it requires knowing which sats are inscribed.
+pub fn curses(envelope: &ParsedEnvelope) -> Vec<Curse> {
+    let inscription = &envelope.payload;
+
+    [
+        (
+            inscription.unrecognized_even_field,
+            Curse::UnrecognizedEvenField,
+        ),
+        (inscription.duplicate_field, Curse::DuplicateField),
+        (inscription.incomplete_field, Curse::IncompleteField),
+        (envelope.input != 0, Curse::NotInFirstInput),
+        (envelope.offset != 0, Curse::NotAtOffsetZero),
+        (inscription.pointer.is_some(), Curse::Pointer),
+        (envelope.pushnum, Curse::Pushnum),
+        (envelope.stutter, Curse::Stutter),
+    ]
+    .into_iter()
+    .filter_map(|(cursed, curse)| cursed.then_some(curse))
+    .collect()
+}
+
+/// Returns the inscription envelopes of the block, along with their transaction, the index of
+/// their transaction and their index within their transaction (i.e.: the index of their
+/// inscription ID). Transactions whose envelopes cannot be parsed are reported and skipped.
+fn block_envelopes(block: &btc::Block) -> Vec<(usize, &btc::Transaction, usize, ParsedEnvelope)> {
+    block
+        .tx
+        .iter()
+        .enumerate()
+        // Cheap pre-scan of the witness data for the envelope marker
+        .filter(|(_, tx)| tx.may_contain_inscriptions())
+        .flat_map(|(tx_index, tx)| match parse_envelopes(tx) {
+            Ok(envelopes) => envelopes
+                .into_iter()
+                .enumerate()
+                .map(|(index, envelope)| (tx_index, tx, index, envelope))
+                .collect(),
+            Err(err) => {
+                substreams::log::info!("Error parsing inscriptions in tx {}: {}", tx.txid, err);
+                vec![]
+            }
+        })
+        .collect()
+}
+
+/// Returns the number of blessed and cursed inscriptions revealed in the block
+pub fn block_inscription_counts(block: &btc::Block) -> InscriptionCounts {
+    let mut counts = InscriptionCounts::default();
+
+    block_envelopes(block)
+        .iter()
+        .for_each(|(_, _, _, envelope)| {
+            counts.next_number(is_cursed(block.height as u64, envelope));
+        });
+
+    counts
+}
+
+fn is_cursed(height: u64, envelope: &ParsedEnvelope) -> bool {
+    height < JUBILEE_HEIGHT && !curses(envelope).is_empty()
+}
+
+/// Returns the inscriptions revealed in the block, numbered from the given counts (i.e.: the
+/// number of blessed and cursed inscriptions revealed before the block). Inscriptions which
+/// cannot be located are reported and skipped, but are still numbered.
+pub fn block_inscriptions(
+    block: &btc::Block,
+    counts: InscriptionCounts,
+    input_sats: impl Fn(&btc::Vin) -> Option<u64>,
+) -> Vec<ord_pb::Inscription> {
+    let height = block.height as u64;
+    let mut counts = counts;
+    // Values of the outputs of the transactions revealing inscriptions, by transaction index
+    let mut output_sats = HashMap::new();
+
+    block_envelopes(block)
+        .into_iter()
+        .filter_map(|(tx_index, tx, index, envelope)| {
+            let number = counts.next_number(is_cursed(height, &envelope));
+            let id = format!("{}i{}", tx.txid, index);
+
+            let inscription = match output_sats
+                .entry(tx_index)
+                .or_insert_with(|| tx.output_sats())
+            {
+                Ok(output_sats) => inscription(
+                    tx_index,
+                    tx,
+                    id.clone(),
+                    number,
+                    envelope,
+                    output_sats,
+                    &input_sats,
+                ),
+                Err(err) => Err(err.clone()),
+            };
+
+            match inscription {
+                Ok(inscription) => Some(inscription),
+                Err(err) => {
+                    substreams::log::info!("Error locating inscription {}: {}", id, err);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+fn inscription(
+    tx_index: usize,
+    tx: &btc::Transaction,
+    id: String,
+    number: i64,
+    envelope: ParsedEnvelope,
+    output_sats: &[u64],
+    input_sats: impl Fn(&btc::Vin) -> Option<u64>,
+) -> Result<ord_pb::Inscription> {
+    let curses = curses(&envelope);
+    let offset = tx.inscription_offset(&envelope, output_sats, input_sats)?;
+    // Inscriptions whose sat is spent as fee have no genesis location
+    let location = tx.nth_sat_utxo(output_sats, offset);
+    let payload = envelope.payload;
+
+    Ok(ord_pb::Inscription {
+        id,
+        content_type: payload.content_type().map(String::from),
+        pointer: payload.pointer().map(|pointer| pointer as i64),
+        parent: payload.parent().map(|parent| parent.to_string()),
+        metaprotocol: payload.metaprotocol().map(String::from),
+        content_encoding: payload
+            .content_encoding()
+            .and_then(|encoding| encoding.to_str().ok().map(String::from)),
+        content_length: payload.content_length().unwrap_or_default() as u64,
+        number,
+        curses: curses.into_iter().map(|curse| curse as i32).collect(),
+        genesis_utxo: location
+            .as_ref()
+            .map(|(vout, _, _)| format!("{}:{}", tx.txid, vout.n))
+            .unwrap_or_default(),
+        genesis_offset: location
+            .as_ref()
+            .map(|(_, offset, _)| *offset)
+            .unwrap_or_default(),
+        genesis_utxo_amount: location
+            .as_ref()
+            .map(|(_, _, utxo_amount)| *utxo_amount)
+            .unwrap_or_default(),
+        genesis_address: location.and_then(|(vout, _, _)| vout.address()),
+        tx_index: tx_index as u64,
+        metadata: payload.metadata,
+        content: payload.body.unwrap_or_default(),
+    })
+}
+
+/// Number of bits by which the sizes of the buckets of two consecutive levels of the inscribed
+/// sat index differ (see `inscribed_sats`)
+const SAT_BUCKET_BITS: u32 = 4;
+
+/// Shift of the largest buckets of the inscribed sat index, which split every sat into a
+/// handful of buckets
+const SAT_BUCKET_MAX_SHIFT: u32 = 48;
+
+/// Returns the key of the bucket of sats `[bucket << shift, (bucket + 1) << shift)` in the
+/// inscribed sat index
+fn sat_bucket_key(shift: u32, bucket: u64) -> String {
+    format!("{}:{}", shift, bucket)
+}
+
+/// Returns the key counting the inscriptions revealed on a sat in the inscribed sat index
+pub fn inscribed_sat_key(sat: u64) -> String {
+    sat_bucket_key(0, sat)
+}
+
+/// Returns the keys of every bucket of the inscribed sat index containing the sat, from the
+/// sat itself to the largest bucket
+pub fn inscribed_sat_keys(sat: u64) -> Vec<String> {
+    (0..=SAT_BUCKET_MAX_SHIFT)
+        .step_by(SAT_BUCKET_BITS as usize)
+        .map(|shift| sat_bucket_key(shift, sat >> shift))
+        .collect()
+}
+
+/// Returns the sats of the range `[start, start + size)` on which inscriptions were revealed, in
+/// order, along with the number of inscriptions revealed on each of them. The inscribed sat
+/// index counts the inscriptions revealed in nested buckets of sats, looked up with `count`
+/// (e.g.: from `store_inscribed_sats`), so that only the non-empty buckets are descended into.
+pub fn inscribed_sats(start: u64, size: u64, count: impl Fn(&str) -> i64) -> Vec<(u64, u64)> {
+    if size == 0 {
+        return vec![];
+    }
+
+    let last = start + size - 1;
+    let mut shift = SAT_BUCKET_MAX_SHIFT;
+    let mut buckets: Vec<u64> = (start >> shift..=last >> shift).collect();
+    loop {
+        let counts: Vec<(u64, u64)> = buckets
+            .into_iter()
+            .filter_map(|bucket| {
+                let count = count(&sat_bucket_key(shift, bucket));
+                (count > 0).then_some((bucket, count as u64))
+            })
+            .collect();
+        if shift == 0 {
+            return counts;
+        }
+
+        // Sub-buckets of the non-empty buckets, within the range
+        shift -= SAT_BUCKET_BITS;
+        buckets = counts
+            .into_iter()
+            .flat_map(|(bucket, _)| {
+                let first = bucket << SAT_BUCKET_BITS;
+                let sub_last = first + (1 << SAT_BUCKET_BITS) - 1;
+                first.max(start >> shift)..=sub_last.min(last >> shift)
+            })
+            .collect();
+    }
+}
+
+/// Returns the sat on which each inscription of the block was revealed, from the absolute
+/// ranges of the UTXOs created by the block (see `map_sat_ranges`). Inscriptions whose sat was
+/// spent as fee, or whose genesis UTXO holds sats whose ranges are unknown, are left out.
+pub fn inscription_sats<'a>(
+    inscriptions: &'a [ord_pb::Inscription],
+    created: &[UtxoSatRanges],
+) -> Vec<(&'a ord_pb::Inscription, u64)> {
+    let created: HashMap<&str, &UtxoSatRanges> = created
+        .iter()
+        .map(|utxo| (utxo.utxo.as_str(), utxo))
+        .collect();
+
+    inscriptions
+        .iter()
+        .filter_map(|inscription| {
+            let utxo = created.get(inscription.genesis_utxo.as_str())?;
+            let sat = sat_at(&utxo.ranges, inscription.genesis_offset)?;
+            Some((inscription, sat))
+        })
+        .collect()
+}
+
+/// Returns the transfers of the inscriptions moved in the block, in order. Inscriptions are
+/// bound to the sat on which they were revealed, so the inscriptions held by a spent UTXO (see
+/// `UtxoSpend`) are the ones revealed on the sats of its absolute ranges, looked up with
+/// `spent_ranges` (e.g.: from `map_sat_ranges`), before the spending transaction. These are
+/// looked up with `inscriptions_at`, given the index of the spending transaction in the block
+/// and a range of sats, as IDs by inscribed sat (see `inscribed_sats`).
+pub fn block_inscription_transfers<'a>(
+    spends: &[UtxoSpend],
+    spent_ranges: impl Fn(&str) -> Option<&'a UtxoSatRanges>,
+    inscriptions_at: impl Fn(u64, &OrdinalBlock) -> Vec<(u64, Vec<String>)>,
+) -> Vec<InscriptionTransfer> {
+    let mut transfers = vec![];
+    for spend in spends {
+        let held = match spent_ranges(&spend.utxo) {
+            Some(held) => held,
+            None => continue,
+        };
+
+        let mut offset = 0;
+        for range in &held.ranges {
+            for (sat, ids) in inscriptions_at(spend.tx_index, range) {
+                let sat_offset = offset + sat - range.start;
+                let to = sat_destination(spend, sat_offset);
+                transfers.extend(ids.into_iter().map(|id| InscriptionTransfer {
+                    inscription_id: id,
+                    txid: spend.txid.clone(),
+                    from: Some(InscriptionLocation {
+                        utxo: spend.utxo.clone(),
+                        offset: sat_offset,
+                        address: held.address.clone(),
+                    }),
+                    to: to.clone(),
+                }));
+            }
+            offset += range.size;
+        }
+    }
+
+    transfers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ord::inscription::Inscription,
+        tx_builder::{account, external_outpoint, text, to_proto, with_pointer, TxBuilder},
+    };
+
+    /// Builds a reveal transaction with the given inscriptions in its first input and a
+    /// single 546 sats output
+    fn reveal_tx(txid: &str, inscriptions: &[Inscription]) -> btc::Transaction {
+        let tx = TxBuilder::new()
+            .reveal(external_outpoint(0xa0), inscriptions)
+            .output(account(0xaa), 546)
+            .build();
+
+        btc::Transaction {
+            txid: txid.into(),
+            ..to_proto(&tx)
+        }
+    }
+
+    fn block(height: i64) -> btc::Block {
+        btc::Block {
+            height,
+            tx: vec![
+                btc::Transaction::default(),
+                reveal_tx("aa", &[text("first"), text("second")]),
+                reveal_tx("bb", &[with_pointer(text("third"), 100)]),
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_block_inscriptions() {
+        let block = block(800_000);
+        let counts = InscriptionCounts {
+            blessed: 10,
+            cursed: 2,
+        };
+
+        assert_eq!(
+            block_inscription_counts(&block),
+            InscriptionCounts {
+                blessed: 1,
+                cursed: 2,
+            }
+        );
+
+        let inscriptions = block_inscriptions(&block, counts, |_| None);
+        let summary = inscriptions
+            .iter()
+            .map(|inscription| {
+                (
+                    inscription.id.as_str(),
+                    inscription.number,
+                    inscription.curses.clone(),
+                    inscription.tx_index,
+                    inscription.genesis_utxo.as_str(),
+                    inscription.content.as_slice(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            summary,
+            vec![
+                ("aai0", 10, vec![], 1, "aa:0", b"first".as_slice()),
+                (
+                    "aai1",
+                    -3,
+                    vec![Curse::NotAtOffsetZero as i32],
+                    1,
+                    "aa:0",
+                    b"second".as_slice()
+                ),
+                (
+                    "bbi0",
+                    -4,
+                    vec![Curse::Pointer as i32],
+                    2,
+                    "bb:0",
+                    b"third".as_slice()
+                ),
+            ]
+        );
+        assert_eq!(inscriptions[0].content_length, 5);
+        assert_eq!(inscriptions[0].genesis_utxo_amount, 546);
+        assert_eq!(inscriptions[2].pointer, Some(100));
+        assert_eq!(inscriptions[2].genesis_offset, 100);
+    }
+
+    #[test]
+    fn test_jubilee() {
+        let block = block(JUBILEE_HEIGHT as i64);
+
+        assert_eq!(
+            block_inscription_counts(&block),
+            InscriptionCounts {
+                blessed: 3,
+                cursed: 0,
+            }
+        );
+
+        let numbers = block_inscriptions(&block, InscriptionCounts::default(), |_| None)
+            .iter()
+            .map(|inscription| (inscription.number, inscription.curses.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(numbers, vec![(0, 0), (1, 1), (2, 1)]);
+    }
+
+    #[test]
+    fn test_inscribed_sats() {
+        let mut counts: HashMap<String, i64> = HashMap::new();
+        for sat in [3, 17, 17, 1 << 40, 2_100_000_000_000_005] {
+            for key in inscribed_sat_keys(sat) {
+                *counts.entry(key).or_default() += 1;
+            }
+        }
+        let lookups = std::cell::Cell::new(0);
+        let count = |key: &str| {
+            lookups.set(lookups.get() + 1);
+            counts.get(key).copied().unwrap_or_default()
+        };
+
+        assert_eq!(inscribed_sats(0, 20, count), vec![(3, 1), (17, 2)]);
+        assert_eq!(inscribed_sats(4, 13, count), vec![]);
+        assert_eq!(inscribed_sats(4, 14, count), vec![(17, 2)]);
+        assert_eq!(inscribed_sats(17, 0, count), vec![]);
+        assert_eq!(
+            inscribed_sats(0, 2_100_000_000_000_010, count),
+            vec![(3, 1), (17, 2), (1 << 40, 1), (2_100_000_000_000_005, 1)]
+        );
+
+        // Empty buckets are not descended into
+        lookups.set(0);
+        assert_eq!(inscribed_sats(1 << 50, 1_000_000, count), vec![]);
+        assert_eq!(lookups.get(), 1);
+    }
+
+    #[test]
+    fn test_inscription_sats() {
+        let inscription = |id: &str, utxo: &str, offset: u64| ord_pb::Inscription {
+            id: id.into(),
+            genesis_utxo: utxo.into(),
+            genesis_offset: offset,
+            ..Default::default()
+        };
+        let created = [UtxoSatRanges {
+            utxo: "bb:0".into(),
+            address: None,
+            ranges: vec![
+                OrdinalBlock {
+                    utxo: "".into(),
+                    address: None,
+                    start: 100,
+                    size: 10,
+                },
+                OrdinalBlock {
+                    utxo: "".into(),
+                    address: None,
+                    start: 500,
+                    size: 10,
+                },
+            ],
+        }];
+        // `bbi1` is spent as fee, and the ranges of the sats of `cc:0` are unknown
+        let inscriptions = [
+            inscription("bbi0", "bb:0", 12),
+            inscription("bbi1", "", 0),
+            inscription("cci0", "cc:0", 0),
+        ];
+
+        assert_eq!(
+            inscription_sats(&inscriptions, &created)
+                .into_iter()
+                .map(|(inscription, sat)| (inscription.id.as_str(), sat))
+                .collect::<Vec<_>>(),
+            vec![("bbi0", 502)]
+        );
+    }
+
+    #[test]
+    fn test_block_inscription_transfers() {
+        let block = |utxo: &str, start: u64, size: u64| OrdinalBlock {
+            utxo: utxo.into(),
+            address: None,
+            start,
+            size,
+        };
+        let spend = |utxo: &str, txid: &str, tx_index: u64, blocks: Vec<OrdinalBlock>| UtxoSpend {
+            utxo: utxo.into(),
+            txid: txid.into(),
+            tx_index,
+            blocks,
+        };
+        // `aa:0` and `bb:0` hold the same sats, as `bb` spends `aa:0` to `bb:0`
+        let spent = |utxo: &str| UtxoSatRanges {
+            utxo: utxo.into(),
+            address: Some("alice".into()),
+            ranges: vec![block("", 1_000, 100), block("", 5_000, 600)],
+        };
+        let spent = [spent("aa:0"), spent("bb:0")];
+        let spends = [
+            spend("aa:0", "bb", 1, vec![block("bb:0", 0, 700)]),
+            spend(
+                "bb:0",
+                "cc",
+                2,
+                vec![block("cc:0", 0, 200), block("", 0, 500)],
+            ),
+            spend("dd:0", "cc", 2, vec![block("cc:0", 200, 546)]),
+        ];
+        // `xxi1` is a reinscription of the sat of `xxi0`, and `bbi0` is revealed by `bb`, which
+        // spends its sat
+        let inscribed = [
+            (1_010, 0, "aai0"),
+            (1_050, 1, "bbi0"),
+            (5_100, 0, "xxi0"),
+            (5_100, 0, "xxi1"),
+        ];
+        let inscriptions_at = |tx_index: u64, range: &OrdinalBlock| {
+            let mut sats: Vec<(u64, Vec<String>)> = vec![];
+            for (sat, revealed_in, id) in inscribed {
+                if revealed_in >= tx_index || sat < range.start || sat >= range.start + range.size {
+                    continue;
+                }
+                match sats.last_mut() {
+                    Some((last, ids)) if *last == sat => ids.push(id.into()),
+                    _ => sats.push((sat, vec![id.into()])),
+                }
+            }
+            sats
+        };
+
+        let transfers = block_inscription_transfers(
+            &spends,
+            |utxo| spent.iter().find(|spent| spent.utxo == utxo),
+            inscriptions_at,
+        );
+
+        assert_eq!(
+            transfers
+                .iter()
+                .map(|transfer| (
+                    transfer.inscription_id.as_str(),
+                    transfer.txid.as_str(),
+                    transfer
+                        .from
+                        .as_ref()
+                        .map(|from| (from.utxo.as_str(), from.offset)),
+                    transfer.to.as_ref().map(|to| (to.utxo.as_str(), to.offset))
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                ("aai0", "bb", Some(("aa:0", 10)), Some(("bb:0", 10))),
+                ("xxi0", "bb", Some(("aa:0", 200)), Some(("bb:0", 200))),
+                ("xxi1", "bb", Some(("aa:0", 200)), Some(("bb:0", 200))),
+                ("aai0", "cc", Some(("bb:0", 10)), Some(("cc:0", 10))),
+                ("bbi0", "cc", Some(("bb:0", 50)), Some(("cc:0", 50))),
+                ("xxi0", "cc", Some(("bb:0", 200)), None),
+                ("xxi1", "cc", Some(("bb:0", 200)), None),
+            ]
+        );
+        assert_eq!(
+            transfers[0].from.as_ref().unwrap().address,
+            Some("alice".into())
+        );
+    }
+}
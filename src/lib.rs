@@ -1,396 +1,322 @@
-mod brc20;
-mod btc_utils;
-mod ord;
-mod pb;
+pub mod bitcoind;
+pub mod brc20;
+pub mod btc_utils;
+#[cfg(test)]
+mod checklist;
+pub mod decode;
+#[cfg(test)]
+mod differential;
+pub mod errors;
+pub mod inscriptions;
+mod modules;
+pub mod ord;
+mod ordinals;
+pub mod pb;
+pub mod replay;
+pub mod rules;
+mod sinks;
+pub mod snapshot;
 mod tables_utils;
+#[cfg(test)]
+mod tx_builder;
 
-use std::str::FromStr;
-
-use anyhow::Result;
-use brc20::Brc20Event;
-use btc_utils::{btc_to_sats, parse_inscriptions};
-use pb::btc::brc20::v1::{
-    Brc20Events, Deploy, ExecutedTransfer, InscribedTransfer, InscribedTransferLocation, Mint,
-    Token,
+use pb::btc::brc20::v1::{Brc20Events, InscribedTransferLocations, PendingTransfers, Token};
+use pb::ordinals::v1::{
+    self as ord_pb, InscriptionLocation, InscriptionParents, InscriptionTransfers, OrdinalBlocks,
+    SatRanges, UtxoSpends,
 };
 use pb::sf::bitcoin::r#type::v1 as btc;
-use substreams::pb::substreams::store_delta::Operation;
 use substreams::pb::substreams::Clock;
-use substreams::scalar::BigInt;
 use substreams::store::{
-    DeltaBigInt, Deltas, StoreAdd, StoreAddBigInt, StoreGet, StoreGetProto, StoreNew, StoreSet,
-    StoreSetProto,
+    DeltaBigInt, DeltaInt64, DeltaProto, Deltas, StoreAddBigInt, StoreAddInt64, StoreGetBigInt,
+    StoreGetInt64, StoreGetProto, StoreGetString, StoreNew, StoreSetIfNotExistsInt64,
+    StoreSetIfNotExistsProto, StoreSetInt64, StoreSetProto, StoreSetString,
 };
+use substreams_database_change::pb::database::DatabaseChanges;
 use substreams_entity_change::pb::entity::EntityChanges;
-use substreams_entity_change::tables::Tables;
 
-struct Location {
-    pub utxo: String,
-    pub offset: u64,
-    pub utxo_amount: u64,
+// Handlers only delegate to `modules`, whose functions are generic over the store traits so that
+// the module DAG can be replayed natively with in-memory stores (see `replay`)
+
+#[substreams::handlers::store]
+fn store_utxo_values(block: btc::Block, store: StoreSetInt64) {
+    modules::store_utxo_values(block, &store);
+}
+
+#[substreams::handlers::map]
+fn map_ordinals(
+    block: btc::Block,
+    utxo_values_store: StoreGetInt64,
+) -> Result<ord_pb::Block, substreams::errors::Error> {
+    modules::map_ordinals(block, &utxo_values_store)
+}
+
+#[substreams::handlers::store]
+fn store_ordinal_blocks(
+    block: btc::Block,
+    ordinals: ord_pb::Block,
+    utxo_values_store: StoreGetInt64,
+    store: StoreSetProto<OrdinalBlocks>,
+) {
+    modules::store_ordinal_blocks(block, ordinals, &utxo_values_store, &store);
 }
 
 #[substreams::handlers::map]
-fn map_brc20_events(block: btc::Block) -> Result<Brc20Events, substreams::errors::Error> {
-    let events = block
-        .tx
-        .into_iter()
-        // Filter if tx data contains "text/plain;charset=utf-8" inscriptions
-        .filter(|tx| {
-            tx.hex
-                .contains("746578742f706c61696e3b636861727365743d7574662d38")
-        })
-        .flat_map(|tx| {
-            let txid = tx.txid.clone();
-            match parse_inscriptions(&tx) {
-                Ok(inscriptions) => inscriptions
-                    .into_iter()
-                    .filter(|inscription| {
-                        match inscription
-                            .content_type()
-                            .map(|ctype| ctype.split(";"))
-                            .and_then(|mut parts| parts.next())
-                        {
-                            Some(content_type) => {
-                                content_type == "text/plain" || content_type == "application/json"
-                            }
-                            None => false,
-                        }
-                    })
-                    .filter_map(|inscription| {
-                        let (vout, offset) = tx.nth_sat_utxo(inscription.pointer().unwrap_or(0))?;
-                        Some((
-                            Location {
-                                utxo: format!("{}:{}", tx.txid, offset),
-                                offset,
-                                utxo_amount: btc_to_sats(vout.value),
-                            },
-                            vout.address(),
-                            inscription,
-                        ))
-                    })
-                    .collect(),
-                Err(err) => {
-                    substreams::log::info!("Error parsing inscriptions in tx {}: {}", txid, err);
-                    vec![]
-                }
-            }
-        })
-        .filter_map(|(location, address, inscription)| {
-            let content = if let Ok(content) =
-                String::from_utf8(inscription.body().unwrap_or_default().to_vec())
-            {
-                content
-            } else {
-                return None;
-            };
-
-            match serde_json::from_str::<Brc20Event>(&content) {
-                Ok(event) if event.valid() => Some((location, address, event)),
-                Ok(_) => None,
-                Err(err) => {
-                    substreams::log::info!(
-                        "Error parsing inscription content {}: {}",
-                        location.utxo,
-                        err
-                    );
-                    None
-                }
-            }
-        })
-        .collect::<Vec<_>>();
-
-    Ok(Brc20Events {
-        deploys: events
-            .iter()
-            .filter_map(|(_, address, event)| match (address, event) {
-                (Some(address), Brc20Event::Deploy(deploy)) => Some(Deploy {
-                    id: "".into(),
-                    symbol: deploy.tick(),
-                    max_supply: deploy.max.to_string(),
-                    mint_limit: deploy.lim().to_string(),
-                    decimals: deploy.dec(),
-                    deployer: address.clone(),
-                }),
-                _ => None,
-            })
-            .collect(),
-        mints: events
-            .iter()
-            .filter_map(|(_, address, event)| match (address, event) {
-                (Some(address), Brc20Event::Mint(mint)) => Some(Mint {
-                    id: "".into(),
-                    token: mint.tick(),
-                    to: address.into(),
-                    amount: mint.amt.to_string(),
-                }),
-                _ => None,
-            })
-            .collect(),
-        inscribed_transfers: events
-            .iter()
-            .filter_map(|(location, address, event)| match (address, event) {
-                (Some(address), Brc20Event::Transfer(transfer)) => Some(InscribedTransfer {
-                    id: "".into(),
-                    token: transfer.tick(),
-                    // to: "".into(),
-                    from: address.into(),
-                    amount: transfer.amt.to_string(),
-                    utxo: location.utxo.clone(),
-                    offset: location.offset,
-                    utxo_amount: location.utxo_amount,
-                }),
-                _ => None,
-            })
-            .collect(),
-        executed_transfers: vec![],
-    })
+fn map_sat_ranges(
+    block: btc::Block,
+    ordinals: ord_pb::Block,
+    utxo_values_store: StoreGetInt64,
+    ordinal_blocks_store: StoreGetProto<OrdinalBlocks>,
+) -> Result<SatRanges, substreams::errors::Error> {
+    modules::map_sat_ranges(block, ordinals, &utxo_values_store, &ordinal_blocks_store)
 }
 
 #[substreams::handlers::store]
-fn store_inscribed_transfers(events: Brc20Events, store: StoreSetProto<InscribedTransferLocation>) {
-    events.inscribed_transfers.iter().for_each(|transfer| {
-        store.set(
-            0,
-            transfer.utxo.clone(),
-            &InscribedTransferLocation {
-                id: transfer.id.clone(),
-                token: transfer.token.clone(),
-                from: transfer.from.clone(),
-                amount: transfer.amount.clone(),
-                offset: transfer.offset.clone(),
-                utxo_amount: transfer.utxo_amount.clone(),
-            },
-        );
-    });
+fn store_ordinals(sat_ranges: SatRanges, store: StoreSetProto<OrdinalBlocks>) {
+    modules::store_ordinals(sat_ranges, &store);
 }
 
 #[substreams::handlers::store]
-fn store_tokens(events: Brc20Events, store: StoreSetProto<Token>) {
-    events.deploys.iter().for_each(|deploy| {
-        store.set(
-            0,
-            deploy.symbol.clone(),
-            &Token {
-                id: deploy.id.clone(),
-                symbol: deploy.symbol.clone(),
-                max_supply: deploy.max_supply.clone(),
-                mint_limit: deploy.mint_limit.clone(),
-                decimals: deploy.decimals.clone(),
-                deployer: deploy.deployer.clone(),
-            },
-        );
-    });
+fn store_inscription_counts(block: btc::Block, store: StoreAddInt64) {
+    modules::store_inscription_counts(block, &store);
+}
+
+#[substreams::handlers::map]
+fn map_inscriptions(
+    block: btc::Block,
+    inscription_counts: Deltas<DeltaInt64>,
+    utxo_values_store: StoreGetInt64,
+) -> Result<ord_pb::Inscriptions, substreams::errors::Error> {
+    modules::map_inscriptions(block, inscription_counts, &utxo_values_store)
+}
+
+#[substreams::handlers::store]
+fn store_inscription_genesis(
+    inscriptions: ord_pb::Inscriptions,
+    store: StoreSetProto<InscriptionLocation>,
+) {
+    modules::store_inscription_genesis(inscriptions, &store);
+}
+
+#[substreams::handlers::map]
+fn map_sat_spends(
+    block: btc::Block,
+    ordinals: ord_pb::Block,
+    utxo_values_store: StoreGetInt64,
+) -> Result<UtxoSpends, substreams::errors::Error> {
+    modules::map_sat_spends(block, ordinals, &utxo_values_store)
+}
+
+#[substreams::handlers::store]
+fn store_inscribed_sats(
+    inscriptions: ord_pb::Inscriptions,
+    sat_ranges: SatRanges,
+    store: StoreAddInt64,
+) {
+    modules::store_inscribed_sats(inscriptions, sat_ranges, &store);
+}
+
+#[substreams::handlers::store]
+fn store_sat_inscriptions(
+    inscriptions: ord_pb::Inscriptions,
+    sat_ranges: SatRanges,
+    inscribed_sats_store: StoreGetInt64,
+    store: StoreSetString,
+) {
+    modules::store_sat_inscriptions(inscriptions, sat_ranges, &inscribed_sats_store, &store);
+}
+
+#[substreams::handlers::map]
+fn map_inscription_transfers(
+    sat_ranges: SatRanges,
+    sat_spends: UtxoSpends,
+    inscribed_sats_store: StoreGetInt64,
+    sat_inscriptions_store: StoreGetString,
+) -> Result<InscriptionTransfers, substreams::errors::Error> {
+    modules::map_inscription_transfers(
+        sat_ranges,
+        sat_spends,
+        &inscribed_sats_store,
+        &sat_inscriptions_store,
+    )
+}
+
+#[substreams::handlers::store]
+fn store_inscription_locations(
+    inscriptions: ord_pb::Inscriptions,
+    transfers: InscriptionTransfers,
+    store: StoreSetProto<InscriptionLocation>,
+) {
+    modules::store_inscription_locations(inscriptions, transfers, &store);
+}
+
+#[substreams::handlers::map]
+fn map_inscription_parents(
+    inscriptions: ord_pb::Inscriptions,
+    transfers: InscriptionTransfers,
+) -> Result<InscriptionParents, substreams::errors::Error> {
+    modules::map_inscription_parents(inscriptions, transfers)
+}
+
+#[substreams::handlers::store]
+fn store_inscription_children(parents: InscriptionParents, store: StoreAddInt64) {
+    modules::store_inscription_children(parents, &store);
+}
+
+#[substreams::handlers::map]
+fn map_brc20_events(
+    params: String,
+    clock: Clock,
+    inscriptions: ord_pb::Inscriptions,
+    inscription_parents: InscriptionParents,
+) -> Result<Brc20Events, substreams::errors::Error> {
+    modules::map_brc20_events(params, clock, inscriptions, inscription_parents)
+}
+
+#[substreams::handlers::store]
+fn store_inscribed_transfers(
+    block: btc::Block,
+    events: Brc20Events,
+    token_store: StoreGetProto<Token>,
+    requested_supply_store: StoreGetBigInt,
+    store: StoreSetProto<InscribedTransferLocations>,
+) {
+    modules::store_inscribed_transfers(
+        block,
+        events,
+        &token_store,
+        &requested_supply_store,
+        &store,
+    );
+}
+
+#[substreams::handlers::map]
+fn map_pending_transfers(
+    inscribed_transfers: Deltas<DeltaProto<InscribedTransferLocations>>,
+) -> Result<PendingTransfers, substreams::errors::Error> {
+    modules::map_pending_transfers(inscribed_transfers)
+}
+
+#[substreams::handlers::store]
+fn store_tokens(events: Brc20Events, store: StoreSetIfNotExistsProto<Token>) {
+    modules::store_tokens(events, &store);
+}
+
+#[substreams::handlers::store]
+fn store_requested_supply(
+    events: Brc20Events,
+    token_store: StoreGetProto<Token>,
+    store: StoreAddBigInt,
+) {
+    modules::store_requested_supply(events, &token_store, &store);
 }
 
 #[substreams::handlers::store]
 fn store_balances(events: Brc20Events, store: StoreAddBigInt) {
-    // On mints, we add the amount to the receiver's balance
-    events.mints.iter().for_each(|mint| {
-        store.add(
-            0,
-            format!("{}:{}", mint.token, mint.to),
-            BigInt::from_str(&mint.amount).expect("Amount should be valid integer"),
-        );
-    });
-
-    // On inscribed transfers, we subtract the amount from the sender's balance.
-    // Note: The sender's transferable balance is increased in the
-    // `store_transferable_balance` store module
-    events.inscribed_transfers.iter().for_each(|transfer| {
-        store.add(
-            0,
-            format!("{}:{}", transfer.token, transfer.from),
-            BigInt::from_str(&transfer.amount)
-                .expect("Amount should be valid integer")
-                .neg(),
-        );
-    });
-
-    // On executed transfers, we add the amount to the receiver's balance
-    events.executed_transfers.iter().for_each(|transfer| {
-        store.add(
-            0,
-            format!("{}:{}", transfer.token, transfer.to),
-            BigInt::from_str(&transfer.amount).expect("Amount should be valid integer"),
-        );
-    });
+    modules::store_balances(events, &store);
 }
 
 #[substreams::handlers::store]
 fn store_transferable_balances(events: Brc20Events, store: StoreAddBigInt) {
-    // On inscribed transfers, we add the amount to the sender's transferable balance
-    events.inscribed_transfers.iter().for_each(|transfer| {
-        store.add(
-            0,
-            format!("{}:{}", transfer.token, transfer.from),
-            BigInt::from_str(&transfer.amount).expect("Amount should be valid integer"),
-        );
-    });
-
-    // On executed transfers, we subtract the amount from the sender's transferable balance
-    events.executed_transfers.iter().for_each(|transfer| {
-        store.add(
-            0,
-            format!("{}:{}", transfer.token, transfer.from),
-            BigInt::from_str(&transfer.amount)
-                .expect("Amount should be valid integer")
-                .neg(),
-        );
-    });
+    modules::store_transferable_balances(events, &store);
+}
+
+#[substreams::handlers::store]
+fn store_total_balances(events: Brc20Events, store: StoreAddBigInt) {
+    modules::store_total_balances(events, &store);
+}
+
+#[substreams::handlers::store]
+fn store_token_stats(events: Brc20Events, store: StoreAddBigInt) {
+    modules::store_token_stats(events, &store);
+}
+
+#[substreams::handlers::store]
+fn store_token_holders(total_balances: Deltas<DeltaBigInt>, store: StoreAddInt64) {
+    modules::store_token_holders(total_balances, &store);
+}
+
+#[substreams::handlers::store]
+fn store_token_completion(
+    clock: Clock,
+    token_stats: Deltas<DeltaBigInt>,
+    token_store: StoreGetProto<Token>,
+    store: StoreSetIfNotExistsInt64,
+) {
+    modules::store_token_completion(clock, token_stats, &token_store, &store);
 }
 
 #[substreams::handlers::map]
 fn map_resolve_transfers(
     block: btc::Block,
     events: Brc20Events,
-    transfer_store: StoreGetProto<InscribedTransferLocation>,
+    transfer_store: StoreGetProto<InscribedTransferLocations>,
     token_store: StoreGetProto<Token>,
+    requested_supply_store: StoreGetBigInt,
+    sat_spends: UtxoSpends,
+) -> Result<Brc20Events, substreams::errors::Error> {
+    modules::map_resolve_transfers(
+        block,
+        events,
+        &transfer_store,
+        &token_store,
+        &requested_supply_store,
+        sat_spends,
+    )
+}
+
+#[substreams::handlers::map]
+fn map_filtered_events(
+    params: String,
+    events: Brc20Events,
 ) -> Result<Brc20Events, substreams::errors::Error> {
-    let executed_transfers =
-        block
-            .tx
-            .into_iter()
-            .filter_map(|tx| {
-                // Note: Without tracking UTXO values, we can only reliably resolve transfers where the
-                // inscribed sat is held by the first input UTXO of the transaction
-                if let Some(inscribed_transfer_loc) =
-                    transfer_store.get_at(0, format!("{}:{}", tx.vin[0].txid, tx.vin[0].vout))
-                {
-                    let (vout, _) = tx.nth_sat_utxo(inscribed_transfer_loc.offset)?;
-                    Some(ExecutedTransfer {
-                        id: inscribed_transfer_loc.id,
-                        token: inscribed_transfer_loc.token,
-                        from: inscribed_transfer_loc.from,
-                        to: vout.address()?,
-                        amount: inscribed_transfer_loc.amount,
-                    })
-                } else {
-                    // Log that we could not resolve transfer
-                    if let Some(inscribed_transfer_loc) = tx.vin.iter().find_map(|vin| {
-                        transfer_store.get_at(0, format!("{}:{}", vin.txid, vin.vout))
-                    }) {
-                        substreams::log::info!(
-                            "Could not resolve inscribed transfer {}",
-                            inscribed_transfer_loc.id
-                        );
-                    }
-                    None
-                }
-            })
-            .collect::<Vec<_>>();
-
-    Ok(Brc20Events {
-        executed_transfers,
-        mints: events
-            .mints
-            .into_iter()
-            .filter(|mint| match token_store.get_at(0, mint.token.clone()) {
-                Some(token) => {
-                    BigInt::from_str(&mint.amount).unwrap()
-                        < BigInt::from_str(&token.mint_limit).unwrap()
-                }
-                None => false,
-            })
-            .collect(),
-        ..events
-    })
+    modules::map_filtered_events(params, events)
 }
 
 #[substreams::handlers::map]
+#[allow(clippy::too_many_arguments)]
 fn graph_out(
+    params: String,
     clock: Clock,
     events: Brc20Events,
     balances_store: Deltas<DeltaBigInt>,
     transferable_balances_store: Deltas<DeltaBigInt>,
+    token_stats_store: Deltas<DeltaBigInt>,
+    token_holders_store: Deltas<DeltaInt64>,
+    token_completion_store: Deltas<DeltaInt64>,
 ) -> Result<EntityChanges, substreams::errors::Error> {
-    let mut tables = Tables::new();
-
-    events.deploys.iter().for_each(|deploy| {
-        tables
-            .create_row("Deploy", deploy.id.clone())
-            .set("token", deploy.symbol.clone())
-            .set("deployer", deploy.deployer.clone())
-            .set("timestamp", clock.number.clone())
-            .set(
-                "block",
-                clock
-                    .timestamp
-                    .as_ref()
-                    .map(|t| t.seconds)
-                    .unwrap_or_default(),
-            );
-
-        tables
-            .create_row("Token", deploy.symbol.clone())
-            .set("symbol", deploy.symbol.clone())
-            .set_bigint("max_supply", &deploy.max_supply)
-            .set_bigint("mint_limit", &deploy.mint_limit)
-            .set("decimals", deploy.decimals.clone())
-            .set("deployment", deploy.id.clone());
-    });
-
-    events.mints.iter().for_each(|mint| {
-        tables
-            .create_row("Mint", mint.id.clone())
-            .set("token", mint.token.clone())
-            .set("to", mint.to.clone())
-            .set_bigint("amount", &mint.amount);
-    });
-
-    events.executed_transfers.iter().for_each(|transfer| {
-        tables
-            .create_row("Transfer", transfer.id.clone())
-            .set("token", transfer.token.clone())
-            .set("from", transfer.from.clone())
-            .set("to", transfer.to.clone())
-            .set_bigint("amount", &transfer.amount);
-    });
-
-    balances_store
-        .deltas
-        .iter()
-        .for_each(|delta| match delta.operation {
-            Operation::Create => {
-                let (token, account) = {
-                    let mut parts = delta.key.split(':');
-                    (
-                        parts
-                            .next()
-                            .expect("Balance store key should be `{SYMBOL}:{ACCOUNT}`"),
-                        parts
-                            .next()
-                            .expect("Balance store key should be `{SYMBOL}:{ACCOUNT}`"),
-                    )
-                };
-
-                tables
-                    .create_row("AccountBalance", delta.key.clone())
-                    .set("account", account.to_string())
-                    .set("token", token.to_string())
-                    .set_bigint("balance", &delta.new_value.to_string())
-                    .set_bigint("transferable", &"0".into());
-
-                tables.create_row("Account", account);
-            }
-            Operation::Update => {
-                tables
-                    .update_row("AccountBalance", delta.key.clone())
-                    .set_bigint("balance", &delta.new_value.to_string());
-            }
-            _ => (),
-        });
-
-    transferable_balances_store.deltas.iter().for_each(|delta| {
-        // Note: No need to check operation since the AccountBalance row should have been created
-        // when the `balances_store` had a `Create` operation for the same key.
-        // This is because an account can only have a transferable balance if it has a balance
-        // in the first place, which is created when the account is the recipient of either a Mint
-        // or a Transfer event.
-        tables
-            .update_row("AccountBalance", delta.key.clone())
-            .set_bigint("transferable", &delta.new_value.to_string());
-    });
-
-    Ok(tables.to_entity_changes())
+    modules::graph_out(
+        params,
+        clock,
+        events,
+        balances_store,
+        transferable_balances_store,
+        token_stats_store,
+        token_holders_store,
+        token_completion_store,
+    )
+}
+
+#[substreams::handlers::map]
+#[allow(clippy::too_many_arguments)]
+fn db_out(
+    params: String,
+    clock: Clock,
+    events: Brc20Events,
+    balances_store: Deltas<DeltaBigInt>,
+    transferable_balances_store: Deltas<DeltaBigInt>,
+    token_stats_store: Deltas<DeltaBigInt>,
+    token_holders_store: Deltas<DeltaInt64>,
+    token_completion_store: Deltas<DeltaInt64>,
+) -> Result<DatabaseChanges, substreams::errors::Error> {
+    modules::db_out(
+        params,
+        clock,
+        events,
+        balances_store,
+        transferable_balances_store,
+        token_stats_store,
+        token_holders_store,
+        token_completion_store,
+    )
 }
@@ -0,0 +1,1276 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::str::FromStr;
+
+use anyhow::Result;
+use substreams::pb::substreams::store_delta::Operation;
+use substreams::pb::substreams::Clock;
+use substreams::scalar::BigInt;
+use substreams::store::{
+    DeltaBigInt, DeltaInt64, DeltaProto, Deltas, StoreAdd, StoreGet, StoreSet, StoreSetIfNotExists,
+};
+use substreams_database_change::pb::database::DatabaseChanges;
+use substreams_entity_change::pb::entity::EntityChanges;
+
+use crate::brc20::{self, Brc20Event};
+use crate::errors::{self, Error};
+use crate::inscriptions::{self, InscriptionCounts};
+use crate::ord::inscription_id::InscriptionId;
+use crate::ordinals;
+use crate::pb::btc::brc20::v1::{
+    Brc20Events, Deploy, ExecutedTransfer, InscribedTransfer, InscribedTransferLocation,
+    InscribedTransferLocations, Mint, PendingTransfer, PendingTransfers, Token,
+};
+use crate::pb::ordinals::v1::{
+    self as ord_pb, InscriptionLocation, InscriptionParent, InscriptionParents,
+    InscriptionTransfers, OrdinalBlocks, SatRanges, UtxoSatRanges, UtxoSpend, UtxoSpends,
+};
+use crate::pb::sf::bitcoin::r#type::v1 as btc;
+use crate::rules::{self, Candidate, RuleSet};
+use crate::sinks;
+
+struct Location {
+    pub inscription_id: String,
+    pub utxo: String,
+    pub offset: u64,
+    pub utxo_amount: u64,
+    pub ordinal: u64,
+}
+
+/// Index of the first new inscription of a transaction when computing event ordinals
+const INSCRIPTION_ORDINAL_OFFSET: usize = 1 << 31;
+
+/// Returns the ordinal of a BRC-20 event within its block, which is used when updating the
+/// balance stores so that store deltas can be traced back to the event that caused them.
+/// Events are ordered by transaction, and within a transaction, transfer executions (indexed
+/// by their order of execution) come before new inscriptions (indexed by
+/// `INSCRIPTION_ORDINAL_OFFSET + inscription_index`).
+fn event_ordinal(tx_index: usize, index: usize) -> u64 {
+    ((tx_index as u64) << 32) | index as u64
+}
+
+/// Parses an amount of tokens of a BRC-20 event or token
+fn parse_amount(amount: &str) -> errors::Result<BigInt> {
+    BigInt::from_str(amount).map_err(|err| Error::Amount(format!("{}: {}", amount, err)))
+}
+
+/// Splits a balance store key (i.e.: `{SYMBOL}:{ACCOUNT}`) into the token and the account
+pub fn split_balance_key(key: &str) -> errors::Result<(&str, &str)> {
+    key.rsplit_once(':')
+        .ok_or_else(|| Error::StoreKey(format!("{} should be `{{SYMBOL}}:{{ACCOUNT}}`", key)))
+}
+
+/// Splits a token stats store key (i.e.: `{STAT}:{SYMBOL}`) into the stat and the token
+pub fn split_token_stat_key(key: &str) -> errors::Result<(&str, &str)> {
+    key.split_once(':')
+        .ok_or_else(|| Error::StoreKey(format!("{} should be `{{STAT}}:{{SYMBOL}}`", key)))
+}
+
+/// Parses an inscription ID (i.e.: `{TXID}i{INDEX}`)
+fn parse_inscription_id(id: &str) -> errors::Result<InscriptionId> {
+    InscriptionId::from_str(id).map_err(|err| Error::Decode(format!("inscription {}: {}", id, err)))
+}
+
+/// Returns the store key of an outpoint (i.e.: `{TXID}:{VOUT}:`) in the stores keyed by UTXO
+/// (e.g.: `store_utxo_values` and `store_inscribed_transfers`). The trailing `:` makes keys
+/// prefix-free, so that deleting the entry of `{TXID}:1` does not delete that of `{TXID}:10`.
+pub fn utxo_key(outpoint: &str) -> String {
+    format!("{}:", outpoint)
+}
+
+/// Returns the value of a `key=value` module param, params being separated by `&` (e.g.:
+/// `rules=genesis`)
+fn param<'a>(params: &'a str, key: &str) -> errors::Result<Option<&'a str>> {
+    for param in params
+        .split('&')
+        .map(str::trim)
+        .filter(|param| !param.is_empty())
+    {
+        match param.split_once('=') {
+            Some((name, value)) if name.trim() == key => return Ok(Some(value.trim())),
+            Some(_) => (),
+            None => return Err(Error::Params(format!("{} should be `KEY=VALUE`", param))),
+        }
+    }
+    Ok(None)
+}
+
+/// Returns the rule set of the block, unless overridden by the `rules` param
+fn rule_set(params: &str, clock: &Clock) -> errors::Result<&'static RuleSet> {
+    match param(params, "rules")? {
+        Some(name) => {
+            RuleSet::named(name).ok_or_else(|| Error::Params(format!("unknown rule set {}", name)))
+        }
+        None => Ok(RuleSet::at(clock.number)),
+    }
+}
+
+/// Ticker allowlist of the `tick` param (comma-separated and case-insensitive, e.g.:
+/// `tick=ordi,sats`), every ticker being allowed without it. Filtering only applies to the
+/// outputs of modules: stores always track every token, so that balances remain accurate.
+#[derive(Debug, Default)]
+pub struct TickFilter {
+    ticks: Option<HashSet<String>>,
+}
+
+impl TickFilter {
+    pub fn from_params(params: &str) -> errors::Result<Self> {
+        let ticks = match param(params, "tick")? {
+            Some(ticks) => {
+                let ticks = ticks
+                    .split(',')
+                    .map(|tick| tick.trim().to_lowercase())
+                    .filter(|tick| !tick.is_empty())
+                    .collect::<HashSet<_>>();
+                if ticks.is_empty() {
+                    return Err(Error::Params("tick should list at least one ticker".into()));
+                }
+                Some(ticks)
+            }
+            None => None,
+        };
+        Ok(Self { ticks })
+    }
+
+    pub fn allows(&self, tick: &str) -> bool {
+        match &self.ticks {
+            Some(ticks) => ticks.contains(tick),
+            None => true,
+        }
+    }
+
+    /// Keeps the events of allowed tickers
+    pub fn events(&self, mut events: Brc20Events) -> Brc20Events {
+        if self.ticks.is_some() {
+            events.deploys.retain(|deploy| self.allows(&deploy.symbol));
+            events.mints.retain(|mint| self.allows(&mint.token));
+            for transfers in [
+                &mut events.inscribed_transfers,
+                &mut events.invalid_transfers,
+            ] {
+                transfers.retain(|transfer| self.allows(&transfer.token));
+            }
+            for transfers in [
+                &mut events.executed_transfers,
+                &mut events.burned_transfers,
+                &mut events.returned_transfers,
+            ] {
+                transfers.retain(|transfer| self.allows(&transfer.token));
+            }
+        }
+        events
+    }
+
+    /// Keeps the deltas of allowed tickers from a balance store (see `split_balance_key`)
+    pub fn balance_deltas(&self, mut deltas: Deltas<DeltaBigInt>) -> Deltas<DeltaBigInt> {
+        if self.ticks.is_some() {
+            deltas.deltas.retain(|delta| {
+                matches!(split_balance_key(&delta.key), Ok((token, _)) if self.allows(token))
+            });
+        }
+        deltas
+    }
+
+    /// Keeps the deltas of allowed tickers from `store_token_stats` (see `split_token_stat_key`)
+    pub fn token_stat_deltas(&self, mut deltas: Deltas<DeltaBigInt>) -> Deltas<DeltaBigInt> {
+        if self.ticks.is_some() {
+            deltas.deltas.retain(|delta| {
+                matches!(split_token_stat_key(&delta.key), Ok((_, token)) if self.allows(token))
+            });
+        }
+        deltas
+    }
+
+    /// Keeps the deltas of allowed tickers from a store keyed by token (e.g.:
+    /// `store_token_holders`)
+    pub fn token_deltas(&self, mut deltas: Deltas<DeltaInt64>) -> Deltas<DeltaInt64> {
+        if self.ticks.is_some() {
+            deltas.deltas.retain(|delta| self.allows(&delta.key));
+        }
+        deltas
+    }
+}
+
+/// Returns the value in sats of the UTXO spent by an input, from `store_utxo_values`
+fn utxo_sats(utxo_values_store: &impl StoreGet<i64>, vin: &btc::Vin) -> Option<u64> {
+    utxo_values_store
+        .get_at(0, utxo_key(&vin.outpoint()))
+        .map(|sats| sats as u64)
+}
+
+/// Returns the store operation adding the amount of an event to a key, reporting and skipping
+/// events whose amount cannot be parsed
+fn amount_change(
+    ordinal: u64,
+    key: String,
+    amount: &str,
+    negate: bool,
+) -> Option<(u64, String, BigInt)> {
+    match parse_amount(amount) {
+        Ok(amount) if negate => Some((ordinal, key, amount.neg())),
+        Ok(amount) => Some((ordinal, key, amount)),
+        Err(err) => {
+            substreams::log::info!("Skipping store update of {}: {}", key, err);
+            None
+        }
+    }
+}
+
+/// Adds the amount of an event to the store, reporting and skipping events whose amount
+/// cannot be parsed
+fn add_amount(
+    store: &impl StoreAdd<BigInt>,
+    ordinal: u64,
+    key: String,
+    amount: &str,
+    negate: bool,
+) {
+    if let Some((ordinal, key, amount)) = amount_change(ordinal, key, amount, negate) {
+        store.add(ordinal, key, amount);
+    }
+}
+
+/// Applies store operations sorted by ordinal, since stores reject operations whose ordinal is
+/// lower than the one of the previous operation of the block. Operations with the same ordinal
+/// keep their order.
+fn add_in_ordinal_order(store: &impl StoreAdd<BigInt>, mut changes: Vec<(u64, String, BigInt)>) {
+    changes.sort_by_key(|(ordinal, _, _)| *ordinal);
+    changes
+        .into_iter()
+        .for_each(|(ordinal, key, amount)| store.add(ordinal, key, amount));
+}
+
+pub fn store_utxo_values(block: btc::Block, store: &impl StoreSet<i64>) {
+    // Value in sats of every unspent output, which is required to locate the sats of a
+    // transaction's inputs (e.g.: to place inscriptions revealed in an input other than the
+    // first one). OP_RETURN outputs can never be spent and are not stored.
+    block.tx.iter().for_each(|tx| match tx.vout_sats() {
+        Ok(output_sats) => tx
+            .vout
+            .iter()
+            .zip(output_sats)
+            .filter(|(vout, _)| !vout.is_op_return())
+            .for_each(|(vout, sats)| {
+                store.set(
+                    0,
+                    utxo_key(&format!("{}:{}", tx.txid, vout.n)),
+                    &(sats as i64),
+                );
+            }),
+        Err(err) => substreams::log::info!("Skipping outputs of tx {}: {}", tx.txid, err),
+    });
+
+    // The values of the outputs spent by the block are no longer needed once the block is
+    // processed. Note: Deletions use ordinal 1 so that the modules reading the store can still
+    // read the values of the outputs spent in the block at ordinal 0 (see `utxo_sats`).
+    block
+        .tx
+        .iter()
+        .flat_map(|tx| tx.vin.iter())
+        .filter(|vin| vin.coinbase.is_empty())
+        .for_each(|vin| store.delete_prefix(1, &utxo_key(&vin.outpoint())));
+}
+
+pub fn map_ordinals(
+    block: btc::Block,
+    utxo_values_store: &impl StoreGet<i64>,
+) -> Result<ord_pb::Block, substreams::errors::Error> {
+    Ok(ordinals::block_ordinals(&block, |vin| {
+        utxo_sats(utxo_values_store, vin)
+    })?)
+}
+
+pub fn store_ordinal_blocks(
+    block: btc::Block,
+    ordinals: ord_pb::Block,
+    utxo_values_store: &impl StoreGet<i64>,
+    store: &impl StoreSet<OrdinalBlocks>,
+) {
+    // Ordinal blocks of every output, i.e.: the ranges of the inputs its sats come from, from
+    // which absolute sat ranges are resolved. Since resolving them walks back through the
+    // outputs the sats were spent from, entries are never deleted.
+    match ordinals::utxo_ordinal_blocks(&block, &ordinals, |vin| utxo_sats(utxo_values_store, vin))
+    {
+        Ok(utxos) => utxos
+            .iter()
+            .for_each(|(utxo, blocks)| store.set(0, utxo_key(utxo), blocks)),
+        Err(err) => {
+            substreams::log::info!("Skipping ordinals of block {}: {}", ordinals.number, err)
+        }
+    }
+}
+
+pub fn map_sat_ranges(
+    block: btc::Block,
+    ordinals: ord_pb::Block,
+    utxo_values_store: &impl StoreGet<i64>,
+    ordinal_blocks_store: &impl StoreGet<OrdinalBlocks>,
+) -> Result<SatRanges, substreams::errors::Error> {
+    // Absolute ranges of the sats held by the outputs spent and created by the block
+    let (spent, created) = ordinals::block_sat_ranges(
+        &block,
+        &ordinals,
+        |vin| utxo_sats(utxo_values_store, vin),
+        |utxo| ordinal_blocks_store.get_first(utxo_key(utxo)),
+    )?;
+    let sat_ranges = |utxos: Vec<ordinals::UtxoRanges>| {
+        utxos
+            .into_iter()
+            .filter_map(|(utxo, ranges)| match ranges {
+                Ok(ranges) => Some(UtxoSatRanges {
+                    utxo,
+                    address: ranges.address,
+                    ranges: ranges.blocks,
+                }),
+                Err(err) => {
+                    substreams::log::info!("Skipping ordinals of {}: {}", utxo, err);
+                    None
+                }
+            })
+            .collect()
+    };
+
+    Ok(SatRanges {
+        spent: sat_ranges(spent),
+        created: sat_ranges(created),
+    })
+}
+
+pub fn store_ordinals(sat_ranges: SatRanges, store: &impl StoreSet<OrdinalBlocks>) {
+    // Absolute ranges of the sats held by every unspent output
+    sat_ranges.created.into_iter().for_each(|utxo| {
+        store.set(
+            0,
+            utxo_key(&utxo.utxo),
+            &OrdinalBlocks {
+                blocks: utxo.ranges,
+                address: utxo.address,
+            },
+        )
+    });
+
+    // Note: Deletions use ordinal 1 so that the ranges of the outputs spent in the block can
+    // still be read at ordinal 0
+    sat_ranges
+        .spent
+        .iter()
+        .for_each(|utxo| store.delete_prefix(1, &utxo_key(&utxo.utxo)));
+}
+
+pub fn store_inscription_counts(block: btc::Block, store: &impl StoreAdd<i64>) {
+    // Number of blessed and cursed inscriptions, from which inscriptions are numbered
+    let counts = inscriptions::block_inscription_counts(&block);
+
+    if counts.blessed > 0 {
+        store.add(0, "blessed", counts.blessed);
+    }
+    if counts.cursed > 0 {
+        store.add(0, "cursed", counts.cursed);
+    }
+}
+
+pub fn map_inscriptions(
+    block: btc::Block,
+    inscription_counts: Deltas<DeltaInt64>,
+    utxo_values_store: &impl StoreGet<i64>,
+) -> Result<ord_pb::Inscriptions, substreams::errors::Error> {
+    // The counts before the block are the old values of the block's deltas
+    let count_before = |key: &str| {
+        inscription_counts
+            .deltas
+            .iter()
+            .find(|delta| delta.key == key)
+            .map(|delta| delta.old_value)
+            .unwrap_or_default()
+    };
+    let counts = InscriptionCounts {
+        blessed: count_before("blessed"),
+        cursed: count_before("cursed"),
+    };
+
+    Ok(ord_pb::Inscriptions {
+        inscriptions: inscriptions::block_inscriptions(&block, counts, |vin| {
+            utxo_sats(utxo_values_store, vin)
+        }),
+    })
+}
+
+pub fn store_inscription_genesis(
+    inscriptions: ord_pb::Inscriptions,
+    store: &impl StoreSet<InscriptionLocation>,
+) {
+    // Inscriptions whose sat is spent as fee have no genesis location
+    inscriptions
+        .inscriptions
+        .iter()
+        .filter(|inscription| !inscription.genesis_utxo.is_empty())
+        .for_each(|inscription| {
+            store.set(
+                0,
+                &inscription.id,
+                &InscriptionLocation {
+                    utxo: inscription.genesis_utxo.clone(),
+                    offset: inscription.genesis_offset,
+                    address: inscription.genesis_address.clone(),
+                },
+            )
+        });
+}
+
+pub fn map_sat_spends(
+    block: btc::Block,
+    ordinals: ord_pb::Block,
+    utxo_values_store: &impl StoreGet<i64>,
+) -> Result<UtxoSpends, substreams::errors::Error> {
+    // Destinations of the sats of every UTXO spent in the block, from which inscribed sats are
+    // followed
+    let spends = ordinals::utxo_spends(&block, &ordinals, |vin| utxo_sats(utxo_values_store, vin))
+        .unwrap_or_else(|err| {
+            substreams::log::info!("Skipping spends of block {}: {}", ordinals.number, err);
+            vec![]
+        });
+
+    Ok(UtxoSpends { spends })
+}
+
+pub fn store_inscribed_sats(
+    inscriptions: ord_pb::Inscriptions,
+    sat_ranges: SatRanges,
+    store: &impl StoreAdd<i64>,
+) {
+    // Number of inscriptions revealed on every sat, and in the buckets of sats containing it
+    // (see `inscriptions::inscribed_sats`). Counts use the index of the revealing transaction
+    // as ordinal, so that the inscriptions revealed by a transaction are only held by the UTXOs
+    // spent after it.
+    inscriptions::inscription_sats(&inscriptions.inscriptions, &sat_ranges.created)
+        .into_iter()
+        .for_each(|(inscription, sat)| {
+            inscriptions::inscribed_sat_keys(sat)
+                .iter()
+                .for_each(|key| store.add(inscription.tx_index, key, 1))
+        });
+}
+
+/// Returns the store key of the inscription revealed on a sat after `index` others (i.e.:
+/// `{SAT}:{INDEX}`) in `store_sat_inscriptions`
+pub fn sat_inscription_key(sat: u64, index: u64) -> String {
+    format!("{}:{}", sat, index)
+}
+
+pub fn store_sat_inscriptions(
+    inscriptions: ord_pb::Inscriptions,
+    sat_ranges: SatRanges,
+    inscribed_sats_store: &impl StoreGet<i64>,
+    store: &impl StoreSet<String>,
+) {
+    // IDs of the inscriptions revealed on every sat, in order, numbered after the ones revealed
+    // on the sat before the block
+    let mut counts: HashMap<u64, u64> = HashMap::new();
+    inscriptions::inscription_sats(&inscriptions.inscriptions, &sat_ranges.created)
+        .into_iter()
+        .for_each(|(inscription, sat)| {
+            let count = counts.entry(sat).or_insert_with(|| {
+                inscribed_sats_store
+                    .get_first(inscriptions::inscribed_sat_key(sat))
+                    .unwrap_or_default() as u64
+            });
+            store.set(
+                inscription.tx_index,
+                sat_inscription_key(sat, *count),
+                &inscription.id,
+            );
+            *count += 1;
+        });
+}
+
+pub fn map_inscription_transfers(
+    sat_ranges: SatRanges,
+    sat_spends: UtxoSpends,
+    inscribed_sats_store: &impl StoreGet<i64>,
+    sat_inscriptions_store: &impl StoreGet<String>,
+) -> Result<InscriptionTransfers, substreams::errors::Error> {
+    // Every inscription revealed on a sat spent in the block is transferred
+    let spent: HashMap<&str, &UtxoSatRanges> = sat_ranges
+        .spent
+        .iter()
+        .map(|utxo| (utxo.utxo.as_str(), utxo))
+        .collect();
+    let transfers = inscriptions::block_inscription_transfers(
+        &sat_spends.spends,
+        |utxo| spent.get(utxo).copied(),
+        |tx_index, range| {
+            // Note: Inscriptions revealed in the block are written at the index of their
+            // transaction, and spends are never in the coinbase transaction
+            let count = |key: &str| {
+                inscribed_sats_store
+                    .get_at(tx_index.saturating_sub(1), key)
+                    .unwrap_or_default()
+            };
+            inscriptions::inscribed_sats(range.start, range.size, count)
+                .into_iter()
+                .map(|(sat, count)| {
+                    let ids = (0..count)
+                        .filter_map(|index| {
+                            sat_inscriptions_store.get_last(sat_inscription_key(sat, index))
+                        })
+                        .collect();
+                    (sat, ids)
+                })
+                .collect()
+        },
+    );
+
+    Ok(InscriptionTransfers { transfers })
+}
+
+pub fn store_inscription_locations(
+    inscriptions: ord_pb::Inscriptions,
+    transfers: InscriptionTransfers,
+    store: &impl StoreSet<InscriptionLocation>,
+) {
+    // Current location of every inscription, whose `utxo` is empty if its sat was lost (e.g.:
+    // spent as fee at its reveal). Transfers are in order, so the last location of the block
+    // is the one kept.
+    inscriptions.inscriptions.iter().for_each(|inscription| {
+        store.set(
+            0,
+            &inscription.id,
+            &InscriptionLocation {
+                utxo: inscription.genesis_utxo.clone(),
+                offset: inscription.genesis_offset,
+                address: inscription.genesis_address.clone(),
+            },
+        )
+    });
+    transfers.transfers.into_iter().for_each(|transfer| {
+        store.set(
+            0,
+            &transfer.inscription_id,
+            &transfer.to.unwrap_or_default(),
+        )
+    });
+}
+
+pub fn map_inscription_parents(
+    inscriptions: ord_pb::Inscriptions,
+    transfers: InscriptionTransfers,
+) -> Result<InscriptionParents, substreams::errors::Error> {
+    let parents = inscriptions
+        .inscriptions
+        .into_iter()
+        .filter_map(|inscription| Some((inscription.parent?, inscription.id)))
+        .map(|(parent, child)| {
+            let txid = parse_inscription_id(&child)?.txid.to_string();
+            // The parent's inscribed sat must be spent by the child's reveal transaction
+            let valid = transfers
+                .transfers
+                .iter()
+                .any(|transfer| transfer.inscription_id == parent && transfer.txid == txid);
+
+            Ok(InscriptionParent {
+                child,
+                parent,
+                valid,
+            })
+        })
+        .collect::<errors::Result<Vec<_>>>()?;
+
+    Ok(InscriptionParents { parents })
+}
+
+pub fn store_inscription_children(parents: InscriptionParents, store: &impl StoreAdd<i64>) {
+    // Number of valid children of each inscription. Inscriptions with children are collections.
+    parents
+        .parents
+        .iter()
+        .filter(|parent| parent.valid)
+        .for_each(|parent| store.add(0, &parent.parent, 1));
+}
+
+pub fn map_brc20_events(
+    params: String,
+    clock: Clock,
+    inscriptions: ord_pb::Inscriptions,
+    inscription_parents: InscriptionParents,
+) -> Result<Brc20Events, substreams::errors::Error> {
+    let rule_set = rule_set(&params, &clock)?;
+    // Valid parents of the block's inscriptions, by child
+    let parents = inscription_parents
+        .parents
+        .into_iter()
+        .filter(|parent| parent.valid)
+        .map(|parent| (parent.child, parent.parent))
+        .collect::<BTreeMap<_, _>>();
+
+    let events = inscriptions
+        .inscriptions
+        .into_iter()
+        .filter(|inscription| brc20::is_brc20_content_type(inscription.content_type.as_deref()))
+        // Inscriptions whose sat is spent as fee are ignored
+        .filter(|inscription| !inscription.genesis_utxo.is_empty())
+        .map(|inscription| {
+            let index = parse_inscription_id(&inscription.id)?.index as usize;
+
+            Ok((
+                Location {
+                    inscription_id: inscription.id,
+                    utxo: inscription.genesis_utxo,
+                    offset: inscription.genesis_offset,
+                    utxo_amount: inscription.genesis_utxo_amount,
+                    ordinal: event_ordinal(
+                        inscription.tx_index as usize,
+                        INSCRIPTION_ORDINAL_OFFSET + index,
+                    ),
+                },
+                inscription.genesis_address,
+                inscription.content,
+                !inscription.curses.is_empty(),
+            ))
+        })
+        .collect::<errors::Result<Vec<_>>>()?
+        .into_iter()
+        .filter_map(|(location, address, content, has_curses)| {
+            let content = if let Ok(content) = String::from_utf8(content) {
+                content
+            } else {
+                return None;
+            };
+
+            match serde_json::from_str::<Brc20Event>(&content) {
+                Ok(event)
+                    if rule_set.is_valid(&Candidate {
+                        event: &event,
+                        has_curses,
+                    }) =>
+                {
+                    Some((location, address, event))
+                }
+                Ok(_) => None,
+                Err(err) => {
+                    substreams::log::info!(
+                        "Error parsing inscription content {}: {}",
+                        location.inscription_id,
+                        err
+                    );
+                    None
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    Ok(Brc20Events {
+        deploys: events
+            .iter()
+            .filter_map(|(location, address, event)| match (address, event) {
+                (Some(address), Brc20Event::Deploy(deploy)) => Some(Deploy {
+                    id: location.inscription_id.clone(),
+                    symbol: deploy.tick(),
+                    max_supply: deploy.max.to_string(),
+                    mint_limit: deploy.lim().to_string(),
+                    decimals: deploy.dec(),
+                    deployer: address.clone(),
+                    self_mint: deploy.self_mint(),
+                }),
+                _ => None,
+            })
+            .collect(),
+        mints: events
+            .iter()
+            .filter_map(|(location, address, event)| match (address, event) {
+                (Some(address), Brc20Event::Mint(mint)) => Some(Mint {
+                    id: location.inscription_id.clone(),
+                    token: mint.tick(),
+                    to: address.into(),
+                    amount: mint.amt.to_string(),
+                    ordinal: location.ordinal,
+                    parent: parents
+                        .get(&location.inscription_id)
+                        .cloned()
+                        .unwrap_or_default(),
+                }),
+                _ => None,
+            })
+            .collect(),
+        inscribed_transfers: events
+            .iter()
+            .filter_map(|(location, address, event)| match (address, event) {
+                (Some(address), Brc20Event::Transfer(transfer)) => Some(InscribedTransfer {
+                    id: location.inscription_id.clone(),
+                    token: transfer.tick(),
+                    // to: "".into(),
+                    from: address.into(),
+                    amount: transfer.amt.to_string(),
+                    utxo: location.utxo.clone(),
+                    offset: location.offset,
+                    utxo_amount: location.utxo_amount,
+                    ordinal: location.ordinal,
+                }),
+                _ => None,
+            })
+            .collect(),
+        executed_transfers: vec![],
+        burned_transfers: vec![],
+        returned_transfers: vec![],
+        invalid_transfers: vec![],
+    })
+}
+
+pub fn store_inscribed_transfers(
+    block: btc::Block,
+    events: Brc20Events,
+    token_store: &impl StoreGet<Token>,
+    requested_supply_store: &impl StoreGet<BigInt>,
+    store: &impl StoreSet<InscribedTransferLocations>,
+) {
+    // Several transfers can be inscribed on the sats of the same outpoint (e.g.: batch reveals),
+    // in which case they are stored together
+    let mut locations: BTreeMap<&str, Vec<InscribedTransferLocation>> = BTreeMap::new();
+
+    events
+        .inscribed_transfers
+        .iter()
+        // Invalid transfers (e.g.: of tokens that have not been deployed) can never be executed
+        .filter(|transfer| {
+            matches!(
+                transfer_is_valid(transfer, token_store, requested_supply_store),
+                Ok(true)
+            )
+        })
+        .for_each(|transfer| {
+            locations
+                .entry(&transfer.utxo)
+                .or_default()
+                .push(InscribedTransferLocation {
+                    id: transfer.id.clone(),
+                    token: transfer.token.clone(),
+                    from: transfer.from.clone(),
+                    amount: transfer.amount.clone(),
+                    offset: transfer.offset.clone(),
+                    utxo_amount: transfer.utxo_amount.clone(),
+                });
+        });
+
+    locations.into_iter().for_each(|(utxo, locations)| {
+        store.set(0, utxo_key(utxo), &InscribedTransferLocations { locations });
+    });
+
+    // A transfer inscription is used after its first move, so the pending transfers held by
+    // every spent outpoint are deleted (i.e.: once executed, burned or spent as fee).
+    // Note: Deletions use ordinal 1 so that `map_resolve_transfers` can still read the
+    // transfers spent in the block at ordinal 0.
+    block
+        .tx
+        .iter()
+        .flat_map(|tx| tx.vin.iter())
+        .filter(|vin| vin.coinbase.is_empty())
+        .for_each(|vin| store.delete_prefix(1, &utxo_key(&vin.outpoint())));
+}
+
+pub fn map_pending_transfers(
+    inscribed_transfers: Deltas<DeltaProto<InscribedTransferLocations>>,
+) -> Result<PendingTransfers, substreams::errors::Error> {
+    let mut pending_transfers = PendingTransfers::default();
+
+    for delta in inscribed_transfers.deltas {
+        let utxo = delta.key.trim_end_matches(':');
+        let (transfers, locations) = match delta.operation {
+            Operation::Create => (&mut pending_transfers.added, delta.new_value.locations),
+            Operation::Delete => (&mut pending_transfers.removed, delta.old_value.locations),
+            _ => continue,
+        };
+
+        transfers.extend(locations.into_iter().map(|location| PendingTransfer {
+            utxo: utxo.into(),
+            location: Some(location),
+        }));
+    }
+
+    Ok(pending_transfers)
+}
+
+pub fn store_tokens(events: Brc20Events, store: &impl StoreSetIfNotExists<Token>) {
+    // Only the first deployment of a ticker is valid
+    events.deploys.iter().for_each(|deploy| {
+        store.set_if_not_exists(
+            0,
+            deploy.symbol.clone(),
+            &Token {
+                id: deploy.id.clone(),
+                symbol: deploy.symbol.clone(),
+                max_supply: deploy.max_supply.clone(),
+                mint_limit: deploy.mint_limit.clone(),
+                decimals: deploy.decimals.clone(),
+                deployer: deploy.deployer.clone(),
+                self_mint: deploy.self_mint,
+            },
+        );
+    });
+}
+
+/// Returns the valid mints of the block, in order, i.e.: the authorized mints of deployed tokens
+/// within their mint limit, whose amount is capped to the remaining supply (see
+/// `rules::minted_amount`). `requested` returns the supply requested by the valid mints of a
+/// token before the block (see `store_requested_supply`), or `None` to start from zero.
+fn valid_mints(
+    mints: Vec<Mint>,
+    token_store: &impl StoreGet<Token>,
+    requested: impl Fn(&str) -> Option<BigInt>,
+) -> errors::Result<Vec<Mint>> {
+    let mut supplies: BTreeMap<String, BigInt> = BTreeMap::new();
+    let mut valid_mints = vec![];
+
+    for mint in mints {
+        let token = match token_store.get_at(0, &mint.token) {
+            Some(token) if rules::mint_is_authorized(&token, &mint) => token,
+            _ => continue,
+        };
+        let max_supply = parse_amount(&token.max_supply)?;
+        // The minted supply is the requested supply, up to the max supply
+        let supply =
+            supplies
+                .entry(mint.token.clone())
+                .or_insert_with(|| match requested(&mint.token) {
+                    Some(requested) if requested < max_supply => requested,
+                    Some(_) => max_supply.clone(),
+                    None => BigInt::zero(),
+                });
+
+        if let Some(amount) = rules::minted_amount(
+            &parse_amount(&mint.amount)?,
+            &parse_amount(&token.mint_limit)?,
+            &max_supply,
+            supply,
+        ) {
+            *supply = supply.clone() + &amount;
+            valid_mints.push(Mint {
+                amount: amount.to_string(),
+                ..mint
+            });
+        }
+    }
+
+    Ok(valid_mints)
+}
+
+/// Returns true if the token of an inscribed transfer has been deployed, and if its amount fits in
+/// the supply minted up to the transfer (see `rules::transfer_fits_supply`)
+fn transfer_is_valid(
+    transfer: &InscribedTransfer,
+    token_store: &impl StoreGet<Token>,
+    requested_supply_store: &impl StoreGet<BigInt>,
+) -> errors::Result<bool> {
+    let token = match token_store.get_at(0, &transfer.token) {
+        Some(token) => token,
+        None => return Ok(false),
+    };
+    let requested = requested_supply_store
+        .get_at(transfer.ordinal, &transfer.token)
+        .unwrap_or_else(BigInt::zero);
+
+    Ok(rules::transfer_fits_supply(
+        &parse_amount(&transfer.amount)?,
+        &parse_amount(&token.max_supply)?,
+        &requested,
+    ))
+}
+
+pub fn store_requested_supply(
+    events: Brc20Events,
+    token_store: &impl StoreGet<Token>,
+    store: &impl StoreAdd<BigInt>,
+) {
+    // Supply requested by the valid mints of every token, each block being capped on its own.
+    // Since it does not depend on the supply minted before the block, it can exceed the max
+    // supply: the minted supply of a token is the lower of the two (see `valid_mints`).
+    match valid_mints(events.mints, token_store, |_| None) {
+        Ok(mints) => mints.iter().for_each(|mint| {
+            add_amount(store, mint.ordinal, mint.token.clone(), &mint.amount, false)
+        }),
+        Err(err) => substreams::log::info!("Skipping mints: {}", err),
+    }
+}
+
+pub fn store_balances(events: Brc20Events, store: &impl StoreAdd<BigInt>) {
+    // Note: Store operations use the ordinal of the event that caused them so that the
+    // deltas can be traced back to their cause (see `balance_change_causes`)
+    let mut changes = Vec::new();
+
+    // On mints, we add the amount to the receiver's balance
+    events.mints.iter().for_each(|mint| {
+        changes.extend(amount_change(
+            mint.ordinal,
+            format!("{}:{}", mint.token, mint.to),
+            &mint.amount,
+            false,
+        ));
+    });
+
+    // On inscribed transfers, we subtract the amount from the sender's balance.
+    // Note: The sender's transferable balance is increased in the
+    // `store_transferable_balance` store module
+    events.inscribed_transfers.iter().for_each(|transfer| {
+        changes.extend(amount_change(
+            transfer.ordinal,
+            format!("{}:{}", transfer.token, transfer.from),
+            &transfer.amount,
+            true,
+        ));
+    });
+
+    // On executed transfers, we add the amount to the receiver's balance. On returned
+    // transfers, the receiver is the sender itself.
+    events
+        .executed_transfers
+        .iter()
+        .chain(events.returned_transfers.iter())
+        .for_each(|transfer| {
+            changes.extend(amount_change(
+                transfer.ordinal,
+                format!("{}:{}", transfer.token, transfer.to),
+                &transfer.amount,
+                false,
+            ));
+        });
+
+    add_in_ordinal_order(store, changes);
+}
+
+pub fn store_transferable_balances(events: Brc20Events, store: &impl StoreAdd<BigInt>) {
+    let mut changes = Vec::new();
+
+    // On inscribed transfers, we add the amount to the sender's transferable balance
+    events.inscribed_transfers.iter().for_each(|transfer| {
+        changes.extend(amount_change(
+            transfer.ordinal,
+            format!("{}:{}", transfer.token, transfer.from),
+            &transfer.amount,
+            false,
+        ));
+    });
+
+    // On executed, burned and returned transfers, we subtract the amount from the sender's
+    // transferable balance
+    events
+        .executed_transfers
+        .iter()
+        .chain(events.burned_transfers.iter())
+        .chain(events.returned_transfers.iter())
+        .for_each(|transfer| {
+            changes.extend(amount_change(
+                transfer.ordinal,
+                format!("{}:{}", transfer.token, transfer.from),
+                &transfer.amount,
+                true,
+            ));
+        });
+
+    add_in_ordinal_order(store, changes);
+}
+
+pub fn store_total_balances(events: Brc20Events, store: &impl StoreAdd<BigInt>) {
+    // Overall balance of an account, i.e.: its available balance plus its transferable
+    // balance. Inscribing a transfer or having it returned after it was spent as fee
+    // does not change the overall balance of the sender.
+    let mut changes = Vec::new();
+
+    // On mints, we add the amount to the receiver's balance
+    events.mints.iter().for_each(|mint| {
+        changes.extend(amount_change(
+            mint.ordinal,
+            format!("{}:{}", mint.token, mint.to),
+            &mint.amount,
+            false,
+        ));
+    });
+
+    // On executed transfers, we move the amount from the sender to the receiver
+    events.executed_transfers.iter().for_each(|transfer| {
+        changes.extend(amount_change(
+            transfer.ordinal,
+            format!("{}:{}", transfer.token, transfer.from),
+            &transfer.amount,
+            true,
+        ));
+        changes.extend(amount_change(
+            transfer.ordinal,
+            format!("{}:{}", transfer.token, transfer.to),
+            &transfer.amount,
+            false,
+        ));
+    });
+
+    // On burned transfers, we subtract the amount from the sender's balance
+    events.burned_transfers.iter().for_each(|transfer| {
+        changes.extend(amount_change(
+            transfer.ordinal,
+            format!("{}:{}", transfer.token, transfer.from),
+            &transfer.amount,
+            true,
+        ));
+    });
+
+    add_in_ordinal_order(store, changes);
+}
+
+pub fn store_token_stats(events: Brc20Events, store: &impl StoreAdd<BigInt>) {
+    // Keys are `{STAT}:{SYMBOL}`, where `STAT` is one of `minted`, `mint_count`,
+    // `burned` or `transfer_count`
+    events.mints.iter().for_each(|mint| {
+        add_amount(
+            store,
+            0,
+            format!("minted:{}", mint.token),
+            &mint.amount,
+            false,
+        );
+        store.add(0, format!("mint_count:{}", mint.token), BigInt::one());
+    });
+
+    events.executed_transfers.iter().for_each(|transfer| {
+        store.add(
+            0,
+            format!("transfer_count:{}", transfer.token),
+            BigInt::one(),
+        );
+    });
+
+    events.burned_transfers.iter().for_each(|transfer| {
+        add_amount(
+            store,
+            0,
+            format!("burned:{}", transfer.token),
+            &transfer.amount,
+            false,
+        );
+    });
+}
+
+pub fn store_token_holders(total_balances: Deltas<DeltaBigInt>, store: &impl StoreAdd<i64>) {
+    // An account becomes a holder of a token when its overall balance goes from zero to
+    // a positive amount, and stops being one when its overall balance goes back to zero
+    total_balances.deltas.iter().for_each(|delta| {
+        let (token, _) = match split_balance_key(&delta.key) {
+            Ok(parts) => parts,
+            Err(err) => {
+                substreams::log::info!("Skipping balance delta: {}", err);
+                return;
+            }
+        };
+
+        let was_holder = delta.old_value > BigInt::zero();
+        let is_holder = delta.new_value > BigInt::zero();
+
+        match (was_holder, is_holder) {
+            (false, true) => store.add(0, token, 1),
+            (true, false) => store.add(0, token, -1),
+            _ => (),
+        }
+    });
+}
+
+pub fn store_token_completion(
+    clock: Clock,
+    token_stats: Deltas<DeltaBigInt>,
+    token_store: &impl StoreGet<Token>,
+    store: &impl StoreSetIfNotExists<i64>,
+) {
+    // Records the block at which the minted supply of a token reached its max supply
+    token_stats
+        .deltas
+        .iter()
+        .filter_map(|delta| {
+            let token = delta.key.strip_prefix("minted:")?;
+            Some((token, delta))
+        })
+        .for_each(|(token, delta)| {
+            if let Some(token_info) = token_store.get_last(token) {
+                match parse_amount(&token_info.max_supply) {
+                    Ok(max_supply) if delta.new_value >= max_supply => {
+                        store.set_if_not_exists(0, token, &(clock.number as i64))
+                    }
+                    Ok(_) => (),
+                    Err(err) => substreams::log::info!("Skipping token {}: {}", token, err),
+                }
+            }
+        });
+}
+
+pub fn map_resolve_transfers(
+    block: btc::Block,
+    events: Brc20Events,
+    transfer_store: &impl StoreGet<InscribedTransferLocations>,
+    token_store: &impl StoreGet<Token>,
+    requested_supply_store: &impl StoreGet<BigInt>,
+    sat_spends: UtxoSpends,
+) -> Result<Brc20Events, substreams::errors::Error> {
+    // Destinations of the sats of the UTXOs spent in the block
+    let spends: HashMap<&str, &UtxoSpend> = sat_spends
+        .spends
+        .iter()
+        .map(|spend| (spend.utxo.as_str(), spend))
+        .collect();
+
+    let mut executed_transfers = vec![];
+    let mut burned_transfers = vec![];
+    let mut returned_transfers = vec![];
+
+    for (tx_index, tx) in block.tx.iter().enumerate() {
+        // Index of the transfer executions within the transaction (see `event_ordinal`)
+        let mut execution_index = 0;
+
+        for vin in tx.vin.iter() {
+            let inscribed_transfer_locs = match transfer_store.get_at(0, utxo_key(&vin.outpoint()))
+            {
+                Some(inscribed_transfer_locs) => inscribed_transfer_locs.locations,
+                None => continue,
+            };
+
+            let spend = match spends.get(vin.outpoint().as_str()) {
+                Some(spend) => spend,
+                None => {
+                    substreams::log::info!(
+                        "Could not resolve inscribed transfers held by {}: unknown spend",
+                        vin.outpoint()
+                    );
+                    continue;
+                }
+            };
+
+            // Every transfer held by the spent outpoint is executed independently
+            for inscribed_transfer_loc in inscribed_transfer_locs {
+                let transfer = ExecutedTransfer {
+                    id: inscribed_transfer_loc.id,
+                    token: inscribed_transfer_loc.token,
+                    from: inscribed_transfer_loc.from,
+                    to: "".into(),
+                    amount: inscribed_transfer_loc.amount,
+                    ordinal: event_ordinal(tx_index, execution_index),
+                    txid: tx.txid.clone(),
+                    utxo: "".into(),
+                };
+                execution_index += 1;
+
+                // Output of the transaction which received the inscribed sat, if any
+                let vout = ordinals::sat_destination(spend, inscribed_transfer_loc.offset)
+                    .and_then(|to| {
+                        let (txid, n) = to.utxo.split_once(':')?;
+                        let n: u32 = n.parse().ok()?;
+                        tx.vout.iter().find(|vout| txid == tx.txid && vout.n == n)
+                    });
+
+                match vout {
+                    // Transfers spent as fee are returned to the sender, even when the miner
+                    // claims their sat
+                    None => returned_transfers.push(ExecutedTransfer {
+                        to: transfer.from.clone(),
+                        ..transfer
+                    }),
+                    // Transfers sent to an OP_RETURN output are burned, as nobody can ever spend them
+                    Some(vout) if vout.is_op_return() => burned_transfers.push(ExecutedTransfer {
+                        utxo: format!("{}:{}", tx.txid, vout.n),
+                        ..transfer
+                    }),
+                    // Outputs without an address are owned by their scriptPubKey, and outputs
+                    // without any script are burned, as there is no owner to credit
+                    Some(vout) => match vout.owner() {
+                        Some(to) => executed_transfers.push(ExecutedTransfer {
+                            to,
+                            utxo: format!("{}:{}", tx.txid, vout.n),
+                            ..transfer
+                        }),
+                        None => burned_transfers.push(ExecutedTransfer {
+                            utxo: format!("{}:{}", tx.txid, vout.n),
+                            ..transfer
+                        }),
+                    },
+                }
+            }
+        }
+    }
+
+    // Only keep valid mints, from the supply requested before the block
+    let mints = valid_mints(events.mints, token_store, |token| {
+        requested_supply_store.get_first(token)
+    })?;
+
+    // Transfers of tokens that have not been deployed, or above their minted supply, are invalid
+    let (inscribed_transfers, invalid_transfers) = events
+        .inscribed_transfers
+        .into_iter()
+        .partition(|transfer| {
+            matches!(
+                transfer_is_valid(transfer, token_store, requested_supply_store),
+                Ok(true)
+            )
+        });
+
+    Ok(Brc20Events {
+        // Only keep the deployment which was actually stored, i.e.: the first deployment of the ticker
+        deploys: events
+            .deploys
+            .into_iter()
+            .filter(|deploy| match token_store.get_at(0, &deploy.symbol) {
+                Some(token) => token.id == deploy.id,
+                None => false,
+            })
+            .collect(),
+        inscribed_transfers,
+        executed_transfers,
+        burned_transfers,
+        returned_transfers,
+        invalid_transfers,
+        mints,
+    })
+}
+
+pub fn map_filtered_events(
+    params: String,
+    events: Brc20Events,
+) -> Result<Brc20Events, substreams::errors::Error> {
+    Ok(TickFilter::from_params(&params)?.events(events))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn graph_out(
+    params: String,
+    clock: Clock,
+    events: Brc20Events,
+    balances_store: Deltas<DeltaBigInt>,
+    transferable_balances_store: Deltas<DeltaBigInt>,
+    token_stats_store: Deltas<DeltaBigInt>,
+    token_holders_store: Deltas<DeltaInt64>,
+    token_completion_store: Deltas<DeltaInt64>,
+) -> Result<EntityChanges, substreams::errors::Error> {
+    let filter = TickFilter::from_params(&params)?;
+    Ok(sinks::graph_out(
+        &clock,
+        &filter.events(events),
+        &filter.balance_deltas(balances_store),
+        &filter.balance_deltas(transferable_balances_store),
+        &filter.token_stat_deltas(token_stats_store),
+        &filter.token_deltas(token_holders_store),
+        &filter.token_deltas(token_completion_store),
+    )?)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn db_out(
+    params: String,
+    clock: Clock,
+    events: Brc20Events,
+    balances_store: Deltas<DeltaBigInt>,
+    transferable_balances_store: Deltas<DeltaBigInt>,
+    token_stats_store: Deltas<DeltaBigInt>,
+    token_holders_store: Deltas<DeltaInt64>,
+    token_completion_store: Deltas<DeltaInt64>,
+) -> Result<DatabaseChanges, substreams::errors::Error> {
+    let filter = TickFilter::from_params(&params)?;
+    Ok(sinks::db_out(
+        &clock,
+        &filter.events(events),
+        &filter.balance_deltas(balances_store),
+        &filter.balance_deltas(transferable_balances_store),
+        &filter.token_stat_deltas(token_stats_store),
+        &filter.token_deltas(token_holders_store),
+        &filter.token_deltas(token_completion_store),
+    )?)
+}
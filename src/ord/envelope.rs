@@ -16,7 +16,7 @@ use {
     std::iter::Peekable,
 };
 
-pub(crate) const PROTOCOL_ID: [u8; 3] = *b"ord";
+pub const PROTOCOL_ID: [u8; 3] = *b"ord";
 
 pub(crate) const BODY_TAG: [u8; 0] = [];
 pub(crate) const CONTENT_TYPE_TAG: [u8; 1] = [1];
@@ -28,15 +28,15 @@ pub(crate) const CONTENT_ENCODING_TAG: [u8; 1] = [9];
 
 type Result<T> = std::result::Result<T, script::Error>;
 type RawEnvelope = Envelope<Vec<Vec<u8>>>;
-pub(crate) type ParsedEnvelope = Envelope<Inscription>;
+pub type ParsedEnvelope = Envelope<Inscription>;
 
 #[derive(Debug, Default, PartialEq, Clone)]
-pub(crate) struct Envelope<T> {
-    pub(crate) input: u32,
-    pub(crate) offset: u32,
-    pub(crate) payload: T,
-    pub(crate) pushnum: bool,
-    pub(crate) stutter: bool,
+pub struct Envelope<T> {
+    pub input: u32,
+    pub offset: u32,
+    pub payload: T,
+    pub pushnum: bool,
+    pub stutter: bool,
 }
 
 fn remove_field(fields: &mut BTreeMap<&[u8], Vec<&[u8]>>, field: &[u8]) -> Option<Vec<u8>> {
@@ -128,12 +128,19 @@ impl From<RawEnvelope> for ParsedEnvelope {
 }
 
 impl ParsedEnvelope {
-    pub(crate) fn from_transaction(transaction: &Transaction) -> Vec<Self> {
+    pub fn from_transaction(transaction: &Transaction) -> Vec<Self> {
         RawEnvelope::from_transaction(transaction)
             .into_iter()
             .map(|envelope| envelope.into())
             .collect()
     }
+
+    pub fn from_tapscript(tapscript: &Script, input: usize) -> Result<Vec<Self>> {
+        Ok(RawEnvelope::from_tapscript(tapscript, input)?
+            .into_iter()
+            .map(|envelope| envelope.into())
+            .collect())
+    }
 }
 
 impl RawEnvelope {
@@ -151,7 +158,7 @@ impl RawEnvelope {
         envelopes
     }
 
-    fn from_tapscript(tapscript: &Script, input: usize) -> Result<Vec<Self>> {
+    pub(crate) fn from_tapscript(tapscript: &Script, input: usize) -> Result<Vec<Self>> {
         let mut envelopes = Vec::new();
 
         let mut instructions = tapscript.instructions().peekable();
@@ -208,8 +215,10 @@ impl RawEnvelope {
                     return Ok((
                         false,
                         Some(Envelope {
-                            input: input.try_into().unwrap(),
-                            offset: offset.try_into().unwrap(),
+                            // Neither can exceed `u32::MAX` in a transaction that fits in a
+                            // block, but the witness is untrusted: saturate instead of panicking
+                            input: u32::try_from(input).unwrap_or(u32::MAX),
+                            offset: u32::try_from(offset).unwrap_or(u32::MAX),
                             payload,
                             pushnum,
                             stutter,
@@ -292,3 +301,210 @@ impl RawEnvelope {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::{opcodes::OP_FALSE, script::PushBytesBuf, ScriptBuf};
+    use proptest::{collection::vec, prelude::any, strategy::Strategy};
+
+    use super::*;
+    use crate::tx_builder::{external_outpoint, TxBuilder};
+
+    fn bytes(max_len: usize) -> impl Strategy<Value = Vec<u8>> {
+        vec(any::<u8>(), 0..max_len)
+    }
+
+    fn inscription() -> impl Strategy<Value = Inscription> {
+        let field = || proptest::option::of(bytes(80));
+        (
+            (field(), field(), field(), field(), field()),
+            // Empty metadata is not pushed at all, so it cannot round-trip
+            proptest::option::of(vec(any::<u8>(), 1..1600)),
+            proptest::option::of(bytes(1600)),
+        )
+            .prop_map(
+                |(
+                    (content_type, content_encoding, metaprotocol, parent, pointer),
+                    metadata,
+                    body,
+                )| {
+                    Inscription {
+                        body,
+                        content_encoding,
+                        content_type,
+                        metadata,
+                        metaprotocol,
+                        parent,
+                        pointer,
+                        ..Default::default()
+                    }
+                },
+            )
+    }
+
+    /// The inscription as parsed back from its reveal script: metadata is pushed in chunks of
+    /// 520 bytes, each under its own tag
+    fn revealed(inscription: Inscription) -> Inscription {
+        Inscription {
+            duplicate_field: inscription.metadata.as_ref().map_or(0, Vec::len) > 520,
+            ..inscription
+        }
+    }
+
+    fn push(builder: script::Builder, bytes: &[u8]) -> script::Builder {
+        builder.push_slice(PushBytesBuf::try_from(bytes.to_vec()).unwrap())
+    }
+
+    /// Tapscript with a single envelope, whose content is pushed by `build`
+    fn envelope(build: impl FnOnce(script::Builder) -> script::Builder) -> ScriptBuf {
+        let builder = script::Builder::new()
+            .push_opcode(OP_FALSE)
+            .push_opcode(opcodes::all::OP_IF)
+            .push_slice(PROTOCOL_ID);
+
+        build(builder)
+            .push_opcode(opcodes::all::OP_ENDIF)
+            .into_script()
+    }
+
+    fn parse(tapscript: ScriptBuf) -> Vec<ParsedEnvelope> {
+        let tx = TxBuilder::new()
+            .input(external_outpoint(1))
+            .tapscript(external_outpoint(2), tapscript)
+            .build();
+
+        ParsedEnvelope::from_transaction(&tx)
+    }
+
+    fn parse_one(tapscript: ScriptBuf) -> ParsedEnvelope {
+        let mut envelopes = parse(tapscript);
+        assert_eq!(envelopes.len(), 1);
+        envelopes.remove(0)
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn prop_round_trip(inscription in inscription()) {
+            let tx = TxBuilder::new()
+                .reveal(external_outpoint(1), std::slice::from_ref(&inscription))
+                .build();
+
+            proptest::prop_assert_eq!(
+                ParsedEnvelope::from_transaction(&tx),
+                vec![ParsedEnvelope {
+                    payload: revealed(inscription),
+                    ..Default::default()
+                }]
+            );
+        }
+
+        #[test]
+        fn prop_batch_round_trip(inscriptions in vec(inscription(), 1..4)) {
+            let tx = TxBuilder::new()
+                .input(external_outpoint(1))
+                .reveal(external_outpoint(2), &inscriptions)
+                .build();
+
+            let expected = inscriptions
+                .into_iter()
+                .enumerate()
+                .map(|(offset, payload)| ParsedEnvelope {
+                    input: 1,
+                    offset: offset as u32,
+                    payload: revealed(payload),
+                    ..Default::default()
+                })
+                .collect::<Vec<_>>();
+            proptest::prop_assert_eq!(ParsedEnvelope::from_transaction(&tx), expected);
+        }
+
+        #[test]
+        fn prop_pushnum(value in (-1i64..=16).prop_filter("OP_0 is an empty push", |v| *v != 0)) {
+            // The pointer's tag is pushed as OP_PUSHNUM_2
+            let envelope = parse_one(envelope(|builder| builder.push_int(2).push_int(value)));
+
+            let expected = if value == -1 { 0x81 } else { value as u8 };
+            proptest::prop_assert!(envelope.pushnum);
+            proptest::prop_assert_eq!(envelope.payload.pointer, Some(vec![expected]));
+        }
+
+        #[test]
+        fn prop_stutter(stutters in 0usize..4) {
+            // Pushes of an empty slice in front of the envelope's own
+            let tapscript = [vec![OP_FALSE.to_u8(); stutters], envelope(|builder| builder).into_bytes()];
+            let envelope = parse_one(ScriptBuf::from_bytes(tapscript.concat()));
+
+            proptest::prop_assert_eq!(envelope.stutter, stutters > 0);
+        }
+
+        #[test]
+        fn prop_chunked_body(chunks in vec(bytes(600), 0..8)) {
+            let envelope = parse_one(envelope(|builder| {
+                chunks
+                    .iter()
+                    .fold(push(builder, &BODY_TAG), |builder, chunk| push(builder, chunk))
+            }));
+
+            proptest::prop_assert_eq!(envelope.payload.body, Some(chunks.concat()));
+            proptest::prop_assert!(!envelope.payload.incomplete_field);
+        }
+
+        #[test]
+        fn prop_metadata_concatenation(chunks in vec(bytes(600), 1..6)) {
+            let envelope = parse_one(envelope(|builder| {
+                chunks.iter().fold(builder, |builder, chunk| {
+                    push(push(builder, &METADATA_TAG), chunk)
+                })
+            }));
+
+            proptest::prop_assert_eq!(envelope.payload.metadata, Some(chunks.concat()));
+            proptest::prop_assert_eq!(envelope.payload.duplicate_field, chunks.len() > 1);
+        }
+
+        #[test]
+        fn prop_duplicate_fields(content_types in vec(bytes(80), 2..4)) {
+            let envelope = parse_one(envelope(|builder| {
+                content_types.iter().fold(builder, |builder, content_type| {
+                    push(push(builder, &CONTENT_TYPE_TAG), content_type)
+                })
+            }));
+
+            proptest::prop_assert!(envelope.payload.duplicate_field);
+            proptest::prop_assert_eq!(envelope.payload.content_type, Some(content_types[0].clone()));
+        }
+
+        #[test]
+        fn prop_arbitrary_pushes(pushes in vec(bytes(600), 0..10)) {
+            let tapscript = envelope(|builder| {
+                pushes.iter().fold(builder, |builder, bytes| push(builder, bytes))
+            });
+
+            let raw = RawEnvelope::from_tapscript(&tapscript, 0).unwrap();
+            proptest::prop_assert_eq!(raw.len(), 1);
+            proptest::prop_assert_eq!(&raw[0].payload, &pushes);
+
+            let body = pushes
+                .iter()
+                .enumerate()
+                .position(|(i, push)| i % 2 == 0 && push.is_empty())
+                .map(|i| pushes[i + 1..].concat());
+            let incomplete_field = body.is_none() && pushes.len() % 2 == 1;
+            let parsed = ParsedEnvelope::from(raw[0].clone());
+            proptest::prop_assert_eq!(parsed.payload.body, body);
+            proptest::prop_assert_eq!(parsed.payload.incomplete_field, incomplete_field);
+        }
+
+        #[test]
+        fn prop_arbitrary_tapscript(tapscript in bytes(2000)) {
+            // Must never panic, whatever the witness contains
+            if let Ok(envelopes) = ParsedEnvelope::from_tapscript(Script::from_bytes(&tapscript), 0) {
+                for envelope in envelopes {
+                    envelope.payload.parent();
+                    envelope.payload.pointer();
+                    envelope.payload.content_type();
+                    envelope.payload.content_encoding();
+                }
+            }
+        }
+    }
+}
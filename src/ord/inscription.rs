@@ -128,7 +128,7 @@ impl Inscription {
         Inscription::append_batch_reveal_script_to_builder(inscriptions, builder).into_script()
     }
 
-    pub(crate) fn body(&self) -> Option<&[u8]> {
+    pub fn body(&self) -> Option<&[u8]> {
         Some(self.body.as_ref()?)
     }
 
@@ -136,24 +136,24 @@ impl Inscription {
         self.body
     }
 
-    pub(crate) fn content_length(&self) -> Option<usize> {
+    pub fn content_length(&self) -> Option<usize> {
         Some(self.body()?.len())
     }
 
-    pub(crate) fn content_type(&self) -> Option<&str> {
+    pub fn content_type(&self) -> Option<&str> {
         str::from_utf8(self.content_type.as_ref()?).ok()
     }
 
-    pub(crate) fn content_encoding(&self) -> Option<HeaderValue> {
+    pub fn content_encoding(&self) -> Option<HeaderValue> {
         HeaderValue::from_str(str::from_utf8(self.content_encoding.as_ref()?).unwrap_or_default())
             .ok()
     }
 
-    pub(crate) fn metaprotocol(&self) -> Option<&str> {
+    pub fn metaprotocol(&self) -> Option<&str> {
         str::from_utf8(self.metaprotocol.as_ref()?).ok()
     }
 
-    pub(crate) fn parent(&self) -> Option<InscriptionId> {
+    pub fn parent(&self) -> Option<InscriptionId> {
         let value = self.parent.as_ref()?;
 
         if value.len() < Txid::LEN {
@@ -188,7 +188,7 @@ impl Inscription {
         Some(InscriptionId { txid, index })
     }
 
-    pub(crate) fn pointer(&self) -> Option<u64> {
+    pub fn pointer(&self) -> Option<u64> {
         let value = self.pointer.as_ref()?;
 
         if value.iter().skip(8).copied().any(|byte| byte != 0) {
@@ -0,0 +1,771 @@
+use std::collections::HashMap;
+
+use crate::{
+    btc_utils::{block_supply, subsidy},
+    errors::{Error, Result},
+    pb::{
+        ordinals::v1::{
+            self as ord_pb, InscriptionLocation, OrdinalBlock, OrdinalBlocks, UtxoSpend,
+        },
+        sf::bitcoin::r#type::v1 as btc,
+    },
+};
+
+/// Returns the ordinal of the first sat mined in the block at the given height
+pub fn first_sat(height: u64) -> u64 {
+    match height {
+        0 => 0,
+        height => block_supply(height - 1),
+    }
+}
+
+/// Returns the ranges of sats `[start, start + size)` of the concatenation of the given
+/// segments, expressed as ranges of the segments themselves. A segment is a range of sats of
+/// a UTXO (or of newly mined sats if its `utxo` is empty).
+fn slice_segments(segments: &[OrdinalBlock], start: u64, size: u64) -> Vec<OrdinalBlock> {
+    let end = start + size;
+    let mut blocks = vec![];
+    let mut offset = 0;
+
+    for segment in segments {
+        let (segment_start, segment_end) = (offset, offset + segment.size);
+        offset = segment_end;
+
+        if segment_end <= start || segment_start >= end {
+            continue;
+        }
+
+        let slice_start = start.max(segment_start);
+        let slice_end = end.min(segment_end);
+        blocks.push(OrdinalBlock {
+            utxo: segment.utxo.clone(),
+            address: None,
+            start: segment.start + (slice_start - segment_start),
+            size: slice_end - slice_start,
+        });
+    }
+
+    blocks
+}
+
+/// Returns the inputs of a transaction as segments, along with the total value of the inputs
+fn input_segments(
+    tx: &btc::Transaction,
+    input_sats: &impl Fn(&btc::Vin) -> Option<u64>,
+) -> Result<(Vec<OrdinalBlock>, u64)> {
+    let segments = tx
+        .vin
+        .iter()
+        .map(|vin| {
+            let size = input_sats(vin).ok_or_else(|| Error::UnknownUtxo(vin.outpoint()))?;
+            Ok(OrdinalBlock {
+                utxo: vin.outpoint(),
+                address: None,
+                start: 0,
+                size,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let total = segments.iter().map(|segment| segment.size).sum();
+
+    Ok((segments, total))
+}
+
+fn is_coinbase(tx: &btc::Transaction) -> bool {
+    tx.vin
+        .first()
+        .map(|vin| !vin.coinbase.is_empty())
+        .unwrap_or(false)
+}
+
+/// Assigns the sats of the block's transactions to their outputs, following ord's first in,
+/// first out rule.
+///
+/// The sats of a regular transaction are assigned relatively to the concatenation of its inputs
+/// (`relative_ordinals`). The coinbase transaction first receives the newly mined sats, which
+/// are assigned absolutely (`coinbase_ordinals`), and then the fees of every transaction of the
+/// block, in order, which are assigned relatively to the concatenation of the fees
+/// (`relative_ordinals`). Sats which are not assigned to any output are lost.
+pub fn block_ordinals(
+    block: &btc::Block,
+    input_sats: impl Fn(&btc::Vin) -> Option<u64>,
+) -> Result<ord_pb::Block> {
+    let height = block.height as u64;
+    let mut ordinals = ord_pb::Block {
+        timestamp: block.time as u64,
+        number: height,
+        subsidy: subsidy(height),
+        ..Default::default()
+    };
+
+    for (idx, tx) in block.tx.iter().enumerate() {
+        let output_sats = tx.vout_sats()?;
+        let mut ord_tx = ord_pb::Transaction {
+            txid: tx.txid.clone(),
+            idx: idx as u64,
+            amount: output_sats.iter().sum(),
+            ..Default::default()
+        };
+
+        let coinbase = is_coinbase(tx);
+        if !coinbase {
+            let (_, total_input) = input_segments(tx, &input_sats)?;
+            ordinals.fees += total_input.saturating_sub(ord_tx.amount);
+            ord_tx.input_utxos = tx.vin.iter().map(|vin| vin.outpoint()).collect();
+        }
+
+        let mut offset = 0;
+        for (vout, sats) in tx.vout.iter().zip(output_sats) {
+            let block = OrdinalBlock {
+                utxo: format!("{}:{}", tx.txid, vout.n),
+                address: vout.address(),
+                start: offset,
+                size: sats,
+            };
+            offset += sats;
+
+            if sats == 0 {
+                continue;
+            }
+
+            if !coinbase {
+                ord_tx.relative_ordinals.push(block);
+                continue;
+            }
+
+            // Coinbase outputs first receive the newly mined sats, then the fees
+            let subsidy = ordinals.subsidy;
+            if block.start < subsidy {
+                let size = block.size.min(subsidy - block.start);
+                ord_tx.coinbase_ordinals.push(OrdinalBlock {
+                    start: first_sat(height) + block.start,
+                    size,
+                    ..block.clone()
+                });
+            }
+            if block.start + block.size > subsidy {
+                let start = block.start.max(subsidy);
+                ord_tx.relative_ordinals.push(OrdinalBlock {
+                    start: start - subsidy,
+                    size: block.start + block.size - start,
+                    ..block
+                });
+            }
+        }
+
+        ordinals.txs.push(ord_tx);
+    }
+
+    ordinals.miner_reward = ordinals
+        .txs
+        .iter()
+        .zip(block.tx.iter())
+        .filter(|(_, tx)| is_coinbase(tx))
+        .map(|(ord_tx, _)| ord_tx.amount)
+        .sum();
+
+    Ok(ordinals)
+}
+
+/// Returns the ordinal blocks held by each UTXO created in the block (see `OrdinalBlocks`)
+pub fn utxo_ordinal_blocks(
+    block: &btc::Block,
+    ordinals: &ord_pb::Block,
+    input_sats: impl Fn(&btc::Vin) -> Option<u64>,
+) -> Result<Vec<(String, OrdinalBlocks)>> {
+    let mut utxos = vec![];
+    // Fees of the block's transactions, as ranges of their inputs
+    let mut fee_segments = vec![];
+
+    for (tx, ord_tx) in block.tx.iter().zip(ordinals.txs.iter()) {
+        if is_coinbase(tx) {
+            continue;
+        }
+
+        let (segments, total_input) = input_segments(tx, &input_sats)?;
+        let fee = total_input.saturating_sub(ord_tx.amount);
+        fee_segments.extend(slice_segments(&segments, ord_tx.amount, fee));
+
+        utxos.extend(ord_tx.relative_ordinals.iter().map(|block| {
+            (
+                block.utxo.clone(),
+                OrdinalBlocks {
+                    blocks: slice_segments(&segments, block.start, block.size),
+                    address: block.address.clone(),
+                },
+            )
+        }));
+    }
+
+    for (tx, ord_tx) in block.tx.iter().zip(ordinals.txs.iter()) {
+        if !is_coinbase(tx) {
+            continue;
+        }
+
+        let mined = ord_tx.coinbase_ordinals.iter().map(|block| {
+            (
+                block,
+                OrdinalBlock {
+                    utxo: "".into(),
+                    address: None,
+                    start: block.start,
+                    size: block.size,
+                },
+            )
+        });
+        let fees = ord_tx.relative_ordinals.iter().flat_map(|block| {
+            slice_segments(&fee_segments, block.start, block.size)
+                .into_iter()
+                .map(move |segment| (block, segment))
+        });
+
+        // Coinbase outputs hold their newly mined sats before their fees
+        let mut coinbase_utxos: Vec<(String, OrdinalBlocks)> = vec![];
+        for (output, block) in mined.chain(fees) {
+            match coinbase_utxos
+                .iter_mut()
+                .find(|(utxo, _)| *utxo == output.utxo)
+            {
+                Some((_, blocks)) => blocks.blocks.push(block),
+                None => coinbase_utxos.push((
+                    output.utxo.clone(),
+                    OrdinalBlocks {
+                        blocks: vec![block],
+                        address: output.address.clone(),
+                    },
+                )),
+            }
+        }
+        utxos.extend(coinbase_utxos);
+    }
+
+    Ok(utxos)
+}
+
+/// Returns the absolute ranges of the sats `[start, start + size)` of a UTXO, walking back through
+/// the ordinal blocks of the UTXOs they come from (see `utxo_ordinal_blocks`) up to the blocks in
+/// which they were mined. Ordinal blocks are looked up with `ordinal_blocks`, unless the ranges
+/// of the UTXO are already `resolved`.
+fn resolve_sat_ranges(
+    utxo: &str,
+    start: u64,
+    size: u64,
+    ordinal_blocks: &impl Fn(&str) -> Option<OrdinalBlocks>,
+    resolved: &HashMap<String, Vec<OrdinalBlock>>,
+) -> Result<Vec<OrdinalBlock>> {
+    let mut ranges: Vec<OrdinalBlock> = vec![];
+    // Blocks left to resolve, the next one last
+    let mut pending = vec![OrdinalBlock {
+        utxo: utxo.into(),
+        address: None,
+        start,
+        size,
+    }];
+
+    while let Some(block) = pending.pop() {
+        if block.utxo.is_empty() {
+            // Merge contiguous ranges
+            match ranges.last_mut() {
+                Some(last) if last.start + last.size == block.start => last.size += block.size,
+                _ => ranges.push(block),
+            }
+            continue;
+        }
+
+        let slices = match resolved.get(&block.utxo) {
+            Some(ranges) => slice_segments(ranges, block.start, block.size),
+            None => {
+                let blocks = ordinal_blocks(&block.utxo)
+                    .ok_or_else(|| Error::UnknownUtxo(block.utxo.clone()))?;
+                slice_segments(&blocks.blocks, block.start, block.size)
+            }
+        };
+        pending.extend(slices.into_iter().rev());
+    }
+
+    Ok(ranges)
+}
+
+/// Absolute ranges of the sats held by a UTXO, or the error preventing their resolution
+pub type UtxoRanges = (String, Result<OrdinalBlocks>);
+
+/// Returns the absolute ranges of the sats held by the UTXOs spent by the block and by the UTXOs
+/// created by the block, in order. The ordinal blocks of the UTXOs created by the block are
+/// computed from `ordinals` (see `utxo_ordinal_blocks`), while the ones of earlier UTXOs are
+/// looked up with `ordinal_blocks` (e.g.: from `store_ordinal_blocks`). UTXOs holding sats whose
+/// ranges are unknown are reported as such.
+pub fn block_sat_ranges(
+    block: &btc::Block,
+    ordinals: &ord_pb::Block,
+    input_sats: impl Fn(&btc::Vin) -> Option<u64>,
+    ordinal_blocks: impl Fn(&str) -> Option<OrdinalBlocks>,
+) -> Result<(Vec<UtxoRanges>, Vec<UtxoRanges>)> {
+    let created_blocks: HashMap<String, OrdinalBlocks> =
+        utxo_ordinal_blocks(block, ordinals, input_sats)?
+            .into_iter()
+            .collect();
+    let ordinal_blocks = |utxo: &str| {
+        created_blocks
+            .get(utxo)
+            .cloned()
+            .or_else(|| ordinal_blocks(utxo))
+    };
+
+    // Ranges of the UTXOs resolved so far, which their descendants are resolved from
+    let mut resolved: HashMap<String, Vec<OrdinalBlock>> = HashMap::new();
+    let mut resolve = |utxo: String| -> UtxoRanges {
+        let ranges = ordinal_blocks(&utxo)
+            .ok_or_else(|| Error::UnknownUtxo(utxo.clone()))
+            .and_then(|blocks| {
+                let size = blocks.blocks.iter().map(|block| block.size).sum();
+                let ranges = resolve_sat_ranges(&utxo, 0, size, &ordinal_blocks, &resolved)?;
+                resolved.insert(utxo.clone(), ranges.clone());
+                Ok(OrdinalBlocks {
+                    blocks: ranges,
+                    address: blocks.address,
+                })
+            });
+        (utxo, ranges)
+    };
+
+    let spent = block
+        .tx
+        .iter()
+        .filter(|tx| !is_coinbase(tx))
+        .flat_map(|tx| tx.vin.iter())
+        .map(|vin| resolve(vin.outpoint()))
+        .collect();
+    let created = block
+        .tx
+        .iter()
+        .flat_map(|tx| {
+            tx.vout
+                .iter()
+                .map(move |vout| format!("{}:{}", tx.txid, vout.n))
+        })
+        .filter(|utxo| created_blocks.contains_key(utxo))
+        .map(resolve)
+        .collect();
+
+    Ok((spent, created))
+}
+
+/// Returns the absolute ranges of the sats held by a UTXO. Ranges are looked up with
+/// `utxo_ranges` (e.g.: from `store_ordinals`).
+pub fn locate_sats(
+    utxo: &str,
+    utxo_ranges: impl Fn(&str) -> Option<OrdinalBlocks>,
+) -> Result<Vec<OrdinalBlock>> {
+    utxo_ranges(utxo)
+        .map(|ranges| ranges.blocks)
+        .ok_or_else(|| Error::UnknownUtxo(utxo.into()))
+}
+
+/// Returns the destinations of the sats of each UTXO spent in the block (see `UtxoSpend`), i.e.:
+/// the inverse of `utxo_ordinal_blocks`. Sats spent as fee are followed to the coinbase outputs.
+pub fn utxo_spends(
+    block: &btc::Block,
+    ordinals: &ord_pb::Block,
+    input_sats: impl Fn(&btc::Vin) -> Option<u64>,
+) -> Result<Vec<UtxoSpend>> {
+    let addresses: HashMap<&str, Option<String>> = ordinals
+        .txs
+        .iter()
+        .flat_map(|tx| {
+            tx.coinbase_ordinals
+                .iter()
+                .chain(tx.relative_ordinals.iter())
+        })
+        .map(|block| (block.utxo.as_str(), block.address.clone()))
+        .collect();
+
+    // Destinations of the sats of each spent UTXO, along with their offset within the UTXO
+    let mut destinations: HashMap<String, Vec<(u64, OrdinalBlock)>> = HashMap::new();
+    for (utxo, blocks) in utxo_ordinal_blocks(block, ordinals, &input_sats)? {
+        let mut offset = 0;
+        for block in blocks.blocks {
+            if !block.utxo.is_empty() {
+                destinations.entry(block.utxo).or_default().push((
+                    block.start,
+                    OrdinalBlock {
+                        utxo: utxo.clone(),
+                        address: addresses.get(utxo.as_str()).cloned().flatten(),
+                        start: offset,
+                        size: block.size,
+                    },
+                ));
+            }
+            offset += block.size;
+        }
+    }
+
+    let mut spends = vec![];
+    for (tx_index, tx) in block.tx.iter().enumerate() {
+        if is_coinbase(tx) {
+            continue;
+        }
+        for vin in tx.vin.iter() {
+            let utxo = vin.outpoint();
+            let size = input_sats(vin).ok_or_else(|| Error::UnknownUtxo(utxo.clone()))?;
+            let mut utxo_destinations = destinations.remove(&utxo).unwrap_or_default();
+            utxo_destinations.sort_by_key(|(start, _)| *start);
+
+            // Sats which are not assigned to any output are lost
+            let lost = |size| OrdinalBlock {
+                utxo: "".into(),
+                address: None,
+                start: 0,
+                size,
+            };
+            let mut blocks = vec![];
+            let mut offset = 0;
+            for (start, block) in utxo_destinations {
+                if start > offset {
+                    blocks.push(lost(start - offset));
+                }
+                offset = start + block.size;
+                blocks.push(block);
+            }
+            if size > offset {
+                blocks.push(lost(size - offset));
+            }
+
+            spends.push(UtxoSpend {
+                txid: tx.txid.clone(),
+                blocks,
+                utxo,
+                tx_index: tx_index as u64,
+            });
+        }
+    }
+
+    Ok(spends)
+}
+
+/// Returns the ordinal of the sat at `offset` within a UTXO, given its absolute ranges of sats
+pub fn sat_at(ranges: &[OrdinalBlock], offset: u64) -> Option<u64> {
+    slice_segments(ranges, offset, 1)
+        .first()
+        .map(|block| block.start)
+}
+
+/// Returns the location to which the sat at `offset` within a spent UTXO was sent, or `None` if
+/// the sat was lost (i.e.: spent as fee and not claimed by the miner)
+pub fn sat_destination(spend: &UtxoSpend, offset: u64) -> Option<InscriptionLocation> {
+    let mut offset = offset;
+    spend
+        .blocks
+        .iter()
+        .find(|block| {
+            if offset >= block.size {
+                offset -= block.size;
+                return false;
+            }
+            true
+        })
+        .filter(|block| !block.utxo.is_empty())
+        .map(|block| InscriptionLocation {
+            utxo: block.utxo.clone(),
+            offset: block.start + offset,
+            address: block.address.clone(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn tx(txid: &str, inputs: &[&str], outputs: &[f64]) -> btc::Transaction {
+        btc::Transaction {
+            txid: txid.into(),
+            vin: inputs
+                .iter()
+                .map(|input| match input.split_once(':') {
+                    Some((txid, vout)) => btc::Vin {
+                        txid: txid.into(),
+                        vout: vout.parse().unwrap(),
+                        ..Default::default()
+                    },
+                    None => btc::Vin {
+                        coinbase: "03".into(),
+                        ..Default::default()
+                    },
+                })
+                .collect(),
+            vout: outputs
+                .iter()
+                .enumerate()
+                .map(|(n, value)| btc::Vout {
+                    n: n as u32,
+                    value: *value,
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Block 1 spending the coinbase output of block 0 (with a 0.1 BTC fee)
+    fn blocks() -> (btc::Block, btc::Block) {
+        let genesis = btc::Block {
+            height: 0,
+            tx: vec![tx("a", &["coinbase"], &[50.0])],
+            ..Default::default()
+        };
+        let block = btc::Block {
+            height: 1,
+            tx: vec![
+                tx("b", &["coinbase"], &[25.0, 25.1]),
+                tx("c", &["a:0"], &[10.0, 39.9]),
+            ],
+            ..Default::default()
+        };
+
+        (genesis, block)
+    }
+
+    fn input_sats(vin: &btc::Vin) -> Option<u64> {
+        match vin.outpoint().as_str() {
+            "a:0" => Some(50 * 100_000_000),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_first_sat() {
+        assert_eq!(first_sat(0), 0);
+        assert_eq!(first_sat(1), 50 * 100_000_000);
+        assert_eq!(first_sat(210_000), block_supply(209_999));
+    }
+
+    #[test]
+    fn test_block_ordinals() {
+        let (_, block) = blocks();
+        let ordinals = block_ordinals(&block, input_sats).unwrap();
+
+        assert_eq!(ordinals.subsidy, 50 * 100_000_000);
+        assert_eq!(ordinals.fees, 10_000_000);
+        assert_eq!(ordinals.miner_reward, 5_010_000_000);
+
+        let coinbase = &ordinals.txs[0];
+        assert_eq!(
+            coinbase
+                .coinbase_ordinals
+                .iter()
+                .map(|block| (block.utxo.as_str(), block.start, block.size))
+                .collect::<Vec<_>>(),
+            vec![
+                ("b:0", 5_000_000_000, 2_500_000_000),
+                ("b:1", 7_500_000_000, 2_500_000_000)
+            ]
+        );
+        assert_eq!(
+            coinbase
+                .relative_ordinals
+                .iter()
+                .map(|block| (block.utxo.as_str(), block.start, block.size))
+                .collect::<Vec<_>>(),
+            vec![("b:1", 0, 10_000_000)]
+        );
+
+        let tx = &ordinals.txs[1];
+        assert_eq!(tx.input_utxos, vec!["a:0".to_string()]);
+        assert_eq!(
+            tx.relative_ordinals
+                .iter()
+                .map(|block| (block.utxo.as_str(), block.start, block.size))
+                .collect::<Vec<_>>(),
+            vec![
+                ("c:0", 0, 1_000_000_000),
+                ("c:1", 1_000_000_000, 3_990_000_000)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_locate_sats() {
+        let (genesis, block) = blocks();
+        // Block 2 spending `c:1`, whose ranges are resolved by walking back to `a:0`
+        let next_block = btc::Block {
+            height: 2,
+            tx: vec![
+                tx("d", &["coinbase"], &[25.0]),
+                tx("e", &["c:1"], &[20.0, 19.9]),
+            ],
+            ..Default::default()
+        };
+        let input_sats = |vin: &btc::Vin| match vin.outpoint().as_str() {
+            "c:1" => Some(3_990_000_000),
+            _ => input_sats(vin),
+        };
+
+        // Like `store_ordinal_blocks` and `store_ordinals`
+        let mut ordinal_blocks: HashMap<String, OrdinalBlocks> = HashMap::new();
+        let mut store: HashMap<String, OrdinalBlocks> = HashMap::new();
+        for block in [genesis, block, next_block] {
+            let ordinals = block_ordinals(&block, input_sats).unwrap();
+            let (spent, created) = block_sat_ranges(&block, &ordinals, input_sats, |utxo| {
+                ordinal_blocks.get(utxo).cloned()
+            })
+            .unwrap();
+            ordinal_blocks.extend(utxo_ordinal_blocks(&block, &ordinals, input_sats).unwrap());
+            store.extend(
+                created
+                    .into_iter()
+                    .map(|(utxo, ranges)| (utxo, ranges.unwrap())),
+            );
+            spent.iter().for_each(|(utxo, _)| {
+                store.remove(utxo);
+            });
+        }
+        let locate = |utxo: &str| {
+            locate_sats(utxo, |utxo| store.get(utxo).cloned())
+                .unwrap()
+                .into_iter()
+                .map(|block| (block.start, block.size))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(locate("c:0"), vec![(0, 1_000_000_000)]);
+        assert_eq!(locate("e:0"), vec![(1_000_000_000, 2_000_000_000)]);
+        assert_eq!(locate("e:1"), vec![(3_000_000_000, 1_990_000_000)]);
+        assert_eq!(locate("b:0"), vec![(5_000_000_000, 2_500_000_000)]);
+        // The fee of `c` (the last sats of `a:0`) is assigned to the last coinbase output
+        assert_eq!(
+            locate("b:1"),
+            vec![(7_500_000_000, 2_500_000_000), (4_990_000_000, 10_000_000)]
+        );
+        // The fee of `e` is lost, as the coinbase output of block 2 does not claim it
+        assert_eq!(locate("d:0"), vec![(10_000_000_000, 2_500_000_000)]);
+        // Spent UTXOs are no longer located
+        assert!(locate_sats("a:0", |utxo| store.get(utxo).cloned()).is_err());
+        assert!(locate_sats("c:1", |utxo| store.get(utxo).cloned()).is_err());
+        assert_eq!(
+            store["e:0"].address, None,
+            "outputs without a script have no address"
+        );
+    }
+
+    #[test]
+    fn test_block_sat_ranges() {
+        // `d` spends `c:1`, created earlier in the block, and `e` spends an unknown UTXO
+        let block = btc::Block {
+            height: 1,
+            tx: vec![
+                tx("b", &["coinbase"], &[25.0, 25.2]),
+                tx("c", &["a:0"], &[10.0, 39.9]),
+                tx("d", &["c:1"], &[20.0, 19.9]),
+                tx("e", &["x:0"], &[0.9]),
+            ],
+            ..Default::default()
+        };
+        let input_sats = |vin: &btc::Vin| match vin.outpoint().as_str() {
+            "c:1" => Some(3_990_000_000),
+            "x:0" => Some(100_000_000),
+            _ => input_sats(vin),
+        };
+        let a_0 = OrdinalBlocks {
+            blocks: vec![OrdinalBlock {
+                utxo: "".into(),
+                address: None,
+                start: 0,
+                size: 5_000_000_000,
+            }],
+            address: None,
+        };
+
+        let ordinals = block_ordinals(&block, input_sats).unwrap();
+        let (spent, created) = block_sat_ranges(&block, &ordinals, input_sats, |utxo| {
+            Some(a_0.clone()).filter(|_| utxo == "a:0")
+        })
+        .unwrap();
+        assert_eq!(
+            spent
+                .iter()
+                .map(|(utxo, _)| utxo.as_str())
+                .collect::<Vec<_>>(),
+            vec!["a:0", "c:1", "x:0"]
+        );
+        assert_eq!(
+            created
+                .iter()
+                .map(|(utxo, _)| utxo.as_str())
+                .collect::<Vec<_>>(),
+            vec!["b:0", "b:1", "c:0", "c:1", "d:0", "d:1", "e:0"]
+        );
+
+        let ranges: HashMap<_, _> = spent.into_iter().chain(created).collect();
+        let ranges = |utxo: &str| {
+            ranges[utxo].clone().map(|ranges| {
+                ranges
+                    .blocks
+                    .into_iter()
+                    .map(|block| (block.start, block.size))
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        assert_eq!(ranges("a:0"), Ok(vec![(0, 5_000_000_000)]));
+        assert_eq!(ranges("c:1"), Ok(vec![(1_000_000_000, 3_990_000_000)]));
+        assert_eq!(ranges("d:0"), Ok(vec![(1_000_000_000, 2_000_000_000)]));
+        assert_eq!(ranges("d:1"), Ok(vec![(3_000_000_000, 1_990_000_000)]));
+        assert_eq!(ranges("x:0"), Err(Error::UnknownUtxo("x:0".into())));
+        assert_eq!(ranges("e:0"), Err(Error::UnknownUtxo("x:0".into())));
+        // The coinbase output collecting the fees of `e` cannot be resolved either
+        assert_eq!(ranges("b:1"), Err(Error::UnknownUtxo("x:0".into())));
+        assert_eq!(ranges("b:0"), Ok(vec![(5_000_000_000, 2_500_000_000)]));
+    }
+
+    #[test]
+    fn test_sat_destination() {
+        let (_, block) = blocks();
+        let ordinals = block_ordinals(&block, input_sats).unwrap();
+        let spends: HashMap<_, _> = utxo_spends(&block, &ordinals, input_sats)
+            .unwrap()
+            .into_iter()
+            .map(|spend| (spend.utxo.clone(), spend))
+            .collect();
+
+        assert_eq!(
+            spends["a:0"]
+                .blocks
+                .iter()
+                .map(|block| (block.utxo.as_str(), block.start, block.size))
+                .collect::<Vec<_>>(),
+            vec![
+                ("c:0", 0, 1_000_000_000),
+                ("c:1", 0, 3_990_000_000),
+                ("b:1", 2_500_000_000, 10_000_000)
+            ]
+        );
+
+        let destination =
+            |offset: u64| sat_destination(&spends["a:0"], offset).map(|to| (to.utxo, to.offset));
+
+        assert_eq!(
+            destination(1_500_000_000),
+            Some(("c:1".into(), 500_000_000))
+        );
+        // Sats spent as fee are followed to the coinbase outputs
+        assert_eq!(
+            destination(4_995_000_000),
+            Some(("b:1".into(), 2_505_000_000))
+        );
+
+        // Fees which are not claimed by the miner are lost
+        let block = btc::Block {
+            height: 1,
+            tx: vec![tx("b", &["coinbase"], &[50.0]), tx("c", &["a:0"], &[49.9])],
+            ..Default::default()
+        };
+        let ordinals = block_ordinals(&block, input_sats).unwrap();
+        let spends: HashMap<_, _> = utxo_spends(&block, &ordinals, input_sats)
+            .unwrap()
+            .into_iter()
+            .map(|spend| (spend.utxo.clone(), spend))
+            .collect();
+        assert_eq!(sat_destination(&spends["a:0"], 4_995_000_000), None);
+    }
+}
@@ -14,6 +14,10 @@ pub struct Deploy {
     pub decimals: i32,
     #[prost(string, tag="6")]
     pub deployer: ::prost::alloc::string::String,
+    /// True for 5-byte tickers deployed with `"self_mint": "true"`, which can only be minted by
+    /// children of the deploy inscription (see `rules`)
+    #[prost(bool, tag="7")]
+    pub self_mint: bool,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -26,11 +30,19 @@ pub struct Mint {
     pub to: ::prost::alloc::string::String,
     #[prost(string, tag="4")]
     pub amount: ::prost::alloc::string::String,
+    /// Ordinal of the event within its block (see `event_ordinal`)
+    #[prost(uint64, tag="5")]
+    pub ordinal: u64,
+    /// Inscription ID of the parent of the mint inscription, if it is valid (i.e.: its inscribed
+    /// sat was spent by the mint's reveal transaction)
+    #[prost(string, tag="6")]
+    pub parent: ::prost::alloc::string::String,
 }
 /// Used to keep track of pending transfers
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct InscribedTransfer {
+    /// Inscription ID, i.e.: "{TXID}i{INDEX}"
     #[prost(string, tag="1")]
     pub id: ::prost::alloc::string::String,
     #[prost(string, tag="2")]
@@ -45,6 +57,9 @@ pub struct InscribedTransfer {
     pub offset: u64,
     #[prost(uint64, tag="7")]
     pub utxo_amount: u64,
+    /// Ordinal of the event within its block (see `event_ordinal`)
+    #[prost(uint64, tag="8")]
+    pub ordinal: u64,
 }
 /// Represents executed transfer
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -60,6 +75,15 @@ pub struct ExecutedTransfer {
     pub to: ::prost::alloc::string::String,
     #[prost(string, tag="5")]
     pub amount: ::prost::alloc::string::String,
+    /// Ordinal of the event within its block (see `event_ordinal`)
+    #[prost(uint64, tag="6")]
+    pub ordinal: u64,
+    /// Transaction in which the transfer inscription was spent
+    #[prost(string, tag="7")]
+    pub txid: ::prost::alloc::string::String,
+    /// Outpoint ("{TXID}:{VOUT}") which received the transfer inscription, if any
+    #[prost(string, tag="8")]
+    pub utxo: ::prost::alloc::string::String,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -72,6 +96,36 @@ pub struct Brc20Events {
     pub inscribed_transfers: ::prost::alloc::vec::Vec<InscribedTransfer>,
     #[prost(message, repeated, tag="4")]
     pub executed_transfers: ::prost::alloc::vec::Vec<ExecutedTransfer>,
+    /// Transfers whose inscribed sat was sent to an unspendable (OP_RETURN) output
+    #[prost(message, repeated, tag="5")]
+    pub burned_transfers: ::prost::alloc::vec::Vec<ExecutedTransfer>,
+    /// Transfers whose inscribed sat was spent as fee, and whose amount is returned to the sender
+    #[prost(message, repeated, tag="6")]
+    pub returned_transfers: ::prost::alloc::vec::Vec<ExecutedTransfer>,
+    /// Transfers inscribed for tokens that have not been deployed
+    #[prost(message, repeated, tag="7")]
+    pub invalid_transfers: ::prost::alloc::vec::Vec<InscribedTransfer>,
+}
+/// Transfer inscription which has not been executed yet
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PendingTransfer {
+    /// Outpoint ("{TXID}:{VOUT}") holding the transfer inscription
+    #[prost(string, tag="1")]
+    pub utxo: ::prost::alloc::string::String,
+    #[prost(message, optional, tag="2")]
+    pub location: ::core::option::Option<InscribedTransferLocation>,
+}
+/// Changes to the set of pending transfers in a block
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PendingTransfers {
+    /// Transfers which became pending, i.e.: inscribed transfers of deployed tokens
+    #[prost(message, repeated, tag="1")]
+    pub added: ::prost::alloc::vec::Vec<PendingTransfer>,
+    /// Transfers which are no longer pending, i.e.: executed, burned or spent as fee
+    #[prost(message, repeated, tag="2")]
+    pub removed: ::prost::alloc::vec::Vec<PendingTransfer>,
 }
 // ================================================================
 // Internal messages (e.g.: used in store modules)
@@ -96,6 +150,13 @@ pub struct InscribedTransferLocation {
     #[prost(uint64, tag="6")]
     pub utxo_amount: u64,
 }
+/// Pending transfers held by an outpoint, i.e.: whose inscribed sats are in the outpoint
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct InscribedTransferLocations {
+    #[prost(message, repeated, tag="1")]
+    pub locations: ::prost::alloc::vec::Vec<InscribedTransferLocation>,
+}
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Token {
@@ -111,5 +172,7 @@ pub struct Token {
     pub decimals: i32,
     #[prost(string, tag="6")]
     pub deployer: ::prost::alloc::string::String,
+    #[prost(bool, tag="7")]
+    pub self_mint: bool,
 }
 // @@protoc_insertion_point(module)
@@ -8,6 +8,13 @@ pub mod btc {
         }
     }
 }
+pub mod ordinals {
+    // @@protoc_insertion_point(attribute:ordinals.v1)
+    pub mod v1 {
+        include!("ordinals.v1.rs");
+        // @@protoc_insertion_point(ordinals.v1)
+    }
+}
 pub mod sf {
     pub mod bitcoin {
         pub mod r#type {
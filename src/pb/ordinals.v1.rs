@@ -17,7 +17,7 @@ pub struct OrdinalBlock {
 pub struct Transaction {
     #[prost(string, tag="1")]
     pub txid: ::prost::alloc::string::String,
-    /// Output number
+    /// Index of the transaction in its block
     #[prost(uint64, tag="2")]
     pub idx: u64,
     /// Amount transferred in sats
@@ -65,6 +65,7 @@ pub struct Block {
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct Inscription {
+    /// Inscription ID, i.e.: "{TXID}i{INDEX}"
     #[prost(string, tag="1")]
     pub id: ::prost::alloc::string::String,
     /// Optional MIME type of the inscription
@@ -74,19 +75,45 @@ pub struct Inscription {
     /// first ordinal of its inputs
     #[prost(int64, optional, tag="3")]
     pub pointer: ::core::option::Option<i64>,
+    /// ID of the parent inscription, if any
     #[prost(string, optional, tag="4")]
     pub parent: ::core::option::Option<::prost::alloc::string::String>,
-    #[prost(string, optional, tag="5")]
-    pub metadata: ::core::option::Option<::prost::alloc::string::String>,
+    /// CBOR encoded metadata
+    #[prost(bytes="vec", optional, tag="5")]
+    pub metadata: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
     #[prost(string, optional, tag="6")]
     pub metaprotocol: ::core::option::Option<::prost::alloc::string::String>,
     #[prost(string, optional, tag="7")]
     pub content_encoding: ::core::option::Option<::prost::alloc::string::String>,
     /// Content of the inscription
-    #[prost(string, tag="8")]
-    pub content: ::prost::alloc::string::String,
-    #[prost(string, tag="9")]
-    pub content_length: ::prost::alloc::string::String,
+    #[prost(bytes="vec", tag="8")]
+    pub content: ::prost::alloc::vec::Vec<u8>,
+    #[prost(uint64, tag="9")]
+    pub content_length: u64,
+    /// Inscription number. Blessed inscriptions are numbered from 0 and cursed
+    /// inscriptions from -1 downwards
+    #[prost(int64, tag="10")]
+    pub number: i64,
+    /// Curses of the inscription, if any. Note: Inscriptions revealed after the
+    /// jubilee are blessed (i.e.: positively numbered) even if they have curses
+    #[prost(enumeration="Curse", repeated, tag="11")]
+    pub curses: ::prost::alloc::vec::Vec<i32>,
+    /// Outpoint ("{TXID}:{VOUT}") holding the inscribed sat when the inscription
+    /// was revealed, empty if the inscribed sat was spent as fee
+    #[prost(string, tag="12")]
+    pub genesis_utxo: ::prost::alloc::string::String,
+    /// Offset of the inscribed sat within the genesis UTXO
+    #[prost(uint64, tag="13")]
+    pub genesis_offset: u64,
+    /// Value of the genesis UTXO (in sats)
+    #[prost(uint64, tag="14")]
+    pub genesis_utxo_amount: u64,
+    /// Address of the genesis UTXO, if any
+    #[prost(string, optional, tag="15")]
+    pub genesis_address: ::core::option::Option<::prost::alloc::string::String>,
+    /// Index of the revealing transaction in its block
+    #[prost(uint64, tag="16")]
+    pub tx_index: u64,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -94,4 +121,176 @@ pub struct Inscriptions {
     #[prost(message, repeated, tag="1")]
     pub inscriptions: ::prost::alloc::vec::Vec<Inscription>,
 }
+/// Location of an inscribed sat
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct InscriptionLocation {
+    /// Outpoint ("{TXID}:{VOUT}") holding the inscribed sat
+    #[prost(string, tag="1")]
+    pub utxo: ::prost::alloc::string::String,
+    /// Offset of the inscribed sat within the UTXO
+    #[prost(uint64, tag="2")]
+    pub offset: u64,
+    /// Address of the UTXO, if any
+    #[prost(string, optional, tag="3")]
+    pub address: ::core::option::Option<::prost::alloc::string::String>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct InscriptionTransfer {
+    /// Inscription ID, i.e.: "{TXID}i{INDEX}"
+    #[prost(string, tag="1")]
+    pub inscription_id: ::prost::alloc::string::String,
+    /// Transaction which spent the inscribed sat
+    #[prost(string, tag="2")]
+    pub txid: ::prost::alloc::string::String,
+    #[prost(message, optional, tag="3")]
+    pub from: ::core::option::Option<InscriptionLocation>,
+    /// Location of the inscribed sat after the transfer, absent if the sat was
+    /// spent as fee and not claimed by the miner
+    #[prost(message, optional, tag="4")]
+    pub to: ::core::option::Option<InscriptionLocation>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct InscriptionTransfers {
+    #[prost(message, repeated, tag="1")]
+    pub transfers: ::prost::alloc::vec::Vec<InscriptionTransfer>,
+}
+/// Link between an inscription and the parent it claims
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct InscriptionParent {
+    /// Inscription ID of the child
+    #[prost(string, tag="1")]
+    pub child: ::prost::alloc::string::String,
+    /// Inscription ID of the parent
+    #[prost(string, tag="2")]
+    pub parent: ::prost::alloc::string::String,
+    /// True if the parent's inscribed sat was spent by the child's reveal
+    /// transaction, which is required for the link to be valid
+    #[prost(bool, tag="3")]
+    pub valid: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct InscriptionParents {
+    #[prost(message, repeated, tag="1")]
+    pub parents: ::prost::alloc::vec::Vec<InscriptionParent>,
+}
+/// Absolute ranges of the sats held by a UTXO, in order (`start` is the
+/// ordinal of the first sat of each range)
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UtxoSatRanges {
+    /// Outpoint ("{TXID}:{VOUT}")
+    #[prost(string, tag="1")]
+    pub utxo: ::prost::alloc::string::String,
+    /// Address of the UTXO, if any
+    #[prost(string, optional, tag="2")]
+    pub address: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(message, repeated, tag="3")]
+    pub ranges: ::prost::alloc::vec::Vec<OrdinalBlock>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SatRanges {
+    /// UTXOs spent by the block, in order
+    #[prost(message, repeated, tag="1")]
+    pub spent: ::prost::alloc::vec::Vec<UtxoSatRanges>,
+    /// UTXOs created by the block, in order
+    #[prost(message, repeated, tag="2")]
+    pub created: ::prost::alloc::vec::Vec<UtxoSatRanges>,
+}
+// ================================================================
+// Internal messages (e.g.: used in store modules)
+// ================================================================
+
+/// Ordinals held by a UTXO, in order. In `store_ordinals`, each block is an absolute range of
+/// sats (`start` is the ordinal of the first sat, `utxo` is empty). Otherwise (see
+/// `utxo_ordinal_blocks`), a block can also be a range of sats of one of the inputs of the
+/// transaction which created the UTXO (`utxo` is the input's outpoint and `start` the offset
+/// within the input).
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct OrdinalBlocks {
+    #[prost(message, repeated, tag="1")]
+    pub blocks: ::prost::alloc::vec::Vec<OrdinalBlock>,
+    /// Address of the UTXO, if any
+    #[prost(string, optional, tag="2")]
+    pub address: ::core::option::Option<::prost::alloc::string::String>,
+}
+/// Destinations of the sats of a spent UTXO, in order. Each block is a range of sats of an
+/// output (`utxo` is the output's outpoint and `start` the offset within the output), or a range
+/// of lost sats (`utxo` is empty).
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UtxoSpend {
+    /// Transaction which spent the UTXO
+    #[prost(string, tag="1")]
+    pub txid: ::prost::alloc::string::String,
+    #[prost(message, repeated, tag="2")]
+    pub blocks: ::prost::alloc::vec::Vec<OrdinalBlock>,
+    /// Outpoint of the spent UTXO, i.e.: "{TXID}:{VOUT}"
+    #[prost(string, tag="3")]
+    pub utxo: ::prost::alloc::string::String,
+    /// Index of the spending transaction in its block
+    #[prost(uint64, tag="4")]
+    pub tx_index: u64,
+}
+/// Spends of the UTXOs spent in a block, in order
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UtxoSpends {
+    #[prost(message, repeated, tag="1")]
+    pub spends: ::prost::alloc::vec::Vec<UtxoSpend>,
+}
+/// Reasons for which an inscription is cursed (see ord's `Curse`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum Curse {
+    Unspecified = 0,
+    UnrecognizedEvenField = 1,
+    DuplicateField = 2,
+    IncompleteField = 3,
+    NotInFirstInput = 4,
+    NotAtOffsetZero = 5,
+    Pointer = 6,
+    Pushnum = 7,
+    Stutter = 8,
+}
+impl Curse {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Curse::Unspecified => "CURSE_UNSPECIFIED",
+            Curse::UnrecognizedEvenField => "CURSE_UNRECOGNIZED_EVEN_FIELD",
+            Curse::DuplicateField => "CURSE_DUPLICATE_FIELD",
+            Curse::IncompleteField => "CURSE_INCOMPLETE_FIELD",
+            Curse::NotInFirstInput => "CURSE_NOT_IN_FIRST_INPUT",
+            Curse::NotAtOffsetZero => "CURSE_NOT_AT_OFFSET_ZERO",
+            Curse::Pointer => "CURSE_POINTER",
+            Curse::Pushnum => "CURSE_PUSHNUM",
+            Curse::Stutter => "CURSE_STUTTER",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "CURSE_UNSPECIFIED" => Some(Self::Unspecified),
+            "CURSE_UNRECOGNIZED_EVEN_FIELD" => Some(Self::UnrecognizedEvenField),
+            "CURSE_DUPLICATE_FIELD" => Some(Self::DuplicateField),
+            "CURSE_INCOMPLETE_FIELD" => Some(Self::IncompleteField),
+            "CURSE_NOT_IN_FIRST_INPUT" => Some(Self::NotInFirstInput),
+            "CURSE_NOT_AT_OFFSET_ZERO" => Some(Self::NotAtOffsetZero),
+            "CURSE_POINTER" => Some(Self::Pointer),
+            "CURSE_PUSHNUM" => Some(Self::Pushnum),
+            "CURSE_STUTTER" => Some(Self::Stutter),
+            _ => None,
+        }
+    }
+}
 // @@protoc_insertion_point(module)
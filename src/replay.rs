@@ -0,0 +1,1198 @@
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+
+use prost::Message;
+use serde::Deserialize;
+use substreams::pb::substreams::store_delta::Operation;
+use substreams::pb::substreams::Clock;
+use substreams::scalar::BigInt;
+use substreams::store::{
+    Delta, DeltaBigInt, DeltaInt64, DeltaProto, Deltas, StoreAdd, StoreDelete, StoreGet, StoreNew,
+    StoreSet, StoreSetIfNotExists,
+};
+use substreams_database_change::pb::database::DatabaseChanges;
+use substreams_entity_change::pb::entity::EntityChanges;
+
+use crate::btc_utils::btc_to_sats;
+use crate::errors::{Error, Result};
+use crate::modules;
+use crate::pb::btc::brc20::v1::{Brc20Events, InscribedTransferLocations, PendingTransfers, Token};
+use crate::pb::ordinals::v1::{
+    self as ord_pb, InscriptionLocation, InscriptionParents, InscriptionTransfers, OrdinalBlock,
+    OrdinalBlocks, SatRanges,
+};
+use crate::pb::sf::bitcoin::r#type::v1 as btc;
+
+// ================================================================
+// In-memory stores
+// ================================================================
+
+/// In-memory implementation of the store traits, following the semantics of substreams stores:
+/// operations are applied as they are made and recorded as deltas, which are committed at the
+/// end of the block (see `MemoryStore::commit`).
+#[derive(Debug, Default)]
+pub struct MemoryStore<T> {
+    /// State at the start of the block
+    committed: RefCell<BTreeMap<String, T>>,
+    /// Current state
+    state: RefCell<BTreeMap<String, T>>,
+    deltas: RefCell<Vec<MemoryDelta<T>>>,
+}
+
+#[derive(Debug, Clone)]
+struct MemoryDelta<T> {
+    operation: Operation,
+    ordinal: u64,
+    key: String,
+    old_value: Option<T>,
+    new_value: Option<T>,
+}
+
+/// Store deltas which can be built from the deltas of a `MemoryStore`
+pub trait FromMemoryDelta<T>: Delta {
+    fn from_memory_delta(
+        operation: Operation,
+        ordinal: u64,
+        key: String,
+        old_value: Option<T>,
+        new_value: Option<T>,
+    ) -> Self;
+}
+
+impl FromMemoryDelta<BigInt> for DeltaBigInt {
+    fn from_memory_delta(
+        operation: Operation,
+        ordinal: u64,
+        key: String,
+        old_value: Option<BigInt>,
+        new_value: Option<BigInt>,
+    ) -> Self {
+        DeltaBigInt {
+            operation,
+            ordinal,
+            key,
+            old_value: old_value.unwrap_or_else(BigInt::zero),
+            new_value: new_value.unwrap_or_else(BigInt::zero),
+        }
+    }
+}
+
+impl FromMemoryDelta<i64> for DeltaInt64 {
+    fn from_memory_delta(
+        operation: Operation,
+        ordinal: u64,
+        key: String,
+        old_value: Option<i64>,
+        new_value: Option<i64>,
+    ) -> Self {
+        DeltaInt64 {
+            operation,
+            ordinal,
+            key,
+            old_value: old_value.unwrap_or_default(),
+            new_value: new_value.unwrap_or_default(),
+        }
+    }
+}
+
+impl<T: Message + Default + PartialEq> FromMemoryDelta<T> for DeltaProto<T> {
+    fn from_memory_delta(
+        operation: Operation,
+        ordinal: u64,
+        key: String,
+        old_value: Option<T>,
+        new_value: Option<T>,
+    ) -> Self {
+        DeltaProto {
+            operation,
+            ordinal,
+            key,
+            old_value: old_value.unwrap_or_default(),
+            new_value: new_value.unwrap_or_default(),
+        }
+    }
+}
+
+impl<T: Clone> MemoryStore<T> {
+    /// Applies an operation. Like substreams stores, panics when the ordinal of the operation
+    /// is lower than the one of the previous operation of the block.
+    fn write(&self, ordinal: u64, key: &str, value: Option<T>) {
+        if let Some(previous) = self.deltas.borrow().last() {
+            assert!(
+                ordinal >= previous.ordinal,
+                "cannot write {} at ordinal {} after ordinal {}",
+                key,
+                ordinal,
+                previous.ordinal
+            );
+        }
+
+        let old_value = match &value {
+            Some(value) => self.state.borrow_mut().insert(key.into(), value.clone()),
+            None => self.state.borrow_mut().remove(key),
+        };
+        let operation = match (&old_value, &value) {
+            (None, Some(_)) => Operation::Create,
+            (Some(_), Some(_)) => Operation::Update,
+            (_, None) => Operation::Delete,
+        };
+
+        self.deltas.borrow_mut().push(MemoryDelta {
+            operation,
+            ordinal,
+            key: key.into(),
+            old_value,
+            new_value: value,
+        });
+    }
+
+    /// Returns the deltas of the current block
+    pub fn deltas<D: FromMemoryDelta<T>>(&self) -> Deltas<D> {
+        Deltas {
+            deltas: self
+                .deltas
+                .borrow()
+                .iter()
+                .cloned()
+                .map(|delta| {
+                    D::from_memory_delta(
+                        delta.operation,
+                        delta.ordinal,
+                        delta.key,
+                        delta.old_value,
+                        delta.new_value,
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// Ends the current block
+    pub fn commit(&self) {
+        *self.committed.borrow_mut() = self.state.borrow().clone();
+        self.deltas.borrow_mut().clear();
+    }
+
+    /// Returns the current state of the store
+    pub fn state(&self) -> BTreeMap<String, T> {
+        self.state.borrow().clone()
+    }
+}
+
+impl<T: Default> StoreNew for MemoryStore<T> {
+    fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<T: Clone> StoreDelete for MemoryStore<T> {
+    fn delete_prefix(&self, ord: i64, prefix: &String) {
+        let keys = self
+            .state
+            .borrow()
+            .keys()
+            .filter(|key| key.starts_with(prefix.as_str()))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        keys.iter()
+            .for_each(|key| self.write(ord as u64, key, None));
+    }
+}
+
+impl<T: Clone + Default> StoreSet<T> for MemoryStore<T> {
+    fn set<K: AsRef<str>>(&self, ord: u64, key: K, value: &T) {
+        self.write(ord, key.as_ref(), Some(value.clone()));
+    }
+
+    fn set_many<K: AsRef<str>>(&self, ord: u64, keys: &Vec<K>, value: &T) {
+        keys.iter().for_each(|key| self.set(ord, key, value));
+    }
+}
+
+impl<T: Clone + Default> StoreSetIfNotExists<T> for MemoryStore<T> {
+    fn set_if_not_exists<K: AsRef<str>>(&self, ord: u64, key: K, value: &T) {
+        if !self.state.borrow().contains_key(key.as_ref()) {
+            self.write(ord, key.as_ref(), Some(value.clone()));
+        }
+    }
+
+    fn set_if_not_exists_many<K: AsRef<str>>(&self, ord: u64, keys: &Vec<K>, value: &T) {
+        keys.iter()
+            .for_each(|key| self.set_if_not_exists(ord, key, value));
+    }
+}
+
+impl StoreAdd<i64> for MemoryStore<i64> {
+    fn add<K: AsRef<str>>(&self, ord: u64, key: K, value: i64) {
+        let current = self.get_last(&key).unwrap_or_default();
+        self.write(ord, key.as_ref(), Some(current + value));
+    }
+
+    fn add_many<K: AsRef<str>>(&self, ord: u64, keys: &Vec<K>, value: i64) {
+        keys.iter().for_each(|key| self.add(ord, key, value));
+    }
+}
+
+impl StoreAdd<BigInt> for MemoryStore<BigInt> {
+    fn add<K: AsRef<str>>(&self, ord: u64, key: K, value: BigInt) {
+        let current = self.get_last(&key).unwrap_or_else(BigInt::zero);
+        self.write(ord, key.as_ref(), Some(current + value));
+    }
+
+    fn add_many<K: AsRef<str>>(&self, ord: u64, keys: &Vec<K>, value: BigInt) {
+        keys.iter()
+            .for_each(|key| self.add(ord, key, value.clone()));
+    }
+}
+
+impl<T: Clone + Default> StoreGet<T> for MemoryStore<T> {
+    fn new(_idx: u32) -> Self {
+        Self::default()
+    }
+
+    /// Returns the value of the key once the operations of the current block with an ordinal
+    /// lower than or equal to `ord` are applied
+    fn get_at<K: AsRef<str>>(&self, ord: u64, key: K) -> Option<T> {
+        let deltas = self.deltas.borrow();
+        match deltas
+            .iter()
+            .rev()
+            .find(|delta| delta.ordinal <= ord && delta.key == key.as_ref())
+        {
+            Some(delta) => delta.new_value.clone(),
+            None => self.get_first(key),
+        }
+    }
+
+    fn get_last<K: AsRef<str>>(&self, key: K) -> Option<T> {
+        self.state.borrow().get(key.as_ref()).cloned()
+    }
+
+    fn get_first<K: AsRef<str>>(&self, key: K) -> Option<T> {
+        self.committed.borrow().get(key.as_ref()).cloned()
+    }
+
+    fn has_at<K: AsRef<str>>(&self, ord: u64, key: K) -> bool {
+        self.get_at(ord, key).is_some()
+    }
+
+    fn has_last<K: AsRef<str>>(&self, key: K) -> bool {
+        self.get_last(key).is_some()
+    }
+
+    fn has_first<K: AsRef<str>>(&self, key: K) -> bool {
+        self.get_first(key).is_some()
+    }
+}
+
+// ================================================================
+// Block fixtures
+// ================================================================
+
+/// Block as returned by bitcoind's `getblock <hash> 2`, which `sf.bitcoin.type.v1.Block` mirrors
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonBlock {
+    hash: String,
+    height: i64,
+    #[serde(default)]
+    time: i64,
+    #[serde(default, rename = "previousblockhash")]
+    previous_hash: String,
+    tx: Vec<JsonTransaction>,
+}
+
+#[derive(Deserialize)]
+struct JsonTransaction {
+    txid: String,
+    #[serde(default)]
+    hash: String,
+    #[serde(default)]
+    hex: String,
+    #[serde(default)]
+    version: u32,
+    #[serde(default)]
+    locktime: u32,
+    vin: Vec<JsonVin>,
+    vout: Vec<JsonVout>,
+}
+
+#[derive(Deserialize)]
+struct JsonVin {
+    #[serde(default)]
+    txid: String,
+    #[serde(default)]
+    vout: u32,
+    #[serde(default)]
+    coinbase: String,
+    #[serde(default)]
+    txinwitness: Vec<String>,
+    #[serde(default)]
+    sequence: u32,
+    /// Spent output, only given by `getblock <hash> 3`
+    prevout: Option<JsonPrevout>,
+}
+
+#[derive(Deserialize)]
+struct JsonPrevout {
+    value: f64,
+}
+
+#[derive(Deserialize)]
+struct JsonVout {
+    value: f64,
+    n: u32,
+    #[serde(rename = "scriptPubKey")]
+    script_pub_key: JsonScriptPubKey,
+}
+
+#[derive(Deserialize)]
+struct JsonScriptPubKey {
+    #[serde(default)]
+    asm: String,
+    hex: String,
+    #[serde(default, rename = "type")]
+    r#type: String,
+    #[serde(default)]
+    address: String,
+}
+
+/// Returns the value of the UTXOs spent by a block fixture in the format of bitcoind's
+/// `getblock <hash> 3`, which gives the output spent by every input
+pub fn prevouts_from_json(json: &str) -> Vec<(String, u64)> {
+    let block: JsonBlock = serde_json::from_str(json).expect("invalid block fixture");
+
+    block
+        .tx
+        .iter()
+        .flat_map(|tx| tx.vin.iter())
+        .filter_map(|vin| {
+            let prevout = vin.prevout.as_ref()?;
+            let sats = btc_to_sats(prevout.value).expect("invalid prevout value");
+            Some((format!("{}:{}", vin.txid, vin.vout), sats))
+        })
+        .collect()
+}
+
+/// Parses a block fixture in the format of bitcoind's `getblock <hash> 2` (or 3)
+pub fn block_from_json(json: &str) -> btc::Block {
+    let block: JsonBlock = serde_json::from_str(json).expect("invalid block fixture");
+
+    btc::Block {
+        hash: block.hash.clone(),
+        height: block.height,
+        time: block.time,
+        previous_hash: block.previous_hash,
+        n_tx: block.tx.len() as u32,
+        tx: block
+            .tx
+            .into_iter()
+            .map(|tx| btc::Transaction {
+                hex: tx.hex,
+                txid: tx.txid,
+                hash: tx.hash,
+                version: tx.version,
+                locktime: tx.locktime,
+                blockhash: block.hash.clone(),
+                blocktime: block.time,
+                vin: tx
+                    .vin
+                    .into_iter()
+                    .map(|vin| btc::Vin {
+                        txid: vin.txid,
+                        vout: vin.vout,
+                        sequence: vin.sequence,
+                        txinwitness: vin.txinwitness,
+                        coinbase: vin.coinbase,
+                        ..Default::default()
+                    })
+                    .collect(),
+                vout: tx
+                    .vout
+                    .into_iter()
+                    .map(|vout| btc::Vout {
+                        value: vout.value,
+                        n: vout.n,
+                        script_pub_key: Some(btc::ScriptPubKey {
+                            asm: vout.script_pub_key.asm,
+                            hex: vout.script_pub_key.hex,
+                            r#type: vout.script_pub_key.r#type,
+                            address: vout.script_pub_key.address,
+                            ..Default::default()
+                        }),
+                    })
+                    .collect(),
+                ..Default::default()
+            })
+            .collect(),
+        ..Default::default()
+    }
+}
+
+/// Decodes a protobuf encoded `sf.bitcoin.type.v1.Block` fixture (e.g.: as fetched from Firehose)
+pub fn block_from_proto(bytes: &[u8]) -> btc::Block {
+    btc::Block::decode(bytes).expect("invalid block fixture")
+}
+
+// ================================================================
+// Module DAG
+// ================================================================
+
+/// Returns the modules of a substreams manifest, in order, along with the names of the modules
+/// they take as inputs. Only the `modules` section is read, as a list of `- name:` entries whose
+/// `- map:` and `- store:` entries are module inputs (sources and params are not).
+fn manifest_modules(manifest: &str) -> Vec<(String, Vec<String>)> {
+    let mut modules: Vec<(String, Vec<String>)> = vec![];
+    let mut in_modules = false;
+
+    for line in manifest.lines() {
+        // Top-level keys start a new section
+        if !line.starts_with([' ', '#']) && !line.trim().is_empty() {
+            in_modules = line.trim_end() == "modules:";
+            continue;
+        }
+        if !in_modules {
+            continue;
+        }
+
+        let entry = line.trim();
+        if let Some(name) = entry.strip_prefix("- name:") {
+            modules.push((name.trim().into(), vec![]));
+        } else if let Some(input) = entry
+            .strip_prefix("- map:")
+            .or_else(|| entry.strip_prefix("- store:"))
+        {
+            if let Some((_, inputs)) = modules.last_mut() {
+                inputs.push(input.trim().into());
+            }
+        }
+    }
+
+    modules
+}
+
+/// Checks that the modules of a substreams manifest can be run, i.e.: that their inputs are
+/// modules of the manifest and that no module depends on its own output, be it directly (e.g.:
+/// a store taking itself as input to read its own state) or through other modules. Returns the
+/// names of the modules in an order in which they can be run.
+pub fn check_manifest(manifest: &str) -> Result<Vec<String>> {
+    let modules = manifest_modules(manifest);
+    for (name, inputs) in &modules {
+        if inputs.contains(name) {
+            return Err(Error::Manifest(format!("{} takes itself as input", name)));
+        }
+        if let Some(input) = inputs
+            .iter()
+            .find(|input| !modules.iter().any(|(name, _)| name == *input))
+        {
+            return Err(Error::Manifest(format!(
+                "{} takes unknown module {} as input",
+                name, input
+            )));
+        }
+    }
+
+    // Modules are ordered once all of their inputs are
+    let mut order: Vec<String> = vec![];
+    let mut pending: Vec<&(String, Vec<String>)> = modules.iter().collect();
+    while !pending.is_empty() {
+        let (ready, blocked): (Vec<_>, Vec<_>) = pending
+            .into_iter()
+            .partition(|(_, inputs)| inputs.iter().all(|input| order.contains(input)));
+        if ready.is_empty() {
+            let names = blocked
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>();
+            return Err(Error::Manifest(format!(
+                "modules {} depend on a cycle",
+                names.join(", ")
+            )));
+        }
+        order.extend(ready.into_iter().map(|(name, _)| name.clone()));
+        pending = blocked;
+    }
+
+    Ok(order)
+}
+
+/// Outputs of the map modules for a block
+#[derive(Debug)]
+pub struct BlockOutputs {
+    pub ordinals: ord_pb::Block,
+    pub sat_ranges: SatRanges,
+    pub inscriptions: ord_pb::Inscriptions,
+    pub inscription_transfers: InscriptionTransfers,
+    pub inscription_parents: InscriptionParents,
+    pub brc20_events: Brc20Events,
+    pub pending_transfers: PendingTransfers,
+    pub resolved_events: Brc20Events,
+    pub filtered_events: Brc20Events,
+    pub entity_changes: EntityChanges,
+    pub database_changes: DatabaseChanges,
+}
+
+/// Runs the module DAG of `substreams.yaml` on blocks, with in-memory stores
+#[derive(Debug, Default)]
+pub struct Replay {
+    /// Params of `map_brc20_events`
+    pub brc20_events_params: String,
+    /// Params of `map_filtered_events`
+    pub filtered_events_params: String,
+    /// Params of `graph_out`
+    pub graph_out_params: String,
+    /// Params of `db_out`
+    pub db_out_params: String,
+
+    pub utxo_values: MemoryStore<i64>,
+    pub ordinal_blocks: MemoryStore<OrdinalBlocks>,
+    pub ordinals: MemoryStore<OrdinalBlocks>,
+    pub inscription_counts: MemoryStore<i64>,
+    pub inscription_genesis: MemoryStore<InscriptionLocation>,
+    pub inscribed_sats: MemoryStore<i64>,
+    pub sat_inscriptions: MemoryStore<String>,
+    pub inscription_locations: MemoryStore<InscriptionLocation>,
+    pub inscription_children: MemoryStore<i64>,
+    pub tokens: MemoryStore<Token>,
+    pub requested_supply: MemoryStore<BigInt>,
+    pub inscribed_transfers: MemoryStore<InscribedTransferLocations>,
+    pub balances: MemoryStore<BigInt>,
+    pub transferable_balances: MemoryStore<BigInt>,
+    pub total_balances: MemoryStore<BigInt>,
+    pub token_stats: MemoryStore<BigInt>,
+    pub token_holders: MemoryStore<i64>,
+    pub token_completion: MemoryStore<i64>,
+
+    /// Number of sats of the UTXOs seeded by `set_utxo_values`
+    pub seeded_sats: Cell<u64>,
+}
+
+/// Ordinal of the first sat of the UTXOs seeded by `Replay::set_utxo_values`, past the last sat
+/// which can ever be mined, so that seeded sats never collide with mined ones
+pub const FIRST_SEEDED_SAT: u64 = 2_100_000_000_000_000;
+
+impl Replay {
+    /// Sets the value of UTXOs created before the first replayed block
+    pub fn with_utxo_values(self, utxos: &[(&str, u64)]) -> Self {
+        self.set_utxo_values(utxos.iter().map(|(utxo, sats)| (*utxo, *sats)));
+        self
+    }
+
+    /// Sets the value of UTXOs created before the first replayed block, between two blocks.
+    /// UTXOs whose sats are unknown are also given a range of sats from `FIRST_SEEDED_SAT`, so
+    /// that inscriptions revealed on them can be followed.
+    pub fn set_utxo_values<'a>(&self, utxos: impl IntoIterator<Item = (&'a str, u64)>) {
+        utxos.into_iter().for_each(|(utxo, sats)| {
+            let key = modules::utxo_key(utxo);
+            self.utxo_values.set(0, &key, &(sats as i64));
+
+            if self.ordinal_blocks.get_last(&key).is_none() {
+                let ranges = OrdinalBlocks {
+                    blocks: vec![OrdinalBlock {
+                        utxo: "".into(),
+                        address: None,
+                        start: FIRST_SEEDED_SAT + self.seeded_sats.get(),
+                        size: sats,
+                    }],
+                    address: None,
+                };
+                self.ordinal_blocks.set(0, &key, &ranges);
+                self.ordinals.set(0, &key, &ranges);
+                self.seeded_sats.set(self.seeded_sats.get() + sats);
+            }
+        });
+        self.utxo_values.commit();
+        self.ordinal_blocks.commit();
+        self.ordinals.commit();
+    }
+
+    /// Runs every module on the block, in the order of the module DAG
+    pub fn process(&self, block: btc::Block) -> BlockOutputs {
+        let clock = Clock {
+            id: block.hash.clone(),
+            number: block.height as u64,
+            timestamp: Some(prost_types::Timestamp {
+                seconds: block.time,
+                nanos: 0,
+            }),
+        };
+
+        modules::store_utxo_values(block.clone(), &self.utxo_values);
+
+        let ordinals = modules::map_ordinals(block.clone(), &self.utxo_values).unwrap();
+        modules::store_ordinal_blocks(
+            block.clone(),
+            ordinals.clone(),
+            &self.utxo_values,
+            &self.ordinal_blocks,
+        );
+        let sat_ranges = modules::map_sat_ranges(
+            block.clone(),
+            ordinals.clone(),
+            &self.utxo_values,
+            &self.ordinal_blocks,
+        )
+        .unwrap();
+        modules::store_ordinals(sat_ranges.clone(), &self.ordinals);
+
+        modules::store_inscription_counts(block.clone(), &self.inscription_counts);
+        let inscriptions = modules::map_inscriptions(
+            block.clone(),
+            self.inscription_counts.deltas(),
+            &self.utxo_values,
+        )
+        .unwrap();
+        modules::store_inscription_genesis(inscriptions.clone(), &self.inscription_genesis);
+        let sat_spends =
+            modules::map_sat_spends(block.clone(), ordinals.clone(), &self.utxo_values).unwrap();
+        modules::store_inscribed_sats(
+            inscriptions.clone(),
+            sat_ranges.clone(),
+            &self.inscribed_sats,
+        );
+        modules::store_sat_inscriptions(
+            inscriptions.clone(),
+            sat_ranges.clone(),
+            &self.inscribed_sats,
+            &self.sat_inscriptions,
+        );
+        let inscription_transfers = modules::map_inscription_transfers(
+            sat_ranges.clone(),
+            sat_spends.clone(),
+            &self.inscribed_sats,
+            &self.sat_inscriptions,
+        )
+        .unwrap();
+        modules::store_inscription_locations(
+            inscriptions.clone(),
+            inscription_transfers.clone(),
+            &self.inscription_locations,
+        );
+        let inscription_parents =
+            modules::map_inscription_parents(inscriptions.clone(), inscription_transfers.clone())
+                .unwrap();
+        modules::store_inscription_children(
+            inscription_parents.clone(),
+            &self.inscription_children,
+        );
+
+        let brc20_events = modules::map_brc20_events(
+            self.brc20_events_params.clone(),
+            clock.clone(),
+            inscriptions.clone(),
+            inscription_parents.clone(),
+        )
+        .unwrap();
+        modules::store_tokens(brc20_events.clone(), &self.tokens);
+        modules::store_requested_supply(brc20_events.clone(), &self.tokens, &self.requested_supply);
+        modules::store_inscribed_transfers(
+            block.clone(),
+            brc20_events.clone(),
+            &self.tokens,
+            &self.requested_supply,
+            &self.inscribed_transfers,
+        );
+        let pending_transfers =
+            modules::map_pending_transfers(self.inscribed_transfers.deltas()).unwrap();
+        let resolved_events = modules::map_resolve_transfers(
+            block,
+            brc20_events.clone(),
+            &self.inscribed_transfers,
+            &self.tokens,
+            &self.requested_supply,
+            sat_spends,
+        )
+        .unwrap();
+
+        modules::store_balances(resolved_events.clone(), &self.balances);
+        modules::store_transferable_balances(resolved_events.clone(), &self.transferable_balances);
+        modules::store_total_balances(resolved_events.clone(), &self.total_balances);
+        modules::store_token_stats(resolved_events.clone(), &self.token_stats);
+        modules::store_token_holders(self.total_balances.deltas(), &self.token_holders);
+        modules::store_token_completion(
+            clock.clone(),
+            self.token_stats.deltas(),
+            &self.tokens,
+            &self.token_completion,
+        );
+
+        let filtered_events = modules::map_filtered_events(
+            self.filtered_events_params.clone(),
+            resolved_events.clone(),
+        )
+        .unwrap();
+        let entity_changes = modules::graph_out(
+            self.graph_out_params.clone(),
+            clock.clone(),
+            resolved_events.clone(),
+            self.balances.deltas(),
+            self.transferable_balances.deltas(),
+            self.token_stats.deltas(),
+            self.token_holders.deltas(),
+            self.token_completion.deltas(),
+        )
+        .unwrap();
+        let database_changes = modules::db_out(
+            self.db_out_params.clone(),
+            clock.clone(),
+            resolved_events.clone(),
+            self.balances.deltas(),
+            self.transferable_balances.deltas(),
+            self.token_stats.deltas(),
+            self.token_holders.deltas(),
+            self.token_completion.deltas(),
+        )
+        .unwrap();
+
+        self.commit();
+
+        BlockOutputs {
+            ordinals,
+            sat_ranges,
+            inscriptions,
+            inscription_transfers,
+            inscription_parents,
+            brc20_events,
+            pending_transfers,
+            resolved_events,
+            filtered_events,
+            entity_changes,
+            database_changes,
+        }
+    }
+
+    /// Commits the changes made to every store (e.g.: after restoring a snapshot)
+    pub fn commit(&self) {
+        self.utxo_values.commit();
+        self.ordinal_blocks.commit();
+        self.ordinals.commit();
+        self.inscription_counts.commit();
+        self.inscription_genesis.commit();
+        self.inscribed_sats.commit();
+        self.sat_inscriptions.commit();
+        self.inscription_locations.commit();
+        self.inscription_children.commit();
+        self.tokens.commit();
+        self.requested_supply.commit();
+        self.inscribed_transfers.commit();
+        self.balances.commit();
+        self.transferable_balances.commit();
+        self.total_balances.commit();
+        self.token_stats.commit();
+        self.token_holders.commit();
+        self.token_completion.commit();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::btc_utils::address_from_scriptpubkey;
+    use crate::pb::btc::brc20::v1::{ExecutedTransfer, InscribedTransfer};
+    use crate::rules::SELF_MINT_HEIGHT;
+    use crate::tx_builder::{self, account, commit_reveal, external_outpoint, text, TxBuilder};
+
+    /// Deploy and mint of `ordi`, followed by the inscription and the execution of a transfer
+    const FIXTURES: [&str; 3] = [
+        include_str!("../fixtures/blocks/779830.json"),
+        include_str!("../fixtures/blocks/779831.json"),
+        include_str!("../fixtures/blocks/779832.json"),
+    ];
+
+    fn txid(byte: &str) -> String {
+        byte.repeat(32)
+    }
+
+    fn replay() -> Replay {
+        // UTXOs spent by the fixtures which were created before the first block
+        Replay::default().with_utxo_values(&[
+            (&format!("{}:0", txid("a0")), 10_000),
+            (&format!("{}:0", txid("b0")), 10_000),
+            (&format!("{}:0", txid("c0")), 10_000),
+            (&format!("{}:0", txid("d0")), 10_000),
+        ])
+    }
+
+    #[test]
+    fn test_check_manifest() {
+        let order = check_manifest(include_str!("../substreams.yaml")).unwrap();
+        assert_eq!(order.len(), 30);
+        assert_eq!(order[0], "store_utxo_values");
+
+        let manifest = |modules: &str| {
+            format!(
+                "specVersion: v0.1.0\nmodules:\n{}\nparams:\n  map_a: \"\"\n",
+                modules
+            )
+        };
+        let module = |name: &str, inputs: &[&str]| {
+            let inputs = inputs
+                .iter()
+                .map(|input| format!("      - {}\n", input))
+                .collect::<String>();
+            format!(
+                "  - name: {}\n    kind: map\n    inputs:\n      - source: sf.bitcoin.type.v1.Block\n{}",
+                name, inputs
+            )
+        };
+
+        assert_eq!(
+            check_manifest(&manifest(&module("store_a", &["store: store_a"]))),
+            Err(Error::Manifest("store_a takes itself as input".into()))
+        );
+        assert_eq!(
+            check_manifest(&manifest(&module("map_a", &["store: store_b"]))),
+            Err(Error::Manifest(
+                "map_a takes unknown module store_b as input".into()
+            ))
+        );
+        let modules = [
+            module("map_a", &["store: store_b"]),
+            module("store_b", &["map: map_a"]),
+            module("map_c", &["map: map_a"]),
+            module("map_d", &[]),
+        ];
+        assert_eq!(
+            check_manifest(&manifest(&modules.concat())),
+            Err(Error::Manifest(
+                "modules map_a, store_b, map_c depend on a cycle".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_memory_store() {
+        let store = MemoryStore::<i64>::default();
+        store.set(0, "a", &1);
+        store.commit();
+
+        store.add(0, "a", 2);
+        store.set(0, "b", &5);
+        store.delete_prefix(1, &"b".to_string());
+
+        assert_eq!(store.get_first("a"), Some(1));
+        assert_eq!(store.get_last("a"), Some(3));
+        assert_eq!(store.get_at(0, "b"), Some(5));
+        assert_eq!(store.get_last("b"), None);
+
+        let deltas: Deltas<DeltaInt64> = store.deltas();
+        assert_eq!(
+            deltas
+                .deltas
+                .iter()
+                .map(|delta| (
+                    delta.operation,
+                    delta.key.as_str(),
+                    delta.old_value,
+                    delta.new_value
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                (Operation::Update, "a", 1, 3),
+                (Operation::Create, "b", 0, 5),
+                (Operation::Delete, "b", 5, 0),
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot write b at ordinal 1 after ordinal 2")]
+    fn test_memory_store_ordinals() {
+        let store = MemoryStore::<i64>::default();
+        store.set(2, "a", &1);
+        store.set(1, "b", &1);
+    }
+
+    #[test]
+    fn test_balance_store_ordinals() {
+        // A transfer inscribed in the same transaction as an execution comes after it in the
+        // events, but its ordinal is higher (see `modules::event_ordinal`)
+        let events = Brc20Events {
+            inscribed_transfers: vec![InscribedTransfer {
+                token: "ordi".into(),
+                from: "alice".into(),
+                amount: "10".into(),
+                ordinal: (1 << 32) | (1 << 31),
+                ..Default::default()
+            }],
+            executed_transfers: vec![ExecutedTransfer {
+                token: "ordi".into(),
+                from: "alice".into(),
+                to: "bob".into(),
+                amount: "4".into(),
+                ordinal: 1 << 32,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let store = MemoryStore::<BigInt>::default();
+        modules::store_transferable_balances(events, &store);
+
+        let deltas: Deltas<DeltaBigInt> = store.deltas();
+        assert_eq!(
+            deltas
+                .deltas
+                .iter()
+                .map(|delta| (delta.ordinal, delta.new_value.to_string()))
+                .collect::<Vec<_>>(),
+            vec![(1 << 32, "-4".into()), ((1 << 32) | (1 << 31), "6".into())]
+        );
+    }
+
+    #[test]
+    fn test_transfers_above_minted_supply() {
+        let tokens = MemoryStore::<Token>::default();
+        tokens.set(
+            0,
+            "ordi",
+            &Token {
+                max_supply: "21000".into(),
+                ..Default::default()
+            },
+        );
+        let requested_supply = MemoryStore::<BigInt>::default();
+        requested_supply.add(1 << 32, "ordi", BigInt::from(500));
+
+        let transfer = |id: &str, token: &str, amount: &str, tx_index: u64| InscribedTransfer {
+            id: id.into(),
+            token: token.into(),
+            from: "alice".into(),
+            amount: amount.into(),
+            utxo: format!("{}:0", txid(id)),
+            ordinal: tx_index << 32,
+            ..Default::default()
+        };
+        // Transfers are checked against the supply minted before them, including in the block
+        let events = Brc20Events {
+            inscribed_transfers: vec![
+                transfer("a1", "ordi", "1", 0),
+                transfer("b1", "ordi", "500", 2),
+                transfer("c1", "ordi", "501", 2),
+                transfer("d1", "pepe", "1", 2),
+            ],
+            ..Default::default()
+        };
+
+        let inscribed_transfers = MemoryStore::<InscribedTransferLocations>::default();
+        modules::store_inscribed_transfers(
+            btc::Block::default(),
+            events.clone(),
+            &tokens,
+            &requested_supply,
+            &inscribed_transfers,
+        );
+        let resolved_events = modules::map_resolve_transfers(
+            btc::Block::default(),
+            events,
+            &inscribed_transfers,
+            &tokens,
+            &requested_supply,
+            ord_pb::UtxoSpends::default(),
+        )
+        .unwrap();
+
+        let ids = |transfers: &[InscribedTransfer]| {
+            transfers
+                .iter()
+                .map(|transfer| transfer.id.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(&resolved_events.inscribed_transfers), vec!["b1"]);
+        assert_eq!(
+            ids(&resolved_events.invalid_transfers),
+            vec!["a1", "c1", "d1"]
+        );
+        assert!(inscribed_transfers
+            .get_last(modules::utxo_key(&format!("{}:0", txid("b1"))))
+            .is_some());
+        assert!(inscribed_transfers
+            .get_last(modules::utxo_key(&format!("{}:0", txid("c1"))))
+            .is_none());
+    }
+
+    #[test]
+    fn test_replay_fixtures() {
+        let replay = replay();
+        let outputs = FIXTURES
+            .iter()
+            .map(|fixture| replay.process(block_from_json(fixture)))
+            .collect::<Vec<_>>();
+        let sender = address_from_scriptpubkey(&format!("0014{}", "aa".repeat(20))).unwrap();
+        let receiver = address_from_scriptpubkey(&format!("0014{}", "bb".repeat(20))).unwrap();
+
+        // Block 779830: deploy and mint
+        let events = &outputs[0].resolved_events;
+        assert_eq!(outputs[0].ordinals.txs.len(), 3);
+        assert_eq!(outputs[0].ordinals.fees, 18_908);
+        assert_eq!(outputs[0].inscriptions.inscriptions.len(), 2);
+        assert!(outputs[0].inscription_parents.parents.is_empty());
+        assert_eq!(outputs[0].brc20_events.mints.len(), 1);
+        assert_eq!(events.deploys.len(), 1);
+        assert_eq!(events.deploys[0].deployer, sender);
+        assert_eq!(events.mints.len(), 1);
+        assert_eq!(events.mints[0].amount, "1000");
+
+        // Block 779831: transfer inscription
+        let events = &outputs[1].resolved_events;
+        assert_eq!(outputs[1].inscriptions.inscriptions[0].number, 2);
+        assert_eq!(events.inscribed_transfers.len(), 1);
+        assert_eq!(outputs[1].pending_transfers.added.len(), 1);
+
+        // Block 779832: transfer execution
+        let events = &outputs[2].resolved_events;
+        assert_eq!(events.executed_transfers.len(), 1);
+        assert_eq!(events.executed_transfers[0].from, sender);
+        assert_eq!(events.executed_transfers[0].to, receiver);
+        assert_eq!(events.executed_transfers[0].amount, "400");
+        assert_eq!(outputs[2].pending_transfers.removed.len(), 1);
+        assert_eq!(outputs[2].inscription_transfers.transfers.len(), 1);
+        assert_eq!(
+            outputs[2].inscription_transfers.transfers[0].to,
+            Some(InscriptionLocation {
+                utxo: format!("{}:0", txid("f1")),
+                offset: 0,
+                address: Some(receiver.clone()),
+            })
+        );
+        assert!(outputs[2]
+            .database_changes
+            .table_changes
+            .iter()
+            .any(|change| change.table == "transfers"));
+        assert!(outputs[2]
+            .entity_changes
+            .entity_changes
+            .iter()
+            .any(|change| change.entity == "Transfer"));
+
+        // Final state
+        let balance = |store: &MemoryStore<BigInt>, account: &str| {
+            store
+                .get_last(format!("ordi:{}", account))
+                .map(|balance| balance.to_string())
+        };
+        assert_eq!(balance(&replay.balances, &sender), Some("600".into()));
+        assert_eq!(balance(&replay.balances, &receiver), Some("400".into()));
+        assert_eq!(
+            balance(&replay.transferable_balances, &sender),
+            Some("0".into())
+        );
+        assert_eq!(replay.token_holders.get_last("ordi"), Some(2));
+        assert_eq!(
+            replay
+                .token_stats
+                .get_last("transfer_count:ordi")
+                .map(|count| count.to_string()),
+            Some("1".into())
+        );
+        assert!(replay.inscribed_transfers.state().is_empty());
+        assert_eq!(replay.inscription_genesis.state().len(), 3);
+        // Every inscription is followed to its current location
+        assert_eq!(
+            replay
+                .inscription_locations
+                .get_last(format!("{}i0", txid("e1")))
+                .map(|location| location.utxo),
+            Some(format!("{}:0", txid("f1")))
+        );
+        // Inscriptions are bound to their sat, which the output of the execution now holds
+        let sat_inscriptions = replay.sat_inscriptions.state();
+        assert_eq!(sat_inscriptions.len(), 3);
+        let (key, _) = sat_inscriptions
+            .iter()
+            .find(|(_, id)| **id == format!("{}i0", txid("e1")))
+            .unwrap();
+        let sat: u64 = key.split(':').next().unwrap().parse().unwrap();
+        let ranges = replay
+            .ordinals
+            .get_last(modules::utxo_key(&format!("{}:0", txid("f1"))))
+            .unwrap();
+        assert!(ranges
+            .blocks
+            .iter()
+            .any(|range| (range.start..range.start + range.size).contains(&sat)));
+        assert!(replay
+            .ordinals
+            .get_last(modules::utxo_key(&format!("{}:0", txid("e1"))))
+            .is_none());
+        // Only the values of unspent outputs are kept
+        let utxo_values = replay.utxo_values.state();
+        assert!(!utxo_values.contains_key(&modules::utxo_key(&format!("{}:0", txid("a0")))));
+        assert!(!utxo_values.contains_key(&modules::utxo_key(&format!("{}:0", txid("e1")))));
+        assert!(utxo_values.contains_key(&modules::utxo_key(&format!("{}:0", txid("f1")))));
+    }
+
+    #[test]
+    fn test_rule_set_param() {
+        // A self-mint deploy is only valid with the rule set of its block
+        let (commit, reveal) = commit_reveal(
+            external_outpoint(1),
+            &[text(
+                r#"{"p":"brc-20","op":"deploy","tick":"abcde","max":"1000","lim":"100","self_mint":"true"}"#,
+            )],
+            account(1),
+            1_000,
+        );
+        let coinbase = TxBuilder::coinbase(SELF_MINT_HEIGHT as i64)
+            .output(account(0), 625_000_000)
+            .build();
+        let block = tx_builder::block(SELF_MINT_HEIGHT as i64, &[coinbase, commit, reveal]);
+        let deploys = |params: &str| {
+            let replay = Replay {
+                brc20_events_params: params.into(),
+                ..Default::default()
+            }
+            .with_utxo_values(&[(&external_outpoint(1).to_string(), 10_000)]);
+            replay.process(block.clone()).resolved_events.deploys.len()
+        };
+
+        assert_eq!(deploys(""), 1);
+        assert_eq!(deploys("rules=self_mint"), 1);
+        assert_eq!(deploys("rules=genesis"), 0);
+    }
+
+    #[test]
+    fn test_tick_filter() {
+        let run = |params: &str| {
+            let replay = Replay {
+                filtered_events_params: params.into(),
+                graph_out_params: params.into(),
+                db_out_params: params.into(),
+                ..replay()
+            };
+            let outputs = FIXTURES
+                .iter()
+                .map(|fixture| replay.process(block_from_json(fixture)))
+                .collect::<Vec<_>>();
+            (replay, outputs)
+        };
+
+        // Tickers are case-insensitive
+        let (_, outputs) = run("tick=sats, ORDI");
+        assert_eq!(outputs[0].filtered_events, outputs[0].resolved_events);
+        assert_eq!(outputs[2].filtered_events, outputs[2].resolved_events);
+
+        // Outputs are filtered, while stores still track every token
+        let (replay, outputs) = run("tick=sats");
+        for outputs in &outputs {
+            assert_eq!(outputs.filtered_events, Brc20Events::default());
+            assert!(outputs.entity_changes.entity_changes.is_empty());
+            assert!(outputs.database_changes.table_changes.is_empty());
+        }
+        assert_eq!(outputs[2].resolved_events.executed_transfers.len(), 1);
+        assert_eq!(replay.token_holders.get_last("ordi"), Some(2));
+
+        assert!(modules::TickFilter::from_params("tick=").is_err());
+        assert!(modules::TickFilter::from_params("tick").is_err());
+        assert!(modules::TickFilter::from_params("").unwrap().allows("ordi"));
+    }
+
+    #[test]
+    fn test_block_from_proto() {
+        let block = block_from_json(FIXTURES[0]);
+
+        assert_eq!(block_from_proto(&block.encode_to_vec()), block);
+    }
+}
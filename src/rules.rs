@@ -0,0 +1,403 @@
+//! Rules which BRC-20 operations must follow to be indexed, grouped in rule sets activated at the
+//! height of the protocol's upgrades. Rules which only depend on the operation (e.g.: its ticker
+//! or amount) are named predicates checked by `map_brc20_events` with the rule set of the block
+//! (see `RuleSet::at`), while rules which depend on the indexer's state are functions checked by
+//! the modules which have access to it.
+//!
+//! A protocol upgrade is a new entry of `RULE_SETS`, made of the rules of the previous rule set
+//! with the upgraded rules replaced.
+
+use crate::brc20::Brc20Event;
+use crate::pb::btc::brc20::v1::{Mint, Token};
+use substreams::scalar::BigInt;
+
+/// Height from which 5-byte tickers can be deployed as self-mint tokens
+pub const SELF_MINT_HEIGHT: u64 = 837_090;
+
+/// BRC-20 operation, along with the inscription which inscribed it
+#[derive(Debug)]
+pub struct Candidate<'a> {
+    pub event: &'a Brc20Event,
+    /// True if the inscription has any curse, i.e.: it is cursed before the jubilee and
+    /// vindicated after it
+    pub has_curses: bool,
+}
+
+/// Named predicate which a BRC-20 operation must satisfy to be indexed
+pub struct Rule {
+    pub name: &'static str,
+    /// Reason why an operation which does not satisfy the rule is not indexed
+    pub reason: &'static str,
+    pub holds: fn(&Candidate) -> bool,
+}
+
+impl std::fmt::Debug for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.name)
+    }
+}
+
+macro_rules! rule {
+    ($name:ident, $reason:expr) => {
+        Rule {
+            name: stringify!($name),
+            reason: $reason,
+            holds: $name,
+        }
+    };
+}
+
+// ================================================================
+// Rules
+// ================================================================
+
+pub fn p_is_brc20(candidate: &Candidate) -> bool {
+    candidate.event.p() == "brc-20"
+}
+
+pub fn tick_is_4_bytes(candidate: &Candidate) -> bool {
+    candidate.event.tick().len() == 4
+}
+
+/// Tickers are 4 bytes wide, or 5 bytes wide for self-mint tokens (which only deploys declare)
+pub fn tick_is_4_bytes_or_self_mint_5_bytes(candidate: &Candidate) -> bool {
+    match (candidate.event.tick().len(), candidate.event) {
+        (4, _) => true,
+        (5, Brc20Event::Deploy(deploy)) => deploy.self_mint(),
+        (5, _) => true,
+        _ => false,
+    }
+}
+
+pub fn max_and_lim_are_not_zero(candidate: &Candidate) -> bool {
+    match candidate.event {
+        Brc20Event::Deploy(deploy) => !deploy.max.is_zero() && !deploy.lim().is_zero(),
+        _ => true,
+    }
+}
+
+pub fn dec_is_at_most_18(candidate: &Candidate) -> bool {
+    match candidate.event {
+        Brc20Event::Deploy(deploy) => (0..=18).contains(&deploy.dec()),
+        _ => true,
+    }
+}
+
+pub fn amt_is_not_zero(candidate: &Candidate) -> bool {
+    match candidate.event {
+        Brc20Event::Mint(mint) => !mint.amt.is_zero(),
+        Brc20Event::Transfer(transfer) => !transfer.amt.is_zero(),
+        Brc20Event::Deploy(_) => true,
+    }
+}
+
+/// Cursed inscriptions are ignored, as are the inscriptions which would have been cursed before
+/// the jubilee (the curses are the same, but they are numbered as blessed inscriptions from the
+/// jubilee, i.e.: vindicated), so the jubilee does not change the rules
+pub fn inscription_has_no_curses(candidate: &Candidate) -> bool {
+    !candidate.has_curses
+}
+
+/// Mints of self-mint tokens are only valid if the mint inscription is a child of the deploy
+/// inscription
+pub fn mint_is_authorized(token: &Token, mint: &Mint) -> bool {
+    !token.self_mint || mint.parent == token.id
+}
+
+/// Returns the amount actually minted by a mint, given the supply of its token minted before it:
+/// mints above the mint limit are invalid, and the mint reaching the max supply is capped to the
+/// remaining supply, after which mints are invalid
+pub fn minted_amount(
+    amount: &BigInt,
+    mint_limit: &BigInt,
+    max_supply: &BigInt,
+    minted: &BigInt,
+) -> Option<BigInt> {
+    let remaining = max_supply.clone() - minted;
+    if amount > mint_limit || remaining <= BigInt::zero() {
+        None
+    } else if *amount > remaining {
+        Some(remaining)
+    } else {
+        Some(amount.clone())
+    }
+}
+
+/// No balance of a token can exceed its minted supply, i.e.: the lower of its requested supply
+/// and its max supply, so transfers inscribed above it are invalid. The available balance of the
+/// sender itself cannot be checked, as balances are stored downstream of the modules resolving
+/// transfers.
+pub fn transfer_fits_supply(amount: &BigInt, max_supply: &BigInt, requested: &BigInt) -> bool {
+    amount <= max_supply && amount <= requested
+}
+
+// ================================================================
+// Rule sets
+// ================================================================
+
+/// Rules of the operations inscribed from a given height
+#[derive(Debug)]
+pub struct RuleSet {
+    pub name: &'static str,
+    pub activation_height: u64,
+    pub rules: &'static [Rule],
+}
+
+/// Rule sets, by activation height
+pub const RULE_SETS: [RuleSet; 2] = [
+    RuleSet {
+        name: "genesis",
+        activation_height: 0,
+        rules: &[
+            rule!(p_is_brc20, "p must be brc-20"),
+            rule!(tick_is_4_bytes, "tick must be 4 bytes wide"),
+            rule!(max_and_lim_are_not_zero, "max and lim must not be zero"),
+            rule!(dec_is_at_most_18, "dec must be between 0 and 18"),
+            rule!(amt_is_not_zero, "amt must not be zero"),
+            rule!(
+                inscription_has_no_curses,
+                "inscription is cursed or vindicated"
+            ),
+        ],
+    },
+    RuleSet {
+        name: "self_mint",
+        activation_height: SELF_MINT_HEIGHT,
+        rules: &[
+            rule!(p_is_brc20, "p must be brc-20"),
+            rule!(
+                tick_is_4_bytes_or_self_mint_5_bytes,
+                "tick must be 4 bytes wide, or 5 bytes wide for self-mint tokens"
+            ),
+            rule!(max_and_lim_are_not_zero, "max and lim must not be zero"),
+            rule!(dec_is_at_most_18, "dec must be between 0 and 18"),
+            rule!(amt_is_not_zero, "amt must not be zero"),
+            rule!(
+                inscription_has_no_curses,
+                "inscription is cursed or vindicated"
+            ),
+        ],
+    },
+];
+
+impl RuleSet {
+    /// Returns the rule set of the operations inscribed at the given height
+    pub fn at(height: u64) -> &'static RuleSet {
+        RULE_SETS
+            .iter()
+            .rev()
+            .find(|rule_set| rule_set.activation_height <= height)
+            .unwrap_or(&RULE_SETS[0])
+    }
+
+    /// Returns the most recent rule set
+    pub fn latest() -> &'static RuleSet {
+        &RULE_SETS[RULE_SETS.len() - 1]
+    }
+
+    pub fn named(name: &str) -> Option<&'static RuleSet> {
+        RULE_SETS.iter().find(|rule_set| rule_set.name == name)
+    }
+
+    /// Returns the first rule (in order) which the operation does not satisfy, if any
+    pub fn violation(&self, candidate: &Candidate) -> Option<&'static Rule> {
+        self.rules.iter().find(|rule| !(rule.holds)(candidate))
+    }
+
+    pub fn is_valid(&self, candidate: &Candidate) -> bool {
+        self.violation(candidate).is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inscriptions::JUBILEE_HEIGHT;
+
+    fn event(json: &str) -> Brc20Event {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn violation(rule_set: &RuleSet, json: &str, has_curses: bool) -> Option<&'static str> {
+        violated_rule(rule_set, json, has_curses).map(|rule| rule.name)
+    }
+
+    fn violated_rule(rule_set: &RuleSet, json: &str, has_curses: bool) -> Option<&'static Rule> {
+        rule_set.violation(&Candidate {
+            event: &event(json),
+            has_curses,
+        })
+    }
+
+    #[test]
+    fn test_rule_set_at() {
+        assert_eq!(RuleSet::at(0).name, "genesis");
+        assert_eq!(RuleSet::at(JUBILEE_HEIGHT).name, "genesis");
+        assert_eq!(RuleSet::at(SELF_MINT_HEIGHT - 1).name, "genesis");
+        assert_eq!(RuleSet::at(SELF_MINT_HEIGHT).name, "self_mint");
+        assert_eq!(RuleSet::latest().name, "self_mint");
+        assert_eq!(
+            RuleSet::named("self_mint").unwrap().activation_height,
+            SELF_MINT_HEIGHT
+        );
+        assert!(RuleSet::named("unknown").is_none());
+
+        // Rule sets are sorted by activation height, have unique rule names, and each one
+        // changes the predicates of the previous one
+        for rule_sets in RULE_SETS.windows(2) {
+            assert!(rule_sets[0].activation_height < rule_sets[1].activation_height);
+            let predicates = |rule_set: &RuleSet| {
+                rule_set
+                    .rules
+                    .iter()
+                    .map(|rule| rule.holds as usize)
+                    .collect::<Vec<_>>()
+            };
+            assert_ne!(predicates(&rule_sets[0]), predicates(&rule_sets[1]));
+        }
+        for rule_set in &RULE_SETS {
+            let mut names = rule_set
+                .rules
+                .iter()
+                .map(|rule| rule.name)
+                .collect::<Vec<_>>();
+            names.sort();
+            names.dedup();
+            assert_eq!(names.len(), rule_set.rules.len());
+        }
+    }
+
+    #[test]
+    fn test_stateless_rules() {
+        let genesis = RuleSet::at(0);
+
+        for (json, expected) in [
+            (
+                r#"{"p":"brc-20","op":"mint","tick":"ordi","amt":"1"}"#,
+                None,
+            ),
+            (
+                r#"{"p":"brc-21","op":"mint","tick":"ordi","amt":"1"}"#,
+                Some("p_is_brc20"),
+            ),
+            (
+                r#"{"p":"brc-20","op":"mint","tick":"ord","amt":"1"}"#,
+                Some("tick_is_4_bytes"),
+            ),
+            (
+                r#"{"p":"brc-20","op":"deploy","tick":"ordi","max":"0","lim":"0"}"#,
+                Some("max_and_lim_are_not_zero"),
+            ),
+            (
+                r#"{"p":"brc-20","op":"deploy","tick":"ordi","max":"1","lim":"1","dec":19}"#,
+                Some("dec_is_at_most_18"),
+            ),
+            (
+                r#"{"p":"brc-20","op":"transfer","tick":"ordi","amt":"0"}"#,
+                Some("amt_is_not_zero"),
+            ),
+        ] {
+            assert_eq!(violation(genesis, json, false), expected, "{}", json);
+        }
+    }
+
+    #[test]
+    fn test_cursed_inscriptions() {
+        let mint = r#"{"p":"brc-20","op":"mint","tick":"ordi","amt":"1"}"#;
+
+        // Inscriptions are ignored whether they are cursed or vindicated
+        assert_eq!(
+            violated_rule(RuleSet::at(0), mint, true).map(|rule| rule.reason),
+            Some("inscription is cursed or vindicated")
+        );
+        assert_eq!(
+            violation(RuleSet::at(JUBILEE_HEIGHT), mint, true),
+            Some("inscription_has_no_curses")
+        );
+        assert_eq!(violation(RuleSet::at(JUBILEE_HEIGHT), mint, false), None);
+    }
+
+    #[test]
+    fn test_self_mint_ticks() {
+        let self_mint =
+            r#"{"p":"brc-20","op":"deploy","tick":"abcde","max":"1","lim":"1","self_mint":"true"}"#;
+        let not_self_mint = r#"{"p":"brc-20","op":"deploy","tick":"abcde","max":"1","lim":"1"}"#;
+        let mint = r#"{"p":"brc-20","op":"mint","tick":"ABCDE","amt":"1"}"#;
+        let six_bytes = r#"{"p":"brc-20","op":"mint","tick":"abcdef","amt":"1"}"#;
+        let before = RuleSet::at(SELF_MINT_HEIGHT - 1);
+        let after = RuleSet::at(SELF_MINT_HEIGHT);
+
+        assert_eq!(violation(before, self_mint, false), Some("tick_is_4_bytes"));
+        assert_eq!(violation(before, mint, false), Some("tick_is_4_bytes"));
+        assert_eq!(violation(after, self_mint, false), None);
+        assert_eq!(violation(after, mint, false), None);
+        assert_eq!(
+            violation(after, not_self_mint, false),
+            Some("tick_is_4_bytes_or_self_mint_5_bytes")
+        );
+        assert_eq!(
+            violation(after, six_bytes, false),
+            Some("tick_is_4_bytes_or_self_mint_5_bytes")
+        );
+
+        // Only a boolean string enables self-minting, but other values do not invalidate deploys
+        let boolean =
+            r#"{"p":"brc-20","op":"deploy","tick":"ordi","max":"1","lim":"1","self_mint":true}"#;
+        assert_eq!(violation(after, boolean, false), None);
+    }
+
+    #[test]
+    fn test_mint_is_authorized() {
+        let token = |self_mint| Token {
+            id: "deployi0".into(),
+            self_mint,
+            ..Default::default()
+        };
+        let mint = |parent: &str| Mint {
+            parent: parent.into(),
+            ..Default::default()
+        };
+
+        assert!(mint_is_authorized(&token(false), &mint("")));
+        assert!(mint_is_authorized(&token(true), &mint("deployi0")));
+        assert!(!mint_is_authorized(&token(true), &mint("")));
+        assert!(!mint_is_authorized(&token(true), &mint("otheri0")));
+    }
+
+    #[test]
+    fn test_minted_amount() {
+        let minted = |amount: u64, minted: u64| {
+            minted_amount(
+                &BigInt::from(amount),
+                &BigInt::from(1000),
+                &BigInt::from(1500),
+                &BigInt::from(minted),
+            )
+            .map(|amount| amount.to_u64())
+        };
+
+        assert_eq!(minted(999, 0), Some(999));
+        assert_eq!(minted(1000, 0), Some(1000));
+        assert_eq!(minted(1001, 0), None);
+        assert_eq!(minted(900, 900), Some(600));
+        assert_eq!(minted(600, 900), Some(600));
+        assert_eq!(minted(1, 1500), None);
+    }
+
+    #[test]
+    fn test_transfer_fits_supply() {
+        let fits = |amount: u64, requested: u64| {
+            transfer_fits_supply(
+                &BigInt::from(amount),
+                &BigInt::from(1500),
+                &BigInt::from(requested),
+            )
+        };
+
+        assert!(fits(500, 500));
+        assert!(!fits(501, 500));
+        assert!(fits(1500, 2000));
+        assert!(!fits(1501, 2000));
+        assert!(!fits(1, 0));
+    }
+}
@@ -0,0 +1,783 @@
+use std::collections::{BTreeSet, HashMap};
+
+use substreams::pb::substreams::store_delta::Operation;
+use substreams::pb::substreams::Clock;
+use substreams::store::{DeltaBigInt, DeltaInt64, Deltas};
+use substreams_database_change::pb::database::DatabaseChanges;
+use substreams_database_change::tables::Tables as DatabaseTables;
+use substreams_entity_change::pb::entity::EntityChanges;
+use substreams_entity_change::tables::Tables;
+
+use crate::errors::Result;
+use crate::modules::{split_balance_key, split_token_stat_key};
+use crate::pb::btc::brc20::v1::Brc20Events;
+
+/// Maps the ordinal of each event affecting balances to the cause of the balance change
+fn balance_change_causes(events: &Brc20Events) -> HashMap<u64, &'static str> {
+    events
+        .mints
+        .iter()
+        .map(|mint| (mint.ordinal, "mint"))
+        .chain(
+            events
+                .inscribed_transfers
+                .iter()
+                .map(|transfer| (transfer.ordinal, "inscribe")),
+        )
+        .chain(
+            events
+                .executed_transfers
+                .iter()
+                .map(|transfer| (transfer.ordinal, "execute")),
+        )
+        .chain(
+            events
+                .burned_transfers
+                .iter()
+                .map(|transfer| (transfer.ordinal, "burn")),
+        )
+        .chain(
+            events
+                .returned_transfers
+                .iter()
+                .map(|transfer| (transfer.ordinal, "fee-return")),
+        )
+        .collect()
+}
+
+fn balance_change_cause(causes: &HashMap<u64, &'static str>, delta: &DeltaBigInt) -> &'static str {
+    causes.get(&delta.ordinal).copied().unwrap_or_else(|| {
+        substreams::log::info!("Unknown cause for balance change {}", delta.key);
+        "unknown"
+    })
+}
+
+/// Returns all the accounts participating in the events, in a deterministic order
+fn accounts(events: &Brc20Events) -> BTreeSet<&str> {
+    events
+        .deploys
+        .iter()
+        .map(|deploy| deploy.deployer.as_str())
+        .chain(events.mints.iter().map(|mint| mint.to.as_str()))
+        .chain(
+            events
+                .inscribed_transfers
+                .iter()
+                .chain(events.invalid_transfers.iter())
+                .map(|transfer| transfer.from.as_str()),
+        )
+        .chain(
+            events
+                .executed_transfers
+                .iter()
+                .chain(events.burned_transfers.iter())
+                .chain(events.returned_transfers.iter())
+                .flat_map(|transfer| [transfer.from.as_str(), transfer.to.as_str()]),
+        )
+        // Burned transfers have no receiver
+        .filter(|account| !account.is_empty())
+        .collect()
+}
+
+fn timestamp(clock: &Clock) -> i64 {
+    clock
+        .timestamp
+        .as_ref()
+        .map(|timestamp| timestamp.seconds)
+        .unwrap_or_default()
+}
+
+pub fn graph_out(
+    clock: &Clock,
+    events: &Brc20Events,
+    balances_store: &Deltas<DeltaBigInt>,
+    transferable_balances_store: &Deltas<DeltaBigInt>,
+    token_stats_store: &Deltas<DeltaBigInt>,
+    token_holders_store: &Deltas<DeltaInt64>,
+    token_completion_store: &Deltas<DeltaInt64>,
+) -> Result<EntityChanges> {
+    let mut tables = Tables::new();
+
+    // Every participant of a BRC-20 operation has an `Account`
+    accounts(events).into_iter().for_each(|account| {
+        tables.create_row("Account", account);
+    });
+
+    events.deploys.iter().for_each(|deploy| {
+        tables
+            .create_row("Deploy", deploy.id.clone())
+            .set("token", deploy.symbol.clone())
+            .set("deployer", deploy.deployer.clone())
+            .set("timestamp", timestamp(clock))
+            .set("block", clock.number);
+
+        tables
+            .create_row("Token", deploy.symbol.clone())
+            .set("symbol", deploy.symbol.clone())
+            .set_bigint("max_supply", &deploy.max_supply)
+            .set_bigint("mint_limit", &deploy.mint_limit)
+            .set("decimals", deploy.decimals)
+            .set("deployment", deploy.id.clone())
+            .set_bigint("minted", &"0".into())
+            .set_bigint("burned", &"0".into())
+            .set("holders", 0i64)
+            .set_bigint("mint_count", &"0".into())
+            .set_bigint("transfer_count", &"0".into());
+    });
+
+    events.mints.iter().for_each(|mint| {
+        tables
+            .create_row("Mint", mint.id.clone())
+            .set("token", mint.token.clone())
+            .set("to", mint.to.clone())
+            .set_bigint("amount", &mint.amount)
+            .set("block", clock.number);
+    });
+
+    events
+        .inscribed_transfers
+        .iter()
+        .map(|transfer| ("inscribed", transfer))
+        .chain(
+            events
+                .invalid_transfers
+                .iter()
+                .map(|transfer| ("invalid", transfer)),
+        )
+        .for_each(|(status, transfer)| {
+            tables
+                .create_row("InscribeTransfer", transfer.id.clone())
+                .set("token", transfer.token.clone())
+                .set("from", transfer.from.clone())
+                .set_bigint("amount", &transfer.amount)
+                .set("status", status)
+                .set("utxo", transfer.utxo.clone())
+                .set("block", clock.number);
+        });
+
+    events.executed_transfers.iter().for_each(|transfer| {
+        tables
+            .create_row("Transfer", transfer.id.clone())
+            .set("token", transfer.token.clone())
+            .set("from", transfer.from.clone())
+            .set("to", transfer.to.clone())
+            .set_bigint("amount", &transfer.amount)
+            .set("inscription", transfer.id.clone())
+            .set("txid", transfer.txid.clone())
+            .set("block", clock.number);
+
+        tables
+            .update_row("InscribeTransfer", transfer.id.clone())
+            .set("execution", transfer.id.clone());
+    });
+
+    events
+        .executed_transfers
+        .iter()
+        .map(|transfer| ("executed", transfer))
+        .chain(
+            events
+                .burned_transfers
+                .iter()
+                .map(|transfer| ("burned", transfer)),
+        )
+        .chain(
+            events
+                .returned_transfers
+                .iter()
+                .map(|transfer| ("sent_to_fee", transfer)),
+        )
+        .for_each(|(status, transfer)| {
+            let row = tables
+                .update_row("InscribeTransfer", transfer.id.clone())
+                .set("status", status)
+                .set("execution_tx", transfer.txid.clone());
+
+            // Transfers spent as fee are not held by any outpoint of the spending transaction
+            if !transfer.utxo.is_empty() {
+                row.set("utxo", transfer.utxo.clone());
+            }
+        });
+
+    balances_store.deltas.iter().try_for_each(|delta| {
+        match delta.operation {
+            Operation::Create => {
+                let (token, account) = split_balance_key(&delta.key)?;
+
+                tables
+                    .create_row("AccountBalance", delta.key.clone())
+                    .set("account", account.to_string())
+                    .set("token", token.to_string())
+                    .set_bigint("balance", &delta.new_value.to_string())
+                    .set_bigint("transferable", &"0".into());
+            }
+            Operation::Update => {
+                tables
+                    .update_row("AccountBalance", delta.key.clone())
+                    .set_bigint("balance", &delta.new_value.to_string());
+            }
+            _ => (),
+        }
+        Ok(())
+    })?;
+
+    transferable_balances_store.deltas.iter().for_each(|delta| {
+        // Note: No need to check operation since the AccountBalance row should have been created
+        // when the `balances_store` had a `Create` operation for the same key.
+        // This is because an account can only have a transferable balance if it has a balance
+        // in the first place, which is created when the account is the recipient of either a Mint
+        // or a Transfer event.
+        tables
+            .update_row("AccountBalance", delta.key.clone())
+            .set_bigint("transferable", &delta.new_value.to_string());
+    });
+
+    let causes = balance_change_causes(events);
+    balances_store
+        .deltas
+        .iter()
+        .map(|delta| ("balance", delta))
+        .chain(
+            transferable_balances_store
+                .deltas
+                .iter()
+                .map(|delta| ("transferable", delta)),
+        )
+        .try_for_each(|(kind, delta)| {
+            let (token, account) = split_balance_key(&delta.key)?;
+
+            tables
+                .create_row(
+                    "BalanceChange",
+                    format!("{}:{}:{}:{}", clock.number, delta.ordinal, kind, delta.key),
+                )
+                .set("token", token)
+                .set("account", account)
+                .set("block", clock.number)
+                .set("ordinal", delta.ordinal)
+                .set("cause", balance_change_cause(&causes, delta))
+                .set("kind", kind)
+                .set_bigint(
+                    "amount",
+                    &(delta.new_value.clone() - delta.old_value.clone()).to_string(),
+                )
+                .set_bigint("value", &delta.new_value.to_string());
+            Ok(())
+        })?;
+
+    token_stats_store.deltas.iter().try_for_each(|delta| {
+        let (stat, token) = split_token_stat_key(&delta.key)?;
+
+        tables
+            .update_row("Token", token)
+            .set_bigint(stat, &delta.new_value.to_string());
+        Ok(())
+    })?;
+
+    token_holders_store.deltas.iter().for_each(|delta| {
+        tables
+            .update_row("Token", delta.key.clone())
+            .set("holders", delta.new_value);
+    });
+
+    token_completion_store.deltas.iter().for_each(|delta| {
+        tables
+            .update_row("Token", delta.key.clone())
+            .set("completed_at_block", delta.new_value);
+    });
+
+    Ok(tables.to_entity_changes())
+}
+
+pub fn db_out(
+    clock: &Clock,
+    events: &Brc20Events,
+    balances_store: &Deltas<DeltaBigInt>,
+    transferable_balances_store: &Deltas<DeltaBigInt>,
+    token_stats_store: &Deltas<DeltaBigInt>,
+    token_holders_store: &Deltas<DeltaInt64>,
+    token_completion_store: &Deltas<DeltaInt64>,
+) -> Result<DatabaseChanges> {
+    let mut tables = DatabaseTables::new();
+
+    events.deploys.iter().for_each(|deploy| {
+        tables
+            .create_row("deploys", deploy.id.clone())
+            .set("token", deploy.symbol.clone())
+            .set("deployer", deploy.deployer.clone())
+            .set("block_number", clock.number)
+            .set("timestamp", timestamp(clock));
+    });
+
+    events.mints.iter().for_each(|mint| {
+        tables
+            .create_row("mints", mint.id.clone())
+            .set("token", mint.token.clone())
+            .set("to", mint.to.clone())
+            .set("amount", mint.amount.clone())
+            .set("block_number", clock.number);
+    });
+
+    events.executed_transfers.iter().for_each(|transfer| {
+        tables
+            .create_row("transfers", transfer.id.clone())
+            .set("token", transfer.token.clone())
+            .set("from", transfer.from.clone())
+            .set("to", transfer.to.clone())
+            .set("amount", transfer.amount.clone())
+            .set("txid", transfer.txid.clone())
+            .set("block_number", clock.number);
+    });
+
+    events.deploys.iter().for_each(|deploy| {
+        tables
+            .create_row("tokens", deploy.symbol.clone())
+            .set("symbol", deploy.symbol.clone())
+            .set("max_supply", deploy.max_supply.clone())
+            .set("mint_limit", deploy.mint_limit.clone())
+            .set("decimals", deploy.decimals)
+            .set("deployer", deploy.deployer.clone())
+            .set("deployment", deploy.id.clone())
+            .set("minted", "0")
+            .set("burned", "0")
+            .set("holders", 0i64)
+            .set("mint_count", "0")
+            .set("transfer_count", "0");
+    });
+
+    events
+        .inscribed_transfers
+        .iter()
+        .map(|transfer| ("inscribed", transfer))
+        .chain(
+            events
+                .invalid_transfers
+                .iter()
+                .map(|transfer| ("invalid", transfer)),
+        )
+        .for_each(|(status, transfer)| {
+            tables
+                .create_row("inscribe_transfers", transfer.id.clone())
+                .set("token", transfer.token.clone())
+                .set("from", transfer.from.clone())
+                .set("amount", transfer.amount.clone())
+                .set("status", status)
+                .set("utxo", transfer.utxo.clone())
+                .set("block_number", clock.number);
+        });
+
+    events
+        .executed_transfers
+        .iter()
+        .map(|transfer| ("executed", transfer))
+        .chain(
+            events
+                .burned_transfers
+                .iter()
+                .map(|transfer| ("burned", transfer)),
+        )
+        .chain(
+            events
+                .returned_transfers
+                .iter()
+                .map(|transfer| ("sent_to_fee", transfer)),
+        )
+        .for_each(|(status, transfer)| {
+            let row = tables
+                .update_row("inscribe_transfers", transfer.id.clone())
+                .set("status", status)
+                .set("execution_tx", transfer.txid.clone());
+
+            // Transfers spent as fee are not held by any outpoint of the spending transaction
+            if !transfer.utxo.is_empty() {
+                row.set("utxo", transfer.utxo.clone());
+            }
+
+            // The receiver is only set for executed transfers
+            if status == "executed" {
+                row.set("to", transfer.to.clone());
+            }
+        });
+
+    balances_store.deltas.iter().try_for_each(|delta| {
+        match delta.operation {
+            Operation::Create => {
+                let (token, account) = split_balance_key(&delta.key)?;
+
+                tables
+                    .create_row("account_balances", delta.key.clone())
+                    .set("account", account)
+                    .set("token", token)
+                    .set("balance", delta.new_value.to_string())
+                    .set("transferable", "0");
+            }
+            Operation::Update => {
+                tables
+                    .update_row("account_balances", delta.key.clone())
+                    .set("balance", delta.new_value.to_string());
+            }
+            _ => (),
+        }
+        Ok(())
+    })?;
+
+    transferable_balances_store.deltas.iter().for_each(|delta| {
+        tables
+            .update_row("account_balances", delta.key.clone())
+            .set("transferable", delta.new_value.to_string());
+    });
+
+    let causes = balance_change_causes(events);
+    balances_store
+        .deltas
+        .iter()
+        .map(|delta| ("balance", delta))
+        .chain(
+            transferable_balances_store
+                .deltas
+                .iter()
+                .map(|delta| ("transferable", delta)),
+        )
+        .try_for_each(|(kind, delta)| {
+            let (token, account) = split_balance_key(&delta.key)?;
+
+            tables
+                .create_row(
+                    "balance_changes",
+                    format!("{}:{}:{}:{}", clock.number, delta.ordinal, kind, delta.key),
+                )
+                .set("token", token)
+                .set("account", account)
+                .set("block_number", clock.number)
+                .set("ordinal", delta.ordinal)
+                .set("cause", balance_change_cause(&causes, delta))
+                .set("kind", kind)
+                .set(
+                    "amount",
+                    (delta.new_value.clone() - delta.old_value.clone()).to_string(),
+                )
+                .set("value", delta.new_value.to_string());
+            Ok(())
+        })?;
+
+    token_stats_store.deltas.iter().try_for_each(|delta| {
+        let (stat, token) = split_token_stat_key(&delta.key)?;
+
+        tables
+            .update_row("tokens", token)
+            .set(stat, delta.new_value.to_string());
+        Ok(())
+    })?;
+
+    token_holders_store.deltas.iter().for_each(|delta| {
+        tables
+            .update_row("tokens", delta.key.clone())
+            .set("holders", delta.new_value);
+    });
+
+    token_completion_store.deltas.iter().for_each(|delta| {
+        tables
+            .update_row("tokens", delta.key.clone())
+            .set("completed_at_block", delta.new_value);
+    });
+
+    Ok(tables.to_database_changes())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use prost_types::Timestamp;
+    use substreams::pb::substreams::store_delta::Operation;
+    use substreams::pb::substreams::Clock;
+    use substreams::scalar::BigInt;
+    use substreams::store::{DeltaBigInt, DeltaInt64, Deltas};
+    use substreams_entity_change::pb::entity::{entity_change, value::Typed, EntityChanges};
+
+    use super::*;
+    use crate::pb::btc::brc20::v1::{Deploy, ExecutedTransfer, InscribedTransfer, Mint};
+
+    const SCHEMA: &str = include_str!("../schema.graphql");
+
+    /// Field name and type (e.g.: `BigInt!`) of each entity declared in the subgraph schema.
+    /// Derived fields are omitted since they cannot be set.
+    fn schema_entities() -> HashMap<String, HashMap<String, String>> {
+        let mut entities = HashMap::new();
+        let mut current: Option<(String, HashMap<String, String>)> = None;
+
+        for line in SCHEMA.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+
+            if let Some(declaration) = line.strip_prefix("type ") {
+                let name = declaration.split_whitespace().next().unwrap();
+                current = Some((name.to_string(), HashMap::new()));
+            } else if line == "}" {
+                let (name, fields) = current.take().expect("Unexpected `}` in schema");
+                entities.insert(name, fields);
+            } else if let Some((field, ty)) = line.split_once(':') {
+                if !line.contains("@derivedFrom") {
+                    let (_, fields) = current.as_mut().expect("Field outside of entity");
+                    fields.insert(field.trim().to_string(), ty.trim().to_string());
+                }
+            }
+        }
+
+        entities
+    }
+
+    fn delta(key: &str, ordinal: u64, old_value: i64, new_value: i64) -> DeltaBigInt {
+        DeltaBigInt {
+            operation: if old_value == 0 {
+                Operation::Create
+            } else {
+                Operation::Update
+            },
+            ordinal,
+            key: key.into(),
+            old_value: BigInt::from(old_value),
+            new_value: BigInt::from(new_value),
+        }
+    }
+
+    fn delta_int64(key: &str, old_value: i64, new_value: i64) -> DeltaInt64 {
+        DeltaInt64 {
+            operation: Operation::Update,
+            ordinal: 0,
+            key: key.into(),
+            old_value,
+            new_value,
+        }
+    }
+
+    fn transfer(id: &str, from: &str, to: &str, ordinal: u64) -> ExecutedTransfer {
+        ExecutedTransfer {
+            id: id.into(),
+            token: "ordi".into(),
+            from: from.into(),
+            to: to.into(),
+            amount: "10".into(),
+            ordinal,
+            txid: "spend".into(),
+            utxo: if to.is_empty() {
+                "".into()
+            } else {
+                "spend:0".into()
+            },
+        }
+    }
+
+    fn entity_changes() -> (Clock, EntityChanges) {
+        let clock = Clock {
+            id: "hash".into(),
+            number: 779_832,
+            timestamp: Some(Timestamp {
+                seconds: 1_678_000_000,
+                nanos: 0,
+            }),
+        };
+
+        let inscribed = |id: &str, token: &str| InscribedTransfer {
+            id: id.into(),
+            token: token.into(),
+            from: "alice".into(),
+            amount: "10".into(),
+            utxo: format!("{}:0", id),
+            offset: 0,
+            utxo_amount: 546,
+            ordinal: 3,
+        };
+
+        let events = Brc20Events {
+            deploys: vec![Deploy {
+                id: "deployi0".into(),
+                symbol: "ordi".into(),
+                max_supply: "21000000".into(),
+                mint_limit: "1000".into(),
+                decimals: 18,
+                deployer: "deployer".into(),
+                ..Default::default()
+            }],
+            mints: vec![Mint {
+                id: "minti0".into(),
+                token: "ordi".into(),
+                to: "alice".into(),
+                amount: "1000".into(),
+                ordinal: 2,
+                ..Default::default()
+            }],
+            inscribed_transfers: vec![inscribed("inscribei0", "ordi")],
+            invalid_transfers: vec![inscribed("invalidi0", "none")],
+            executed_transfers: vec![transfer("executedi0", "alice", "bob", 4)],
+            burned_transfers: vec![transfer("burnedi0", "alice", "", 5)],
+            returned_transfers: vec![transfer("returnedi0", "alice", "alice", 6)],
+        };
+
+        let balances = Deltas {
+            deltas: vec![
+                delta("ordi:alice", 2, 0, 1000),
+                delta("ordi:alice", 3, 1000, 990),
+                delta("ordi:bob", 4, 0, 10),
+                delta("ordi:alice", 6, 990, 1000),
+            ],
+        };
+        let transferables = Deltas {
+            deltas: vec![delta("ordi:alice", 3, 0, 10), delta("ordi:alice", 4, 10, 0)],
+        };
+        let stats = Deltas {
+            deltas: vec![
+                delta("minted:ordi", 0, 0, 1000),
+                delta("mint_count:ordi", 0, 0, 1),
+            ],
+        };
+        let holders = Deltas {
+            deltas: vec![delta_int64("ordi", 0, 2)],
+        };
+        let completion = Deltas {
+            deltas: vec![delta_int64("ordi", 0, 779_832)],
+        };
+
+        let changes = graph_out(
+            &clock,
+            &events,
+            &balances,
+            &transferables,
+            &stats,
+            &holders,
+            &completion,
+        )
+        .unwrap();
+
+        (clock, changes)
+    }
+
+    #[test]
+    fn test_graph_out_matches_schema() {
+        let entities = schema_entities();
+        let (_, changes) = entity_changes();
+
+        for change in changes.entity_changes.iter() {
+            let fields = entities
+                .get(&change.entity)
+                .unwrap_or_else(|| panic!("Entity {} is not in the schema", change.entity));
+
+            for field in change.fields.iter() {
+                let ty = fields.get(&field.name).unwrap_or_else(|| {
+                    panic!(
+                        "Field {}.{} is not in the schema",
+                        change.entity, field.name
+                    )
+                });
+
+                let base_ty = ty.trim_end_matches('!');
+                let typed = field
+                    .new_value
+                    .as_ref()
+                    .and_then(|value| value.typed.as_ref())
+                    .expect("Field should have a value");
+
+                let matches = match (base_ty, typed) {
+                    ("BigInt", Typed::Bigint(_)) => true,
+                    ("Int", Typed::Int32(_)) => true,
+                    ("Boolean", Typed::Bool(_)) => true,
+                    ("String" | "ID", Typed::String(_)) => true,
+                    // References to other entities are set using their ID
+                    (entity, Typed::String(_)) => entities.contains_key(entity),
+                    _ => false,
+                };
+                assert!(
+                    matches,
+                    "Field {}.{} of type {} has value {:?}",
+                    change.entity, field.name, ty, typed
+                );
+            }
+
+            // Created entities must set all of their required fields
+            if change.operation == entity_change::Operation::Create as i32 {
+                let set = change
+                    .fields
+                    .iter()
+                    .map(|field| field.name.as_str())
+                    .collect::<HashSet<_>>();
+
+                fields
+                    .iter()
+                    .filter(|(name, ty)| *name != "id" && ty.ends_with('!'))
+                    .for_each(|(name, _)| {
+                        assert!(
+                            set.contains(name.as_str()),
+                            "Field {}.{} is required but not set on creation",
+                            change.entity,
+                            name
+                        )
+                    });
+            }
+        }
+    }
+
+    #[test]
+    fn test_graph_out_creates_accounts_for_all_participants() {
+        let entities = schema_entities();
+        let (_, changes) = entity_changes();
+
+        let accounts = changes
+            .entity_changes
+            .iter()
+            .filter(|change| change.entity == "Account")
+            .map(|change| change.id.as_str())
+            .collect::<HashSet<_>>();
+
+        assert_eq!(accounts, HashSet::from(["deployer", "alice", "bob"]));
+
+        // Every reference to an account must point to an existing account
+        for change in changes.entity_changes.iter() {
+            for field in change.fields.iter() {
+                if entities[&change.entity][&field.name].trim_end_matches('!') != "Account" {
+                    continue;
+                }
+
+                match field
+                    .new_value
+                    .as_ref()
+                    .and_then(|value| value.typed.as_ref())
+                {
+                    Some(Typed::String(account)) => assert!(
+                        accounts.contains(account.as_str()),
+                        "{}.{} references missing account {}",
+                        change.entity,
+                        field.name,
+                        account
+                    ),
+                    value => panic!("Unexpected account reference {:?}", value),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_graph_out_deploy_block_and_timestamp() {
+        let (clock, changes) = entity_changes();
+
+        let deploy = changes
+            .entity_changes
+            .iter()
+            .find(|change| change.entity == "Deploy")
+            .expect("Deploy entity should be created");
+
+        let value = |name: &str| {
+            deploy
+                .fields
+                .iter()
+                .find(|field| field.name == name)
+                .and_then(|field| field.new_value.as_ref())
+                .and_then(|value| value.typed.clone())
+        };
+
+        assert_eq!(deploy.id, "deployi0");
+        assert_eq!(
+            value("block"),
+            Some(Typed::Bigint(clock.number.to_string()))
+        );
+        assert_eq!(value("timestamp"), Some(Typed::Bigint("1678000000".into())));
+    }
+}
@@ -0,0 +1,693 @@
+//! Point-in-time snapshots of the state of a replay (BRC-20 tokens and their stats, balances and
+//! pending transfers, as well as the inscriptions and unspent outputs they depend on), which can
+//! be written to a versioned JSON file and restored into a replay to resume indexing from the
+//! snapshot's height (see the `brc20-reindex` binary) or to compare two runs. Entries are sorted,
+//! so that the same state always gives the same file.
+//!
+//! `store_ordinal_blocks` is only restored for unspent outputs, whose ranges are restored as
+//! absolute ranges of sats.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use substreams::scalar::BigInt;
+use substreams::store::{StoreAdd, StoreSet};
+
+use crate::errors::{Error, Result};
+use crate::inscriptions;
+use crate::modules;
+use crate::pb::btc::brc20::v1::{InscribedTransferLocation, InscribedTransferLocations, Token};
+use crate::pb::ordinals::v1::{InscriptionLocation, OrdinalBlock, OrdinalBlocks};
+use crate::replay::{MemoryStore, Replay};
+
+/// Version of the snapshot format, to be bumped on any change which older versions cannot read
+pub const SNAPSHOT_VERSION: u32 = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    /// Height of the last block included in the snapshot
+    pub height: u64,
+    pub block_hash: String,
+    /// Deployed tokens, by ticker
+    pub tokens: Vec<TokenState>,
+    /// Non-zero balances, by ticker and address
+    pub balances: Vec<BalanceState>,
+    /// Transfers inscribed but not executed yet, by outpoint and in the order of the store
+    pub pending_transfers: Vec<PendingTransferState>,
+    /// Number of blessed and cursed inscriptions, from which the next ones are numbered
+    pub inscription_counts: InscriptionCountsState,
+    /// Inscriptions, by ID
+    pub inscriptions: Vec<InscriptionState>,
+    /// Unspent outputs, by outpoint
+    pub utxos: Vec<UtxoState>,
+    /// Number of sats given to the UTXOs seeded by the replay (see `Replay::set_utxo_values`)
+    pub seeded_sats: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenState {
+    pub tick: String,
+    /// ID of the deploy inscription
+    pub id: String,
+    pub max_supply: String,
+    pub mint_limit: String,
+    pub decimals: i32,
+    pub deployer: String,
+    /// True for self-mint tokens, which only children of the deploy inscription can mint
+    #[serde(default)]
+    pub self_mint: bool,
+    pub minted: String,
+    pub mint_count: u64,
+    pub transfer_count: u64,
+    pub burned: String,
+    pub holders: u64,
+    /// Height at which the minted supply reached the max supply, if it did
+    pub completed_at: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BalanceState {
+    pub tick: String,
+    pub address: String,
+    pub overall: String,
+    pub available: String,
+    pub transferable: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingTransferState {
+    /// Outpoint (`{TXID}:{VOUT}`) holding the transfer inscription
+    pub utxo: String,
+    /// ID of the transfer inscription
+    pub id: String,
+    pub tick: String,
+    pub from: String,
+    pub amount: String,
+    pub offset: u64,
+    pub utxo_amount: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InscriptionCountsState {
+    pub blessed: u64,
+    pub cursed: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InscriptionState {
+    pub id: String,
+    /// Location at which the inscription was revealed, unless its sat was spent as fee
+    pub genesis: Option<LocationState>,
+    /// Current location, whose `utxo` is empty if the inscription's sat was lost
+    pub location: Option<LocationState>,
+    /// Sat on which the inscription was revealed, unless it is unknown
+    pub sat: Option<InscribedSatState>,
+    /// Number of valid children
+    pub children: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InscribedSatState {
+    pub sat: u64,
+    /// Number of inscriptions revealed on the sat before this one
+    pub index: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LocationState {
+    pub utxo: String,
+    pub offset: u64,
+    pub address: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UtxoState {
+    /// Outpoint (`{TXID}:{VOUT}`)
+    pub utxo: String,
+    /// Value in sats
+    pub value: Option<u64>,
+    /// Absolute ranges of the sats held by the output
+    pub sats: Option<Vec<SatRangeState>>,
+    /// Address of the output, if any
+    pub address: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SatRangeState {
+    pub start: u64,
+    pub size: u64,
+}
+
+impl From<InscriptionLocation> for LocationState {
+    fn from(location: InscriptionLocation) -> Self {
+        LocationState {
+            utxo: location.utxo,
+            offset: location.offset,
+            address: location.address,
+        }
+    }
+}
+
+impl From<&LocationState> for InscriptionLocation {
+    fn from(location: &LocationState) -> Self {
+        InscriptionLocation {
+            utxo: location.utxo.clone(),
+            offset: location.offset,
+            address: location.address.clone(),
+        }
+    }
+}
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+fn parse_amount(amount: &str) -> Result<BigInt> {
+    BigInt::from_str(amount).map_err(|err| Error::Amount(format!("{}: {}", amount, err)))
+}
+
+/// Sets the amount in the store unless it is zero, as missing keys are read as zero
+fn restore_amount(store: &MemoryStore<BigInt>, key: String, amount: BigInt) {
+    if !amount.is_zero() {
+        store.set(0, key, &amount);
+    }
+}
+
+impl Snapshot {
+    /// Takes a snapshot of the state of the replay after the given block
+    pub fn export(replay: &Replay, height: u64, block_hash: &str) -> Self {
+        let stats = replay.token_stats.state();
+        let holders = replay.token_holders.state();
+        let completion = replay.token_completion.state();
+        let stat = |name: &str, tick: &str| {
+            stats
+                .get(&format!("{}:{}", name, tick))
+                .cloned()
+                .unwrap_or_default()
+        };
+
+        let tokens = replay
+            .tokens
+            .state()
+            .into_iter()
+            .map(|(tick, token)| TokenState {
+                id: token.id,
+                max_supply: token.max_supply,
+                mint_limit: token.mint_limit,
+                decimals: token.decimals,
+                deployer: token.deployer,
+                self_mint: token.self_mint,
+                minted: stat("minted", &tick).to_string(),
+                mint_count: stat("mint_count", &tick).to_u64(),
+                transfer_count: stat("transfer_count", &tick).to_u64(),
+                burned: stat("burned", &tick).to_string(),
+                holders: holders.get(&tick).copied().unwrap_or_default() as u64,
+                completed_at: completion.get(&tick).map(|height| *height as u64),
+                tick,
+            })
+            .collect();
+
+        // Balances of the three stores, by ticker and address (i.e.: not by store key, as tickers
+        // may contain `:`)
+        let mut balances: BTreeMap<(String, String), [BigInt; 3]> = BTreeMap::new();
+        let stores = [
+            &replay.total_balances,
+            &replay.balances,
+            &replay.transferable_balances,
+        ];
+        for (i, store) in stores.into_iter().enumerate() {
+            for (key, amount) in store.state() {
+                if let Some((tick, address)) = key.rsplit_once(':') {
+                    balances
+                        .entry((tick.into(), address.into()))
+                        .or_insert_with(|| [BigInt::zero(), BigInt::zero(), BigInt::zero()])[i] =
+                        amount;
+                }
+            }
+        }
+        let balances = balances
+            .into_iter()
+            .filter(|(_, amounts)| amounts.iter().any(|amount| !amount.is_zero()))
+            .map(
+                |((tick, address), [overall, available, transferable])| BalanceState {
+                    tick,
+                    address,
+                    overall: overall.to_string(),
+                    available: available.to_string(),
+                    transferable: transferable.to_string(),
+                },
+            )
+            .collect();
+
+        let pending_transfers = replay
+            .inscribed_transfers
+            .state()
+            .into_iter()
+            .flat_map(|(key, locations)| {
+                let utxo = key.trim_end_matches(':').to_string();
+                locations
+                    .locations
+                    .into_iter()
+                    .map(move |location| PendingTransferState {
+                        utxo: utxo.clone(),
+                        id: location.id,
+                        tick: location.token,
+                        from: location.from,
+                        amount: location.amount,
+                        offset: location.offset,
+                        utxo_amount: location.utxo_amount,
+                    })
+            })
+            .collect();
+
+        let counts = replay.inscription_counts.state();
+        let count = |key: &str| counts.get(key).copied().unwrap_or_default() as u64;
+        let inscription_counts = InscriptionCountsState {
+            blessed: count("blessed"),
+            cursed: count("cursed"),
+        };
+
+        // Inscriptions of the three stores, by ID
+        let mut inscriptions: BTreeMap<String, InscriptionState> = BTreeMap::new();
+        fn inscription(
+            inscriptions: &mut BTreeMap<String, InscriptionState>,
+            id: String,
+        ) -> &mut InscriptionState {
+            inscriptions
+                .entry(id.clone())
+                .or_insert_with(|| InscriptionState {
+                    id,
+                    genesis: None,
+                    location: None,
+                    sat: None,
+                    children: 0,
+                })
+        }
+        for (id, location) in replay.inscription_genesis.state() {
+            inscription(&mut inscriptions, id).genesis = Some(location.into());
+        }
+        for (id, location) in replay.inscription_locations.state() {
+            inscription(&mut inscriptions, id).location = Some(location.into());
+        }
+        for (key, id) in replay.sat_inscriptions.state() {
+            if let Some((sat, index)) = key.split_once(':') {
+                if let (Ok(sat), Ok(index)) = (sat.parse(), index.parse()) {
+                    inscription(&mut inscriptions, id).sat = Some(InscribedSatState { sat, index });
+                }
+            }
+        }
+        for (id, children) in replay.inscription_children.state() {
+            inscription(&mut inscriptions, id).children = children as u64;
+        }
+        let inscriptions = inscriptions.into_values().collect();
+
+        // Unspent outputs of the two stores, by outpoint (i.e.: without the `:` which ends
+        // their store keys)
+        let mut utxos: BTreeMap<String, UtxoState> = BTreeMap::new();
+        fn utxo<'a>(utxos: &'a mut BTreeMap<String, UtxoState>, key: &str) -> &'a mut UtxoState {
+            let utxo = key.trim_end_matches(':').to_string();
+            utxos.entry(utxo.clone()).or_insert_with(|| UtxoState {
+                utxo,
+                value: None,
+                sats: None,
+                address: None,
+            })
+        }
+        for (key, value) in replay.utxo_values.state() {
+            utxo(&mut utxos, &key).value = Some(value as u64);
+        }
+        for (key, ranges) in replay.ordinals.state() {
+            let utxo = utxo(&mut utxos, &key);
+            utxo.sats = Some(
+                ranges
+                    .blocks
+                    .into_iter()
+                    .map(|range| SatRangeState {
+                        start: range.start,
+                        size: range.size,
+                    })
+                    .collect(),
+            );
+            utxo.address = ranges.address;
+        }
+        let utxos = utxos.into_values().collect();
+
+        Snapshot {
+            version: SNAPSHOT_VERSION,
+            height,
+            block_hash: block_hash.into(),
+            tokens,
+            balances,
+            pending_transfers,
+            inscription_counts,
+            inscriptions,
+            utxos,
+            seeded_sats: replay.seeded_sats.get(),
+        }
+    }
+
+    /// Restores the state of the snapshot into a replay which has not processed any block
+    pub fn restore(&self, replay: &Replay) -> Result<()> {
+        for token in &self.tokens {
+            let tick = &token.tick;
+            replay.tokens.set(
+                0,
+                tick,
+                &Token {
+                    id: token.id.clone(),
+                    symbol: tick.clone(),
+                    max_supply: token.max_supply.clone(),
+                    mint_limit: token.mint_limit.clone(),
+                    decimals: token.decimals,
+                    deployer: token.deployer.clone(),
+                    self_mint: token.self_mint,
+                },
+            );
+
+            let minted = parse_amount(&token.minted)?;
+            restore_amount(&replay.requested_supply, tick.clone(), minted.clone());
+            let stats = &replay.token_stats;
+            restore_amount(stats, format!("minted:{}", tick), minted);
+            restore_amount(
+                stats,
+                format!("burned:{}", tick),
+                parse_amount(&token.burned)?,
+            );
+            restore_amount(
+                stats,
+                format!("mint_count:{}", tick),
+                BigInt::from(token.mint_count),
+            );
+            restore_amount(
+                stats,
+                format!("transfer_count:{}", tick),
+                BigInt::from(token.transfer_count),
+            );
+
+            if token.holders > 0 {
+                replay.token_holders.set(0, tick, &(token.holders as i64));
+            }
+            if let Some(height) = token.completed_at {
+                replay.token_completion.set(0, tick, &(height as i64));
+            }
+        }
+
+        for balance in &self.balances {
+            let key = || format!("{}:{}", balance.tick, balance.address);
+            let amount = parse_amount;
+            restore_amount(&replay.total_balances, key(), amount(&balance.overall)?);
+            restore_amount(&replay.balances, key(), amount(&balance.available)?);
+            restore_amount(
+                &replay.transferable_balances,
+                key(),
+                amount(&balance.transferable)?,
+            );
+        }
+
+        let mut pending_transfers: BTreeMap<&str, Vec<InscribedTransferLocation>> = BTreeMap::new();
+        for transfer in &self.pending_transfers {
+            pending_transfers
+                .entry(&transfer.utxo)
+                .or_default()
+                .push(InscribedTransferLocation {
+                    id: transfer.id.clone(),
+                    token: transfer.tick.clone(),
+                    from: transfer.from.clone(),
+                    amount: transfer.amount.clone(),
+                    offset: transfer.offset,
+                    utxo_amount: transfer.utxo_amount,
+                });
+        }
+        for (utxo, locations) in pending_transfers {
+            replay.inscribed_transfers.set(
+                0,
+                modules::utxo_key(utxo),
+                &InscribedTransferLocations { locations },
+            );
+        }
+
+        let counts = &self.inscription_counts;
+        for (key, count) in [("blessed", counts.blessed), ("cursed", counts.cursed)] {
+            if count > 0 {
+                replay.inscription_counts.set(0, key, &(count as i64));
+            }
+        }
+
+        for inscription in &self.inscriptions {
+            let id = &inscription.id;
+            if let Some(genesis) = &inscription.genesis {
+                replay.inscription_genesis.set(0, id, &genesis.into());
+            }
+            if let Some(location) = &inscription.location {
+                replay.inscription_locations.set(0, id, &location.into());
+            }
+            if let Some(InscribedSatState { sat, index }) = inscription.sat {
+                inscriptions::inscribed_sat_keys(sat)
+                    .iter()
+                    .for_each(|key| replay.inscribed_sats.add(0, key, 1));
+                replay
+                    .sat_inscriptions
+                    .set(0, modules::sat_inscription_key(sat, index), id);
+            }
+            if inscription.children > 0 {
+                replay
+                    .inscription_children
+                    .set(0, id, &(inscription.children as i64));
+            }
+        }
+
+        for utxo in &self.utxos {
+            let key = modules::utxo_key(&utxo.utxo);
+            if let Some(value) = utxo.value {
+                replay.utxo_values.set(0, &key, &(value as i64));
+            }
+            if let Some(sats) = &utxo.sats {
+                let blocks = sats
+                    .iter()
+                    .map(|range| OrdinalBlock {
+                        utxo: "".into(),
+                        address: None,
+                        start: range.start,
+                        size: range.size,
+                    })
+                    .collect();
+                // Note: Restored as absolute ranges, at which sat range resolution stops
+                let ranges = OrdinalBlocks {
+                    blocks,
+                    address: utxo.address.clone(),
+                };
+                replay.ordinal_blocks.set(0, &key, &ranges);
+                replay.ordinals.set(0, &key, &ranges);
+            }
+        }
+        replay.seeded_sats.set(self.seeded_sats);
+
+        replay.commit();
+        Ok(())
+    }
+
+    /// Reads a snapshot file, checking its version
+    pub fn read(path: &Path) -> io::Result<Self> {
+        #[derive(Deserialize)]
+        struct Versioned {
+            version: u32,
+        }
+
+        let json = fs::read_to_string(path)?;
+        let Versioned { version } =
+            serde_json::from_str(&json).map_err(|err| invalid_data(err.to_string()))?;
+        if version != SNAPSHOT_VERSION {
+            return Err(invalid_data(format!(
+                "snapshot version {} is not supported (expected {})",
+                version, SNAPSHOT_VERSION
+            )));
+        }
+
+        serde_json::from_str(&json).map_err(|err| invalid_data(err.to_string()))
+    }
+
+    /// Writes the snapshot as pretty-printed JSON, one field per line, so that two snapshots can
+    /// be compared with `diff`
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json + "\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::Transaction;
+
+    use super::*;
+    use crate::ord::inscription_id::InscriptionId;
+    use crate::pb::sf::bitcoin::r#type::v1 as btc;
+    use crate::replay::{block_from_json, prevouts_from_json};
+    use crate::rules::SELF_MINT_HEIGHT;
+    use crate::tx_builder::{
+        account, commit_reveal, commit_tx, external_outpoint, outpoint, text, with_parent,
+        TxBuilder, POSTAGE,
+    };
+
+    const FIXTURES: [&str; 3] = [
+        include_str!("../fixtures/blocks/779830.json"),
+        include_str!("../fixtures/blocks/779831.json"),
+        include_str!("../fixtures/blocks/779832.json"),
+    ];
+
+    fn replay(fixtures: &[&str]) -> Replay {
+        let replay = Replay::default();
+        for fixture in fixtures {
+            replay.set_utxo_values(
+                prevouts_from_json(fixture)
+                    .iter()
+                    .map(|(utxo, sats)| (utxo.as_str(), *sats)),
+            );
+            replay.process(block_from_json(fixture));
+        }
+        replay
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let replay = replay(&FIXTURES[..2]);
+        let snapshot = Snapshot::export(&replay, 779_831, "hash");
+
+        assert_eq!(snapshot.tokens.len(), 1);
+        assert_eq!(snapshot.tokens[0].tick, "ordi");
+        assert_eq!(snapshot.tokens[0].minted, "1000");
+        assert_eq!(snapshot.tokens[0].mint_count, 1);
+        assert_eq!(snapshot.tokens[0].holders, 1);
+        assert_eq!(snapshot.balances.len(), 1);
+        assert_eq!(
+            (
+                snapshot.balances[0].overall.as_str(),
+                snapshot.balances[0].available.as_str(),
+                snapshot.balances[0].transferable.as_str()
+            ),
+            ("1000", "600", "400")
+        );
+        assert_eq!(snapshot.pending_transfers.len(), 1);
+        assert_eq!(snapshot.pending_transfers[0].amount, "400");
+
+        let path = std::env::temp_dir().join(format!("brc20-snapshot-{}.json", std::process::id()));
+        snapshot.write(&path).unwrap();
+        let read = Snapshot::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(read, snapshot);
+
+        let restored = Replay::default();
+        read.restore(&restored).unwrap();
+        assert_eq!(Snapshot::export(&restored, 779_831, "hash"), snapshot);
+    }
+
+    #[test]
+    fn test_resume_from_snapshot() {
+        let replay = replay(&FIXTURES);
+
+        // The transfer is executed from the restored state
+        let restored = Replay::default();
+        Snapshot::export(&self::replay(&FIXTURES[..2]), 779_831, "hash")
+            .restore(&restored)
+            .unwrap();
+        restored.set_utxo_values(
+            prevouts_from_json(FIXTURES[2])
+                .iter()
+                .map(|(utxo, sats)| (utxo.as_str(), *sats)),
+        );
+        let outputs = restored.process(block_from_json(FIXTURES[2]));
+
+        assert_eq!(outputs.resolved_events.executed_transfers.len(), 1);
+        assert_eq!(
+            Snapshot::export(&restored, 779_832, "hash"),
+            Snapshot::export(&replay, 779_832, "hash")
+        );
+    }
+
+    /// Block at the given height, made of a coinbase transaction and the transactions
+    fn block(height: i64, txs: Vec<Transaction>) -> btc::Block {
+        let coinbase = TxBuilder::coinbase(height)
+            .output(account(0), 625_000_000)
+            .build();
+        crate::tx_builder::block(height, &[vec![coinbase], txs].concat())
+    }
+
+    #[test]
+    fn test_resume_self_mint() {
+        let height = SELF_MINT_HEIGHT as i64;
+        let funding = [
+            external_outpoint(1).to_string(),
+            external_outpoint(2).to_string(),
+        ];
+        let funding = funding
+            .iter()
+            .map(|utxo| (utxo.as_str(), 10_000))
+            .collect::<Vec<_>>();
+
+        // The deploy inscription is held by the first output of its reveal transaction
+        let (deploy_commit, deploy_reveal) = commit_reveal(
+            external_outpoint(1),
+            &[text(
+                r#"{"p":"brc-20","op":"deploy","tick":"abcde","max":"1000","lim":"100","self_mint":"true"}"#,
+            )],
+            account(1),
+            1_000,
+        );
+        let parent = InscriptionId {
+            txid: deploy_reveal.txid(),
+            index: 0,
+        };
+        let deploy_block = block(height, vec![deploy_commit, deploy_reveal.clone()]);
+
+        // The mint's reveal transaction spends the deploy inscription, which makes it a child
+        let mint = with_parent(
+            text(r#"{"p":"brc-20","op":"mint","tick":"abcde","amt":"60"}"#),
+            parent,
+        );
+        let mint_commit = commit_tx(external_outpoint(2), std::slice::from_ref(&mint), POSTAGE);
+        let mint_reveal = TxBuilder::new()
+            .reveal(outpoint(&mint_commit, 0), &[mint])
+            .input(outpoint(&deploy_reveal, 0))
+            .output(account(1), POSTAGE)
+            .output(account(1), POSTAGE)
+            .build();
+        let mint_block = block(height + 1, vec![mint_commit, mint_reveal]);
+
+        let replay = Replay::default().with_utxo_values(&funding);
+        replay.process(deploy_block.clone());
+        replay.process(mint_block.clone());
+
+        // The parent of the mint is validated from the restored inscriptions and UTXOs, including
+        // the value of the UTXO funding the mint
+        let restored = Replay::default();
+        let previous = Replay::default().with_utxo_values(&funding);
+        previous.process(deploy_block);
+        Snapshot::export(&previous, height as u64, "hash")
+            .restore(&restored)
+            .unwrap();
+        let outputs = restored.process(mint_block);
+
+        assert_eq!(outputs.resolved_events.mints.len(), 1);
+        let snapshot = Snapshot::export(&restored, height as u64 + 1, "hash");
+        assert_eq!(snapshot.tokens[0].minted, "60");
+        assert_eq!(
+            snapshot,
+            Snapshot::export(&replay, height as u64 + 1, "hash")
+        );
+    }
+
+    #[test]
+    fn test_read_unsupported_version() {
+        let path = std::env::temp_dir().join(format!("brc20-version-{}.json", std::process::id()));
+        fs::write(&path, r#"{"version": 99, "height": 0}"#).unwrap();
+        let err = Snapshot::read(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("version 99"));
+    }
+}
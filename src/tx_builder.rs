@@ -0,0 +1,359 @@
+use bitcoin::{
+    absolute::LockTime,
+    hashes::Hash,
+    key::XOnlyPublicKey,
+    opcodes, script,
+    secp256k1::Secp256k1,
+    taproot::{LeafVersion, TaprootBuilder, TaprootSpendInfo},
+    transaction, Amount, OutPoint, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid, Witness,
+};
+
+pub use crate::btc_utils::to_proto;
+use crate::{
+    btc_utils::address_from_scriptpubkey,
+    ord::{inscription::Inscription, inscription_id::InscriptionId},
+    pb::sf::bitcoin::r#type::v1 as btc,
+};
+
+/// Internal key of the commit outputs (the x coordinate of secp256k1's generator)
+const INTERNAL_KEY: [u8; 32] = [
+    0x79, 0xbe, 0x66, 0x7e, 0xf9, 0xdc, 0xbb, 0xac, 0x55, 0xa0, 0x62, 0x95, 0xce, 0x87, 0x0b, 0x07,
+    0x02, 0x9b, 0xfc, 0xdb, 0x2d, 0xce, 0x28, 0xd9, 0x59, 0xf2, 0x81, 0x5b, 0x16, 0xf8, 0x17, 0x98,
+];
+
+/// Value of the outputs holding inscriptions, unless specified otherwise
+pub const POSTAGE: u64 = 546;
+
+// ================================================================
+// Inscriptions
+// ================================================================
+
+/// Inscription with a `text/plain;charset=utf-8` content
+pub fn text(content: &str) -> Inscription {
+    Inscription::new(
+        Some(b"text/plain;charset=utf-8".to_vec()),
+        Some(content.as_bytes().to_vec()),
+    )
+}
+
+pub fn deploy(tick: &str, max: &str, lim: &str) -> Inscription {
+    text(&format!(
+        r#"{{"p":"brc-20","op":"deploy","tick":"{}","max":"{}","lim":"{}"}}"#,
+        tick, max, lim
+    ))
+}
+
+pub fn mint(tick: &str, amt: &str) -> Inscription {
+    text(&format!(
+        r#"{{"p":"brc-20","op":"mint","tick":"{}","amt":"{}"}}"#,
+        tick, amt
+    ))
+}
+
+pub fn transfer(tick: &str, amt: &str) -> Inscription {
+    text(&format!(
+        r#"{{"p":"brc-20","op":"transfer","tick":"{}","amt":"{}"}}"#,
+        tick, amt
+    ))
+}
+
+/// Sets the pointer of the inscription (i.e.: the offset of its sat in the reveal transaction's
+/// outputs)
+pub fn with_pointer(inscription: Inscription, pointer: u64) -> Inscription {
+    Inscription {
+        pointer: Some(Inscription::pointer_value(pointer)),
+        ..inscription
+    }
+}
+
+/// Sets the parent of the inscription, whose inscribed sat must be spent by the reveal
+/// transaction for the link to be valid
+pub fn with_parent(inscription: Inscription, parent: InscriptionId) -> Inscription {
+    Inscription {
+        parent: Some(parent.parent_value()),
+        ..inscription
+    }
+}
+
+// ================================================================
+// Scripts
+// ================================================================
+
+/// P2WPKH script of a test account, whose key hash is `byte` repeated 20 times
+pub fn account(byte: u8) -> ScriptBuf {
+    ScriptBuf::from_bytes([vec![0x00, 0x14], vec![byte; 20]].concat())
+}
+
+/// Mainnet address of the script
+pub fn address(script_pubkey: &ScriptBuf) -> String {
+    address_from_scriptpubkey(&script_pubkey.to_hex_string()).expect("script without address")
+}
+
+/// Tapscript revealing the inscriptions (in a single input, i.e.: a batch reveal)
+pub fn reveal_script(inscriptions: &[Inscription]) -> ScriptBuf {
+    let builder = script::Builder::new()
+        .push_slice(INTERNAL_KEY)
+        .push_opcode(opcodes::all::OP_CHECKSIG);
+
+    match inscriptions {
+        [inscription] => inscription.append_reveal_script(builder),
+        _ => Inscription::append_batch_reveal_script(inscriptions, builder),
+    }
+}
+
+fn spend_info(reveal_script: &ScriptBuf) -> TaprootSpendInfo {
+    let secp = Secp256k1::verification_only();
+    let internal_key = XOnlyPublicKey::from_slice(&INTERNAL_KEY).unwrap();
+
+    TaprootBuilder::new()
+        .add_leaf(0, reveal_script.clone())
+        .unwrap()
+        .finalize(&secp, internal_key)
+        .unwrap()
+}
+
+/// P2TR script of the commit output for the inscriptions
+pub fn commit_script_pubkey(inscriptions: &[Inscription]) -> ScriptBuf {
+    ScriptBuf::new_p2tr_tweaked(spend_info(&reveal_script(inscriptions)).output_key())
+}
+
+// ================================================================
+// Transactions
+// ================================================================
+
+/// Builds `bitcoin::Transaction`s input by input and output by output. Signatures are dummy
+/// values, since they are never verified.
+pub struct TxBuilder {
+    tx: Transaction,
+}
+
+impl Default for TxBuilder {
+    fn default() -> Self {
+        Self {
+            tx: Transaction {
+                version: transaction::Version::TWO,
+                lock_time: LockTime::ZERO,
+                input: vec![],
+                output: vec![],
+            },
+        }
+    }
+}
+
+impl TxBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Coinbase transaction of the block at the given height
+    pub fn coinbase(height: i64) -> Self {
+        let mut builder = Self::new();
+        builder.tx.input.push(TxIn {
+            previous_output: OutPoint::null(),
+            script_sig: script::Builder::new().push_int(height).into_script(),
+            sequence: Sequence::MAX,
+            witness: Witness::new(),
+        });
+        builder
+    }
+
+    /// Adds a key path spend input
+    pub fn input(mut self, previous_output: OutPoint) -> Self {
+        self.tx.input.push(TxIn {
+            previous_output,
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::MAX,
+            witness: Witness::from_slice(&[vec![0x01; 64]]),
+        });
+        self
+    }
+
+    /// Adds an input revealing the inscriptions, spending the output of their commit transaction
+    /// (see `commit_tx`)
+    pub fn reveal(self, commit_output: OutPoint, inscriptions: &[Inscription]) -> Self {
+        self.tapscript(commit_output, reveal_script(inscriptions))
+    }
+
+    /// Adds a script path spend input of `tapscript` (e.g.: a hand-built envelope)
+    pub fn tapscript(mut self, previous_output: OutPoint, tapscript: ScriptBuf) -> Self {
+        let control_block = spend_info(&tapscript)
+            .control_block(&(tapscript.clone(), LeafVersion::TapScript))
+            .unwrap();
+
+        self.tx.input.push(TxIn {
+            previous_output,
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::MAX,
+            witness: Witness::from_slice(&[
+                vec![0x01; 64],
+                tapscript.into_bytes(),
+                control_block.serialize(),
+            ]),
+        });
+        self
+    }
+
+    pub fn output(mut self, script_pubkey: ScriptBuf, sats: u64) -> Self {
+        self.tx.output.push(TxOut {
+            value: Amount::from_sat(sats),
+            script_pubkey,
+        });
+        self
+    }
+
+    /// Adds an `OP_RETURN` output (i.e.: sats sent to it are burned)
+    pub fn op_return(self, sats: u64) -> Self {
+        self.output(ScriptBuf::new_op_return(b"burn"), sats)
+    }
+
+    pub fn build(self) -> Transaction {
+        self.tx
+    }
+}
+
+/// Commit transaction spending `funding`, whose single output of `sats` commits to the
+/// inscriptions' reveal script
+pub fn commit_tx(funding: OutPoint, inscriptions: &[Inscription], sats: u64) -> Transaction {
+    TxBuilder::new()
+        .input(funding)
+        .output(commit_script_pubkey(inscriptions), sats)
+        .build()
+}
+
+/// Commit and reveal transactions of the inscriptions, the reveal transaction sending the
+/// commit output (minus `fee`) to `receiver`
+pub fn commit_reveal(
+    funding: OutPoint,
+    inscriptions: &[Inscription],
+    receiver: ScriptBuf,
+    fee: u64,
+) -> (Transaction, Transaction) {
+    let commit = commit_tx(funding, inscriptions, POSTAGE + fee);
+    let reveal = TxBuilder::new()
+        .reveal(outpoint(&commit, 0), inscriptions)
+        .output(receiver, POSTAGE)
+        .build();
+
+    (commit, reveal)
+}
+
+pub fn outpoint(tx: &Transaction, vout: u32) -> OutPoint {
+    OutPoint::new(tx.txid(), vout)
+}
+
+/// Outpoint of a transaction outside the test's transactions, whose txid is `byte` repeated 32
+/// times (e.g.: UTXOs funding commit transactions)
+pub fn external_outpoint(byte: u8) -> OutPoint {
+    OutPoint::new(Txid::from_byte_array([byte; 32]), 0)
+}
+
+// ================================================================
+// Protobuf
+// ================================================================
+
+/// Block at the given height made of the transactions (the first one being its coinbase
+/// transaction, if any)
+pub fn block(height: i64, txs: &[Transaction]) -> btc::Block {
+    let hash = format!("{:064x}", height);
+
+    btc::Block {
+        hash: hash.clone(),
+        height,
+        n_tx: txs.len() as u32,
+        tx: txs
+            .iter()
+            .map(|tx| btc::Transaction {
+                blockhash: hash.clone(),
+                ..to_proto(tx)
+            })
+            .collect(),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoin::consensus::deserialize;
+
+    use super::*;
+    use crate::btc_utils::parse_envelopes;
+
+    #[test]
+    fn test_commit_reveal() {
+        let inscriptions = [deploy("ordi", "21000", "1000"), mint("ordi", "1000")];
+        let (commit, reveal) =
+            commit_reveal(external_outpoint(0xa0), &inscriptions, account(0xaa), 1_000);
+
+        // The reveal input spends the commit output through the script path
+        let reveal_script = reveal_script(&inscriptions);
+        let control_block = spend_info(&reveal_script)
+            .control_block(&(reveal_script.clone(), LeafVersion::TapScript))
+            .unwrap();
+        let output_key = spend_info(&reveal_script).output_key().to_inner();
+        assert!(control_block.verify_taproot_commitment(
+            &Secp256k1::verification_only(),
+            output_key,
+            &reveal_script
+        ));
+        assert_eq!(
+            commit.output[0].script_pubkey,
+            commit_script_pubkey(&inscriptions)
+        );
+        assert_eq!(reveal.input[0].previous_output, outpoint(&commit, 0));
+
+        let proto = to_proto(&reveal);
+        let envelopes = parse_envelopes(&proto).unwrap();
+        assert_eq!(
+            envelopes
+                .into_iter()
+                .map(|envelope| envelope.payload)
+                .collect::<Vec<_>>(),
+            inscriptions
+        );
+        assert_eq!(proto.txid, reveal.txid().to_string());
+        assert_eq!(proto.vin[0].txid, commit.txid().to_string());
+        assert_eq!(proto.vout[0].value, 0.00000546);
+        assert_eq!(
+            proto.vout[0].script_pub_key.as_ref().unwrap().address,
+            address(&account(0xaa))
+        );
+        assert_eq!(
+            deserialize::<Transaction>(&hex::decode(&proto.hex).unwrap()).unwrap(),
+            reveal
+        );
+    }
+
+    #[test]
+    fn test_block() {
+        let (commit, reveal) = commit_reveal(
+            external_outpoint(0xa0),
+            &[with_pointer(transfer("ordi", "10"), 546)],
+            account(0xaa),
+            0,
+        );
+        let spend = TxBuilder::new()
+            .input(outpoint(&reveal, 0))
+            .input(external_outpoint(0xb0))
+            .op_return(546)
+            .build();
+        let block = block(
+            779_830,
+            &[
+                TxBuilder::coinbase(779_830)
+                    .output(account(0xcc), 625_000_000)
+                    .build(),
+                commit,
+                reveal,
+                spend,
+            ],
+        );
+
+        assert_eq!(block.n_tx, 4);
+        assert!(!block.tx[0].vin[0].coinbase.is_empty());
+        assert!(block.tx[0].vin[0].txinwitness.is_empty());
+        assert!(block.tx[2].may_contain_inscriptions());
+        assert!(!block.tx[3].may_contain_inscriptions());
+        assert!(block.tx[3].vout[0].is_op_return());
+        assert_eq!(block.tx[3].vin[1].txid, "b0".repeat(32));
+    }
+}